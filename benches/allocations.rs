@@ -0,0 +1,79 @@
+//! Counts the heap allocations per converted frame of `get_ppm_from_jpeg` and of a reused `PpmConverter`,
+//! which keeps its buffers between frames. Run with `cargo bench --bench allocations`.
+//!
+//! The remaining allocations of `PpmConverter` are made by the image decoder and resizing,
+//! which allocate the decoded image of each frame.
+mod fixtures;
+
+use fixtures::{jpeg_frame, WALL_SIZES};
+use flaschentaschen_web::{
+    get_ppm_from_jpeg, ConversionOptions, PpmConverter, ScreencastImageFormat,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of frames converted per measurement, the counts are averaged over them.
+const FRAMES: usize = 100;
+
+/// Counts the allocations and allocated bytes of the whole process.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Returns the average number of allocations and allocated bytes of the given conversion of a frame.
+fn count_allocations(mut convert: impl FnMut()) -> (usize, usize) {
+    // the first conversion fills the reused buffers:
+    convert();
+    let (allocations, bytes) = (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    );
+    for _ in 0..FRAMES {
+        convert();
+    }
+    (
+        (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / FRAMES,
+        (ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes) / FRAMES,
+    )
+}
+
+fn main() {
+    println!("allocations per frame (allocated bytes per frame):");
+    for (width, height) in WALL_SIZES {
+        let frame = jpeg_frame(width, height);
+        let fresh = count_allocations(|| {
+            get_ppm_from_jpeg(&frame).unwrap();
+        });
+        let mut converter =
+            PpmConverter::new(ScreencastImageFormat::Jpeg, ConversionOptions::default());
+        let reused = count_allocations(|| {
+            converter.convert(&frame).unwrap();
+        });
+        println!(
+            "{}x{}: get_ppm_from_jpeg {} ({} bytes), PpmConverter {} ({} bytes)",
+            width, height, fresh.0, fresh.1, reused.0, reused.1
+        );
+    }
+}
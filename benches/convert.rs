@@ -0,0 +1,36 @@
+//! Benchmarks the conversion of screencast frames to PPM, which runs for every frame chrome sends.
+//! Run with `cargo bench --bench convert`.
+mod fixtures;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use fixtures::{jpeg_frame, WALL_SIZES};
+use flaschentaschen_web::{
+    get_ppm_from_jpeg, ConversionOptions, PpmConverter, ScreencastImageFormat,
+};
+
+fn convert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("jpeg_to_ppm");
+    for (width, height) in WALL_SIZES {
+        let frame = jpeg_frame(width, height);
+        let size = format!("{}x{}", width, height);
+        group.throughput(Throughput::Elements(u64::from(width * height)));
+
+        group.bench_with_input(
+            BenchmarkId::new("get_ppm_from_jpeg", &size),
+            &frame,
+            |b, frame| b.iter(|| get_ppm_from_jpeg(frame).unwrap()),
+        );
+
+        let mut converter =
+            PpmConverter::new(ScreencastImageFormat::Jpeg, ConversionOptions::default());
+        group.bench_with_input(
+            BenchmarkId::new("PpmConverter", &size),
+            &frame,
+            |b, frame| b.iter(|| converter.convert(frame).unwrap().len()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, convert);
+criterion_main!(benches);
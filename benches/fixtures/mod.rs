@@ -0,0 +1,30 @@
+//! Screencast frames shared by the benchmarks.
+use flaschentaschen_web::pattern::diagonal_gradient;
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, Rgb, RgbImage};
+
+/// Typical LED screen sizes: the default of the flaschentaschen server, a common 64x32 panel
+/// and a wall of 3x4 such panels.
+pub const WALL_SIZES: [(u32, u32); 3] = [(45, 35), (64, 32), (192, 128)];
+
+/// The JPEG quality chrome uses for screencast frames if none is requested.
+const JPEG_QUALITY: u8 = 80;
+
+/// Returns a base64 encoded JPEG of the given dimensions resembling a page on a dark background:
+/// a gradient with colored blocks of "text", which gives the encoder both smooth areas and sharp edges.
+pub fn jpeg_frame(width: u32, height: u32) -> String {
+    let gradient = diagonal_gradient(width, height);
+    let page = RgbImage::from_fn(width, height, |x, y| {
+        if y % 8 < 5 && x % 12 < 9 {
+            Rgb([255, (x * 7 % 256) as u8, (y * 13 % 256) as u8])
+        } else {
+            let Rgb([value, _, _]) = *gradient.get_pixel(x, y);
+            Rgb([value / 4, value / 4, value / 2])
+        }
+    });
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+        .encode(page.as_raw(), width, height, ColorType::Rgb8)
+        .unwrap();
+    base64::encode(jpeg)
+}
@@ -0,0 +1,99 @@
+//! Benchmarks sending a frame to a group of servers sequentially and concurrently, see
+//! `FlaschenTaschenGroup::parallel_sends`. Run with `cargo bench --bench send --features testing`.
+//!
+//! `group_send` measures sending alone: the mock servers listen on the loopback interface, so the results show
+//! the overhead of handing the frame to the sending threads. `group_delivery` measures the time until every server
+//! of a group handled the frame, with servers of varying latency, see `MockFtServer::start_with_latency`.
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use flaschentaschen_web::testing::MockFtServer;
+use flaschentaschen_web::FlaschenTaschenGroup;
+use std::time::Duration;
+
+const SERVER_COUNTS: [usize; 3] = [2, 4, 8];
+
+/// The number of servers of each group of `group_delivery`
+const DELIVERY_SERVER_COUNT: usize = 4;
+
+/// The latency of the slowest server of each group of `group_delivery`, the others are evenly spaced below it
+const MAX_LATENCIES_MS: [u64; 3] = [0, 2, 10];
+
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A binary PPM of a 64x32 screen, a common panel size
+fn wall_ppm() -> Vec<u8> {
+    let mut ppm = b"P6\n64 32\n255\n".to_vec();
+    ppm.resize(ppm.len() + 64 * 32 * 3, 127);
+    ppm
+}
+
+fn group_of(servers: &[MockFtServer]) -> FlaschenTaschenGroup {
+    FlaschenTaschenGroup::new(
+        servers
+            .iter()
+            .map(|server| server.address().to_string())
+            .collect(),
+    )
+    .unwrap()
+}
+
+fn send(c: &mut Criterion) {
+    let ppm = wall_ppm();
+    let mut group = c.benchmark_group("group_send");
+    for server_count in SERVER_COUNTS {
+        let servers: Vec<MockFtServer> = (0..server_count)
+            .map(|_| MockFtServer::start().unwrap())
+            .collect();
+        let mut flaschentaschen = group_of(&servers);
+
+        for parallel_sends in [1, server_count] {
+            flaschentaschen.parallel_sends = parallel_sends;
+            group.bench_with_input(
+                BenchmarkId::new(format!("parallel_sends={}", parallel_sends), server_count),
+                &ppm,
+                |b, ppm| b.iter(|| flaschentaschen.send_ppm(ppm)),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn delivery(c: &mut Criterion) {
+    let ppm = wall_ppm();
+    let mut group = c.benchmark_group("group_delivery");
+    for max_latency_ms in MAX_LATENCIES_MS {
+        let servers: Vec<MockFtServer> = (1..=DELIVERY_SERVER_COUNT)
+            .map(|index| {
+                let latency = Duration::from_millis(max_latency_ms) * index as u32
+                    / DELIVERY_SERVER_COUNT as u32;
+                MockFtServer::start_with_latency(latency).unwrap()
+            })
+            .collect();
+        let mut flaschentaschen = group_of(&servers);
+
+        for parallel_sends in [1, DELIVERY_SERVER_COUNT] {
+            flaschentaschen.parallel_sends = parallel_sends;
+            group.bench_with_input(
+                BenchmarkId::new(
+                    format!("parallel_sends={}", parallel_sends),
+                    format!("max_latency={}ms", max_latency_ms),
+                ),
+                &ppm,
+                |b, ppm| {
+                    b.iter(|| {
+                        for server in servers.iter() {
+                            server.clear_received();
+                        }
+                        flaschentaschen.send_ppm(ppm);
+                        for server in servers.iter() {
+                            server.wait_for_datagrams(1, RECEIVE_TIMEOUT);
+                        }
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, send, delivery);
+criterion_main!(benches);
@@ -0,0 +1,79 @@
+use crate::control::ScreencastControl;
+use crate::{flatten_alpha, get_ppm_from_dynamic_image, ConversionOptions};
+use eyre::Result;
+use image::codecs::gif::GifDecoder;
+use image::{AnimationDecoder, DynamicImage};
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+
+/// The delay used for GIF frames without a meaningful delay.
+/// Browsers treat delays of 10ms and below like this, most GIFs rely on it.
+const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// A converted frame of an animation and how long it is shown
+pub struct AnimationFrame {
+    pub ppm: Vec<u8>,
+    pub delay: Duration,
+}
+
+/// Decodes all frames of the given GIF and converts them to PPM using the given options.
+/// The decoder composites each frame onto the previous ones according to its disposal method,
+/// so every returned frame is a complete image. Transparent pixels are replaced by `opts.pad_color`.
+pub fn decode_gif(bytes: &[u8], opts: &ConversionOptions) -> Result<Vec<AnimationFrame>> {
+    let decoder = GifDecoder::new(Cursor::new(bytes))?;
+    decoder
+        .into_frames()
+        .map(|frame| -> Result<AnimationFrame> {
+            let frame = frame?;
+            let (numerator, denominator) = frame.delay().numer_denom_ms();
+            let delay = match Duration::from_millis(numerator as u64) / denominator.max(1) {
+                delay if delay <= Duration::from_millis(10) => DEFAULT_FRAME_DELAY,
+                delay => delay,
+            };
+            let image = flatten_alpha(frame.buffer(), opts.pad_color);
+            Ok(AnimationFrame {
+                ppm: get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(image), opts)?,
+                delay,
+            })
+        })
+        .collect()
+}
+
+/// Passes the given frames to `on_frame` at the cadence given by their delays, once or forever if `repeat` is true.
+/// If `max_fps` is set, frames are shown at least `1 / max_fps` seconds each.
+/// The playback ends early once the given control is stopped, e.g. by a signal. Returns false if it was stopped.
+pub fn play_animation<'a, F>(
+    frames: &'a [AnimationFrame],
+    repeat: bool,
+    max_fps: Option<u32>,
+    control: &ScreencastControl,
+    mut on_frame: F,
+) -> bool
+where
+    F: FnMut(&'a [u8]),
+{
+    let min_delay = max_fps
+        .filter(|fps| *fps > 0)
+        .map_or(Duration::from_millis(0), |fps| {
+            Duration::from_millis(1000 / fps as u64)
+        });
+
+    // frames are scheduled relative to the start of the playback, so slow sends do not accumulate a drift:
+    let mut next_frame_at = Instant::now();
+    loop {
+        for frame in frames {
+            if control.is_stopped() {
+                return false;
+            }
+            on_frame(frame.ppm.as_slice());
+            next_frame_at += frame.delay.max(min_delay);
+            let now = Instant::now();
+            if next_frame_at > now && !control.sleep(next_frame_at - now) {
+                return false;
+            }
+        }
+        if !repeat || frames.is_empty() {
+            return true;
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::time::{Duration, Instant};
+
+/// Stops screencasting once frames fail consistently, e.g. because the flaschentaschen server is down.
+///
+/// The breaker trips as soon as more than `threshold` consecutive errors have been recorded.
+/// Errors only count as consecutive if no success was recorded in between and each of them
+/// occurred within `window` of the previous one. Sporadic errors spread over a long time
+/// (e.g. on static pages causing chrome to send few frames) therefore do not trip the breaker.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    window: Duration,
+    consecutive_errors: u32,
+    last_error: Option<Instant>,
+    tripped: bool,
+}
+
+impl CircuitBreaker {
+    /// Returns a new breaker tripping after more than `threshold` consecutive errors within the given time window.
+    pub fn new(threshold: u32, window: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            threshold,
+            window,
+            consecutive_errors: 0,
+            last_error: None,
+            tripped: false,
+        }
+    }
+
+    /// Records a successfully handled frame, resetting the consecutive error count.
+    pub fn record_success(&mut self) {
+        self.consecutive_errors = 0;
+        self.last_error = None;
+    }
+
+    /// Records a failed frame and returns true if the breaker tripped because of it.
+    /// Once tripped, the breaker stays tripped and this method returns false for all further errors.
+    pub fn record_error(&mut self) -> bool {
+        let now = Instant::now();
+        match self.last_error {
+            Some(last_error) if now.duration_since(last_error) <= self.window => {
+                self.consecutive_errors += 1
+            }
+            _ => self.consecutive_errors = 1,
+        }
+        self.last_error = Some(now);
+
+        if !self.tripped && self.consecutive_errors > self.threshold {
+            self.tripped = true;
+            return true;
+        }
+        false
+    }
+
+    /// Returns the number of consecutive errors recorded so far.
+    pub fn consecutive_errors(&self) -> u32 {
+        self.consecutive_errors
+    }
+
+    /// Returns true if this breaker has tripped.
+    pub fn is_tripped(&self) -> bool {
+        self.tripped
+    }
+}
@@ -1,25 +1,1338 @@
-use clap::Parser;
+use crate::cookies::Cookie;
+use crate::logging::LogFormat;
+use crate::playlist::{parse_duration, parse_playlist, PlaylistEntry};
+use crate::region::RegionSpec;
+use crate::ScreencastImageFormat;
+use crate::{ChannelOrder, Dimension, Orientation, OutputProtocol, ResizeFilter, ScalingMode};
+use crate::{FtTransport, MAX_DATAGRAM_SIZE};
+use clap::{ArgEnum, Args, Parser, Subcommand};
+use eyre::{eyre, Result, WrapErr};
+use image::Rgb;
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
 
+/// The command line arguments of flaschentaschen-web, a subcommand and the arguments shared by all subcommands.
+/// Use `resolve` to get the final settings.
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 pub struct CliArgs {
-    /// The URL of the website to screencast
-    #[clap(short = 'u', long)]
-    pub url: String,
+    /// What to send, see `Command`. Screencasts the website given by --url, --playlist or --region,
+    /// or sends the image given by --image if not set
+    #[clap(subcommand)]
+    pub command: Option<Command>,
 
-    /// The address of the target flaschentaschen server, e.g. localhost:1337
-    #[clap(short = 'f', long)]
-    pub ft_endpoint: String,
+    #[clap(flatten)]
+    pub shared: SharedArgs,
+}
+
+/// The arguments of flaschentaschen-web shared by all subcommands, given before or after the subcommand,
+/// e.g. `image foo.png --gamma 2`.
+/// All settings can also be provided by a TOML config file (see `--config`) using the long names of the arguments as keys,
+/// e.g. `screen-width = 64`, or by environment variables (`FT_` followed by the long name in upper snake case,
+/// e.g. `FT_MAX_FPS`, except for `FT_URL`, `FT_ENDPOINT`, `FT_WIDTH` and `FT_HEIGHT`).
+/// Arguments given on the command line override environment variables, which override the values of the config file.
+#[derive(Args, Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct SharedArgs {
+    /// Path to a TOML config file providing default values for all other arguments
+    #[clap(long, parse(from_os_str), global = true, env = "FT_CONFIG")]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+
+    /// The URL of the website to screencast.
+    /// The sources --url, --image, --replay and --playlist are also read from `FT_URL`, `FT_IMAGE`, `FT_REPLAY`
+    /// and `FT_PLAYLIST` if none of them is given on the command line
+    #[clap(short = 'u', long, global = true)]
+    pub url: Option<String>,
+
+    /// Path to an image file sent instead of screencasting a website, `-` reads the image from stdin.
+    /// Animated GIFs are played respecting their frame delays and --max-fps
+    #[clap(long, parse(from_os_str), global = true)]
+    pub image: Option<PathBuf>,
+
+    /// Path to a recording of --record sent instead of screencasting a website, at the pace it was recorded.
+    /// This reproduces what the LED screen showed without starting chrome
+    #[clap(long, parse(from_os_str), global = true)]
+    pub replay: Option<PathBuf>,
+
+    /// A comma separated list of websites shown in rotation, each for the given duration,
+    /// e.g. "https://example.com=30s,https://example.org=2m"
+    #[clap(long, global = true)]
+    pub playlist: Option<String>,
+
+    /// A website screencasted into a region of the LED screen, of the form `x,y,width,height=url`
+    /// (can be repeated). All regions are tabs of the same browser, e.g. for a dashboard of multiple pages
+    #[clap(long = "region", multiple_occurrences(true), parse(try_from_str = parse_region), global = true)]
+    #[serde(rename = "region", deserialize_with = "deserialize_regions")]
+    pub regions: Vec<RegionSpec>,
+
+    /// Repeat an animated GIF given by --image forever
+    #[clap(long = "loop", global = true, env = "FT_LOOP")]
+    #[serde(rename = "loop")]
+    pub loop_animation: bool,
+
+    /// The address of the target flaschentaschen server, e.g. localhost:1337, or the path of a Unix domain socket
+    /// of a local bridge prefixed with `unix:`, e.g. unix:/run/ft-bridge.sock. Addresses prefixed with `tcp:`
+    /// are connected over TCP, see --transport. Multiple servers can be given comma-separated or by repeating this option
+    #[clap(
+        short = 'f',
+        long,
+        multiple_occurrences(true),
+        use_delimiter(true),
+        global = true,
+        env = "FT_ENDPOINT"
+    )]
+    pub ft_endpoint: Vec<String>,
+
+    /// How frames are sent to --ft-endpoint addresses without a `unix:` or `tcp:` prefix [default: udp].
+    /// tcp keeps a persistent connection to servers accepting PPMs over TCP: frames are not lost silently
+    /// and sending blocks while a server does not keep up. TCP servers cannot be queried for their size
+    #[clap(long, arg_enum, global = true, env = "FT_TRANSPORT")]
+    pub transport: Option<EndpointTransport>,
+
+    /// The width of the LED screen (in pixels). Queried from the flaschentaschen server if not set
+    #[clap(short = 'w', long, global = true, env = "FT_WIDTH")]
+    pub screen_width: Option<u32>,
+
+    /// The height of the LED screen (in pixels). Queried from the flaschentaschen server if not set
+    #[clap(short = 'h', long, global = true, env = "FT_HEIGHT")]
+    pub screen_height: Option<u32>,
+
+    /// The width of the browser window the page is rendered at (in CSS pixels) before downscaling frames
+    /// to the LED screen, e.g. 1280 to avoid mobile layouts of responsive pages. Applies before --rotate [default: screen width]
+    #[clap(long, global = true, env = "FT_VIEWPORT_WIDTH")]
+    pub viewport_width: Option<u32>,
+
+    /// The height of the browser window the page is rendered at (in CSS pixels), see --viewport-width [default: screen height]
+    #[clap(long, global = true, env = "FT_VIEWPORT_HEIGHT")]
+    pub viewport_height: Option<u32>,
+
+    /// The image format chrome uses for screencast frames. PNG avoids compression artifacts on sharp content [default: jpeg]
+    #[clap(long, arg_enum, global = true, env = "FT_IMAGE_FORMAT")]
+    pub image_format: Option<ScreencastImageFormat>,
+
+    /// The maximum number of frames per second sent to the flaschentaschen server.
+    /// Chrome still captures the dropped frames, see --capture-every-nth to capture fewer frames
+    #[clap(long, global = true, env = "FT_MAX_FPS")]
+    pub max_fps: Option<u32>,
+
+    /// The filter used to resize frames not matching the dimensions of the LED screen
+    /// [default: nearest if the frames are an exact integer multiple of the screen size, e.g. for pixel art, triangle otherwise]
+    #[clap(long, arg_enum, global = true, env = "FT_RESIZE_FILTER")]
+    pub resize_filter: Option<ResizeFilter>,
+
+    /// A custom user agent used by the browser
+    #[clap(long, global = true, env = "FT_USER_AGENT")]
+    pub user_agent: Option<String>,
+
+    /// An additional command line argument passed to chrome, e.g. --chrome-arg=--disable-gpu (can be repeated)
+    #[clap(
+        long = "chrome-arg",
+        multiple_occurrences(true),
+        allow_hyphen_values(true),
+        global = true
+    )]
+    pub chrome_args: Vec<String>,
+
+    /// The remote debugging websocket URL of a running chrome instance to use instead of launching a new one,
+    /// e.g. ws://127.0.0.1:9222/devtools/browser/<id>
+    #[clap(long, global = true, env = "FT_CHROME_WS_URL")]
+    pub chrome_ws_url: Option<String>,
+
+    /// Reconnect to the flaschentaschen server if sending a frame fails, retrying with an exponential backoff
+    /// up to the given maximum (in milliseconds). Frames sent while waiting for the next attempt are dropped
+    #[clap(long, global = true, env = "FT_RECONNECT_MAX_BACKOFF_MS")]
+    pub reconnect_max_backoff_ms: Option<u64>,
+
+    /// The compression quality (0-100) of JPEG screencast frames. Lower values produce smaller frames on slow links [default: 100]
+    #[clap(long, parse(try_from_str = parse_jpeg_quality), global = true, env = "FT_JPEG_QUALITY")]
+    pub jpeg_quality: Option<u32>,
+
+    /// Only screencast the given region of the page: x,y,width,height. Each value is given in pixels
+    /// or as percentage of the frame width or height, e.g. 0%,0%,50%,100% for the left half of the page
+    #[clap(long, parse(try_from_str = parse_crop), global = true, env = "FT_CROP")]
+    pub crop: Option<(Dimension, Dimension, Dimension, Dimension)>,
+
+    /// Only screencast the element matching the given CSS selector, e.g. a single widget of a dashboard.
+    /// Its bounding box is tracked while the page reflows, the full page is sent while it is not on the page
+    #[clap(long, global = true, env = "FT_CAPTURE_SELECTOR")]
+    pub capture_selector: Option<String>,
+
+    /// The gamma correction applied to frames, values > 1 darken the midtones (e.g. 2.2)
+    #[clap(long, global = true, env = "FT_GAMMA")]
+    pub gamma: Option<f32>,
+
+    /// A brightness factor applied to frames after the gamma correction, e.g. 0.5 for half the brightness
+    #[clap(long, global = true, env = "FT_BRIGHTNESS")]
+    pub brightness: Option<f32>,
+
+    /// A contrast factor applied to frames after the brightness, e.g. 1.2 for LED screens looking flat
+    #[clap(long, global = true, env = "FT_CONTRAST")]
+    pub contrast: Option<f32>,
+
+    /// A saturation factor applied to frames after the contrast, 0.0 for grayscale or e.g. 1.3 for more vivid colors
+    #[clap(long, global = true, env = "FT_SATURATION")]
+    pub saturation: Option<f32>,
+
+    /// Capture and send a single frame once the page is loaded, then exit
+    #[clap(long, global = true, env = "FT_ONCE")]
+    pub once: bool,
+
+    /// Wait for an element matching the given CSS selector to appear before starting the screencast
+    #[clap(long = "wait-for", global = true, env = "FT_WAIT_FOR")]
+    #[serde(rename = "wait-for")]
+    pub wait_for_selector: Option<String>,
+
+    /// Wait for the page to finish navigating before starting the screencast
+    #[clap(long, global = true, env = "FT_WAIT_FOR_NAVIGATION")]
+    pub wait_for_navigation: bool,
+
+    /// Path to a CSS file injected into the page, e.g. to hide cookie banners
+    #[clap(long, parse(from_os_str), global = true, env = "FT_INJECT_CSS_FILE")]
+    pub inject_css_file: Option<PathBuf>,
+
+    /// Path to a JavaScript file evaluated on the page once its DOM is loaded
+    #[clap(long, parse(from_os_str), global = true, env = "FT_INJECT_JS_FILE")]
+    pub inject_js_file: Option<PathBuf>,
+
+    /// Log frame statistics (fps, bytes/s, drops, errors) every given number of seconds
+    #[clap(long, global = true, env = "FT_STATS_INTERVAL")]
+    pub stats_interval: Option<u64>,
+
+    /// Dither frames to reduce banding on LED screens with a limited color depth
+    #[clap(long, global = true, env = "FT_DITHER")]
+    pub dither: bool,
+
+    /// The number of values per color channel the LED screen can display, used by --dither [default: 8]
+    #[clap(long, global = true, env = "FT_DITHER_LEVELS")]
+    pub dither_levels: Option<u8>,
+
+    /// Rotate frames clockwise by the given degrees (90, 180 or 270) for LED screens mounted sideways.
+    /// --screen-width/--screen-height always refer to the LED screen itself, the page is rendered at the rotated size
+    #[clap(long, parse(try_from_str = parse_rotation), conflicts_with = "flip", global = true, env = "FT_ROTATE")]
+    #[serde(deserialize_with = "deserialize_rotation")]
+    pub rotate: Option<Orientation>,
+
+    /// Flip frames horizontally or vertically
+    #[clap(
+        long,
+        possible_values = &["horizontal", "vertical"],
+        parse(try_from_str = parse_flip),
+        global = true,
+        env = "FT_FLIP"
+    )]
+    #[serde(deserialize_with = "deserialize_flip")]
+    pub flip: Option<Orientation>,
+
+    /// Stop screencasting after more than this number of consecutive frame errors
+    /// (within --error-window-secs of each other) [default: 100]
+    #[clap(long, global = true, env = "FT_ERROR_THRESHOLD")]
+    pub error_threshold: Option<u32>,
+
+    /// The maximum number of seconds between two frame errors to count them as consecutive [default: 10]
+    #[clap(long, global = true, env = "FT_ERROR_WINDOW_SECS")]
+    pub error_window_secs: Option<u64>,
+
+    /// How frames are scaled to the LED screen if their aspect ratios differ [default: stretch]
+    #[clap(long = "scaling", arg_enum, global = true, env = "FT_SCALING")]
+    #[serde(rename = "scaling")]
+    pub scaling_mode: Option<ScalingMode>,
+
+    /// The color used to pad frames scaled with --scaling=fit, as hex (#rrggbb) or r,g,b [default: #000000]
+    #[clap(long, parse(try_from_str = parse_color), global = true, env = "FT_PAD_COLOR")]
+    #[serde(deserialize_with = "deserialize_color")]
+    pub pad_color: Option<Rgb<u8>>,
+
+    /// The maximum number of frames waiting to be converted and sent. If the queue is full,
+    /// the oldest frame is dropped in favour of the latest one [default: 2]
+    #[clap(long, global = true, env = "FT_FRAME_QUEUE_CAPACITY")]
+    pub frame_queue_capacity: Option<usize>,
+
+    /// The format of the log output, `json` writes one JSON object per line [default: human]
+    #[clap(long, arg_enum, global = true, env = "FT_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+
+    /// The maximum number of seconds to wait for the page to load and render its first frame [default: 30]
+    #[clap(long = "load-timeout", global = true, env = "FT_LOAD_TIMEOUT")]
+    #[serde(rename = "load-timeout")]
+    pub load_timeout_secs: Option<u64>,
+
+    /// An additional HTTP header sent with every request of the page, e.g. "X-Api-Key: secret" (can be repeated)
+    #[clap(long = "header", multiple_occurrences(true), parse(try_from_str = parse_header), global = true)]
+    #[serde(rename = "header", deserialize_with = "deserialize_headers")]
+    pub headers: Vec<(String, String)>,
+
+    /// The credentials used to authenticate at the page using basic auth: user:password
+    #[clap(long, parse(try_from_str = parse_basic_auth), global = true, env = "FT_BASIC_AUTH")]
+    #[serde(deserialize_with = "deserialize_basic_auth")]
+    pub basic_auth: Option<(String, String)>,
+
+    /// A cookie set before navigating to the page, e.g. "session=secret; domain=example.com" (can be repeated).
+    /// Supported attributes: domain, path, secure, httponly and expires (seconds since the UNIX epoch)
+    #[clap(long = "cookie", multiple_occurrences(true), parse(try_from_str = parse_cookie), global = true)]
+    #[serde(rename = "cookie", deserialize_with = "deserialize_cookies")]
+    pub cookies: Vec<Cookie>,
+
+    /// A file containing cookies set before navigating to the page, either a JSON array or a Netscape cookies file
+    #[clap(long, global = true, env = "FT_COOKIES_FILE")]
+    pub cookies_file: Option<PathBuf>,
+
+    /// Check that each converted frame is a complete PPM matching the dimensions of the LED screen
+    #[clap(long, global = true, env = "FT_VALIDATE_FRAMES")]
+    pub validate_frames: bool,
+
+    /// Debugging aid: send frames as human-readable ASCII PPM (P3) instead of binary PPM (P6).
+    /// ASCII frames are several times larger, never use this for real LED screens
+    #[clap(long, global = true, env = "FT_PPM_ASCII")]
+    pub ppm_ascii: bool,
+
+    /// Debugging aid: save converted frames as PNG files to the given directory, see --dump-every
+    #[clap(long, parse(from_os_str), global = true, env = "FT_DUMP_FRAMES")]
+    pub dump_frames: Option<PathBuf>,
+
+    /// Only save every nth converted frame when using --dump-frames [default: 1]
+    #[clap(long, global = true, env = "FT_DUMP_EVERY")]
+    pub dump_every: Option<u64>,
+
+    /// Debugging aid: record the sent frames to the given path, to be replayed with --replay.
+    /// An existing directory receives one PPM file per frame, any other path a single recording file
+    #[clap(long, parse(from_os_str), global = true, env = "FT_RECORD")]
+    pub record: Option<PathBuf>,
+
+    /// The device pixel ratio chrome renders the page at, e.g. 2.0 for sharper text.
+    /// Higher values produce larger frames which are more expensive to process [default: 1.0]
+    #[clap(long, global = true, env = "FT_DEVICE_SCALE_FACTOR")]
+    pub device_scale_factor: Option<f32>,
+
+    /// Check that all flaschentaschen servers are reachable before launching chrome
+    #[clap(long, global = true, env = "FT_VERIFY_SERVER")]
+    pub verify_server: bool,
+
+    /// Only send the regions of a frame which changed since the previous frame, to reduce the bandwidth
+    /// for mostly static pages
+    #[clap(long, global = true, env = "FT_PARTIAL_UPDATE")]
+    pub partial_update: bool,
+
+    /// The size of the square blocks compared by --partial-update, in pixels [default: 8]
+    #[clap(long, global = true, env = "FT_PARTIAL_UPDATE_BLOCK_SIZE")]
+    pub partial_update_block_size: Option<u32>,
+
+    /// The local address frames are sent from, e.g. 192.168.1.10:0 to use a specific interface.
+    /// Defaults to the wildcard address of the address family of each server
+    #[clap(long, global = true, env = "FT_BIND_ADDR")]
+    pub bind_addr: Option<String>,
+
+    /// Reload the page every given number of seconds, e.g. for dashboards which do not update themselves
+    #[clap(long, global = true, env = "FT_RELOAD_INTERVAL")]
+    pub reload_interval: Option<u64>,
+
+    /// The horizontal position of the frames on the LED screen, e.g. to tile a large screen with multiple instances.
+    /// Given in pixels or as percentage of the screen width, e.g. 50% [default: 0]
+    #[clap(long, global = true, env = "FT_OFFSET_X")]
+    pub offset_x: Option<Dimension>,
+
+    /// The vertical position of the frames on the LED screen, in pixels or as percentage of the screen height [default: 0]
+    #[clap(long, global = true, env = "FT_OFFSET_Y")]
+    pub offset_y: Option<Dimension>,
+
+    /// The layer frames are sent to, higher layers are drawn on top of lower ones [default: 0].
+    /// Black pixels are transparent on layers above 0, see --transparent
+    #[clap(long, global = true, env = "FT_LAYER")]
+    pub layer: Option<u8>,
+
+    /// The proxy chrome sends all requests through, e.g. `http://proxy.local:3128`.
+    /// Proxies requiring authentication are not supported
+    #[clap(long, global = true, env = "FT_PROXY")]
+    pub proxy: Option<String>,
+
+    /// A semicolon-separated list of hosts requested without the proxy, e.g. `localhost;*.local`
+    #[clap(long, global = true, env = "FT_PROXY_BYPASS_LIST")]
+    pub proxy_bypass_list: Option<String>,
+
+    /// Restart the screencast if no frame arrived within this number of seconds, e.g. because the page froze.
+    /// Exits with an error if the restarted screencast does not deliver frames either
+    #[clap(long, global = true, env = "FT_FRAME_TIMEOUT")]
+    pub frame_timeout: Option<u64>,
+
+    /// The maximum number of bytes per second sent to all flaschentaschen servers combined.
+    /// Frames exceeding this budget are dropped
+    #[clap(long, global = true, env = "FT_MAX_BYTES_PER_SEC")]
+    pub max_bytes_per_sec: Option<u64>,
+
+    /// Send an all-black frame on shutdown, so the LED screen does not freeze on the last frame
+    #[clap(long, global = true, env = "FT_CLEAR_ON_EXIT")]
+    pub clear_on_exit: bool,
+
+    /// Fade the LED screen to black within this number of milliseconds on shutdown, implies --clear-on-exit
+    #[clap(long, global = true, env = "FT_FADE_ON_EXIT")]
+    pub fade_on_exit: Option<u64>,
+
+    /// Skip sending frames identical to the previously sent one, e.g. for static pages
+    #[clap(long, global = true, env = "FT_SKIP_UNCHANGED")]
+    pub skip_unchanged: bool,
+
+    /// Send identical frames anyway after this number of seconds when using --skip-unchanged,
+    /// so restarted servers do not stay blank. 0 disables keyframes [default: 10]
+    #[clap(long, global = true, env = "FT_KEYFRAME_INTERVAL")]
+    pub keyframe_interval: Option<u64>,
+
+    /// Render the page without a default background and send its transparent pixels black,
+    /// so they are transparent when sent to a layer above 0. Requires --image-format png, the default with this flag
+    #[clap(long, global = true, env = "FT_TRANSPARENT")]
+    pub transparent: bool,
+
+    /// Render and convert frames without sending them, e.g. to test a page without a flaschentaschen server.
+    /// Requires --screen-width and --screen-height
+    #[clap(long, global = true, env = "FT_DRY_RUN")]
+    pub dry_run: bool,
+
+    /// Log the console messages and errors of the page at debug level (-vvv), prefixed with [page]
+    #[clap(long, global = true, env = "FT_LOG_PAGE_CONSOLE")]
+    pub log_page_console: bool,
+
+    /// Serve /healthz and Prometheus /metrics over HTTP on this port
+    #[clap(long, global = true, env = "FT_HEALTH_PORT")]
+    pub health_port: Option<u16>,
+
+    /// The maximum number of seconds since the last sent frame for /healthz to report healthy [default: 60].
+    /// Chrome only sends frames when the page changes, use --frame-timeout to keep static pages healthy
+    #[clap(long, global = true, env = "FT_HEALTH_MAX_FRAME_AGE")]
+    pub health_max_frame_age: Option<u64>,
+
+    /// Send test patterns (solid red, green, blue and white, a gradient and corner markers) before showing
+    /// the website or image, e.g. to verify the wiring and orientation of a new LED screen.
+    /// Only the test patterns are sent if no --url, --image or --playlist is given
+    #[clap(long, global = true, env = "FT_TEST_PATTERN")]
+    pub test_pattern: bool,
+
+    /// The number of milliseconds each test pattern is shown [default: 1000]
+    #[clap(long, global = true, env = "FT_TEST_PATTERN_HOLD")]
+    pub test_pattern_hold: Option<u64>,
+
+    /// Navigate to each URL read from stdin, one per line, without restarting the browser.
+    /// Blank lines are ignored
+    #[clap(long, global = true, env = "FT_CONTROL_STDIN")]
+    pub control_stdin: bool,
+
+    /// Launch chrome without its sandbox, required to run it as root, e.g. in Docker.
+    /// This is a security risk if the screencasted pages are not trusted
+    #[clap(long, global = true, env = "FT_NO_SANDBOX")]
+    pub no_sandbox: bool,
+
+    /// Send the size query to all flaschentaschen servers every this number of seconds and exit like
+    /// --error-threshold once all probed servers stopped answering, see --liveness-failures.
+    /// Sending succeeds even if a server is down, this detects it. Servers not supporting the query are not probed
+    #[clap(long, global = true, env = "FT_LIVENESS_INTERVAL")]
+    pub liveness_interval: Option<u64>,
+
+    /// The number of consecutive unanswered liveness probes after which a server is considered dead [default: 3]
+    #[clap(long, global = true, env = "FT_LIVENESS_FAILURES")]
+    pub liveness_failures: Option<u32>,
+
+    /// The encoding of the sent frames: binary PPM for flaschentaschen servers, or raw-rgb for LED controllers
+    /// expecting only the RGB bytes of the pixels (width * height * 3 bytes) without a header [default: ppm]
+    #[clap(long, arg_enum, global = true, env = "FT_OUTPUT_PROTOCOL")]
+    pub output_protocol: Option<OutputProtocol>,
+
+    /// Shut down gracefully after screencasting for this duration, e.g. `30m`, `2h` or `1h 30m`, so a wrapper can restart
+    /// the process. Clears the screen like on a signal if --clear-on-exit is given
+    #[clap(long, global = true, env = "FT_MAX_RUNTIME")]
+    pub max_runtime: Option<String>,
 
-    /// The width of the LED screen (in pixels)
-    #[clap(short = 'w', long)]
-    pub screen_width: u32,
+    /// Write the frame captured by --once to stdout as binary PPM instead of sending it, e.g. to pipe it into other tools.
+    /// Logs are always written to stderr
+    #[clap(long, global = true, env = "FT_STDOUT_PPM")]
+    pub stdout_ppm: bool,
 
-    /// The height of the LED screen (in pixels)
-    #[clap(short = 'h', long)]
-    pub screen_height: u32,
+    /// Pad every datagram with zeros to this number of bytes, for receivers expecting datagrams of a fixed size.
+    /// Flaschentaschen servers ignore the bytes after a complete PPM. At most 65507 bytes, the UDP limit.
+    /// Frames sent over TCP are not padded
+    #[clap(long, global = true, env = "FT_PAD_DATAGRAM")]
+    pub pad_datagram: Option<usize>,
+
+    /// Pause the screencast while an element matching the given CSS selector is on the page, e.g. a maintenance banner,
+    /// and resume it once the element disappeared. The LED screen keeps showing the last frame while paused
+    #[clap(long = "pause-on", global = true, env = "FT_PAUSE_ON")]
+    #[serde(rename = "pause-on")]
+    pub pause_on_selector: Option<String>,
+
+    /// Let chrome capture only every nth frame of the page [default: 1]. Frames above --max-fps are dropped after
+    /// chrome captured them, frames skipped by this option are never captured, which saves CPU in the browser.
+    /// Both can be combined, e.g. 2 halves the frame rate of chrome before --max-fps limits it further
+    #[clap(
+        long = "capture-every-nth",
+        global = true,
+        env = "FT_CAPTURE_EVERY_NTH"
+    )]
+    #[serde(rename = "capture-every-nth")]
+    pub capture_every_nth_frame: Option<u32>,
+
+    /// Only start the screencast once the given JavaScript expression evaluates to a truthy value on the page,
+    /// e.g. 'window.chartData !== undefined'. The expression is polled for up to 30s and checked again after
+    /// each reload of --reload-interval or SIGHUP, the LED screen keeps showing the last frame meanwhile
+    #[clap(long = "ready-expr", global = true, env = "FT_READY_EXPR")]
+    #[serde(rename = "ready-expr")]
+    pub ready_expression: Option<String>,
+
+    /// The order of the color channels of each pixel expected by the LED screen,
+    /// e.g. bgr for controllers showing red and blue swapped [default: rgb]
+    #[clap(long, arg_enum, global = true, env = "FT_CHANNEL_ORDER")]
+    pub channel_order: Option<ChannelOrder>,
+
+    /// Send each frame to up to this number of --ft-endpoint servers concurrently [default: 1].
+    /// Sending concurrently bounds the time to send a frame by the slowest server instead of the sum of all servers
+    #[clap(long, global = true, env = "FT_PARALLEL_SENDS")]
+    pub parallel_sends: Option<usize>,
+
+    /// Remap frames for LED modules wired in a serpentine layout, where every other pixel row of a module
+    /// runs from right to left. The module size is set by --module-width and --module-height
+    #[clap(long, global = true, env = "FT_SERPENTINE")]
+    pub serpentine: bool,
+
+    /// Send exactly this number of frames per second at an even interval, repeating the last frame
+    /// if chrome sent no new one in the meantime. Unlike --max-fps, the LED screen refreshes at a steady rate
+    /// regardless of when chrome emits frames. Cannot be combined with --partial-update or --skip-unchanged
+    #[clap(long, global = true, env = "FT_FIXED_FPS")]
+    pub fixed_fps: Option<u32>,
+
+    /// The width of a single LED module in pixels for --serpentine [default: the screen width]
+    #[clap(long, global = true, env = "FT_MODULE_WIDTH")]
+    pub module_width: Option<u32>,
+
+    /// The height of a single LED module in pixels for --serpentine [default: the screen height]
+    #[clap(long, global = true, env = "FT_MODULE_HEIGHT")]
+    pub module_height: Option<u32>,
+
+    /// Adapt the JPEG quality to the frames dropped because they could not be handled or sent fast enough,
+    /// e.g. because of --max-bytes-per-sec: lower it while many frames are dropped,
+    /// and slowly raise it again while hardly any are.
+    /// Requires JPEG frames
+    #[clap(long, global = true, env = "FT_ADAPTIVE_QUALITY")]
+    pub adaptive_quality: bool,
+
+    /// The lowest JPEG quality (0-100) of --adaptive-quality [default: 30, or the max if lower]
+    #[clap(long, parse(try_from_str = parse_jpeg_quality), global = true, env = "FT_MIN_JPEG_QUALITY")]
+    pub min_jpeg_quality: Option<u32>,
+
+    /// The highest JPEG quality (0-100) of --adaptive-quality, the quality it starts at [default: --jpeg-quality]
+    #[clap(long, parse(try_from_str = parse_jpeg_quality), global = true, env = "FT_MAX_JPEG_QUALITY")]
+    pub max_jpeg_quality: Option<u32>,
+
+    /// Fail screencast frames whose size does not match the LED screen exactly instead of resizing them, e.g. when
+    /// the viewport was matched to the screen for pixel-perfect pages. Like send errors, failed frames count towards
+    /// --error-threshold. Images, GIFs and test patterns of other sizes are still resized
+    #[clap(long, global = true, env = "FT_STRICT_DIMENSIONS")]
+    pub strict_dimensions: bool,
+
+    /// Only log errors, overriding --verbosity and the verbosity of the config file
+    #[clap(
+        short = 'q',
+        long,
+        conflicts_with = "verbosity",
+        global = true,
+        env = "FT_QUIET"
+    )]
+    pub quiet: bool,
 
     /// Set the level of verbosity (add multiple to increase level, e.g. -vvv)
-    #[clap(short = 'v', long, parse(from_occurrences))]
+    #[clap(short = 'v', long, parse(from_occurrences), global = true)]
+    pub verbosity: u64,
+}
+
+/// The subcommands of flaschentaschen-web. All other arguments apply to them like without a subcommand,
+/// e.g. --gamma is applied to the sent image too.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Screencast the website given by --url, --playlist or --region
+    Screencast,
+    /// Send an image file instead of screencasting a website, like --image.
+    /// Overrides the website of the environment variables and the config file
+    Image {
+        /// Path to the image file, `-` reads the image from stdin
+        #[clap(parse(from_os_str))]
+        path: PathBuf,
+    },
+    /// Only send the test patterns of --test-pattern, e.g. to verify the wiring of a new LED screen
+    TestPattern,
+    /// Print the size of the LED screen reported by each flaschentaschen server (`<width>x<height>`, one line per server)
+    /// and exit. The stock flaschentaschen server does not report its size
+    QuerySize,
+}
+
+/// Where the frames sent to the flaschentaschen servers come from
+#[derive(Debug)]
+pub enum FrameSource {
+    /// Screencasts the website at the given URL
+    Url(String),
+    /// Sends the image file at the given path, `-` for stdin
+    Image(PathBuf),
+    /// Screencasts the websites of the given playlist in rotation
+    Playlist(Vec<PlaylistEntry>),
+    /// Screencasts each website in its own region of the LED screen
+    Regions(Vec<RegionSpec>),
+    /// Sends the frames of the recording at the given path, see `record::Recording`
+    Replay(PathBuf),
+    /// Sends only the test patterns, see `--test-pattern`
+    TestPattern,
+    /// Sends nothing, only prints the size of the LED screen, see `Command::QuerySize`
+    QuerySize,
+}
+
+/// The transport of --ft-endpoint addresses without a scheme, see `FtTransport`
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum EndpointTransport {
+    Udp,
+    Tcp,
+}
+
+/// The final settings of flaschentaschen-web, merged from the command line arguments, the config file and the defaults.
+#[derive(Debug)]
+pub struct ResolvedArgs {
+    pub source: FrameSource,
+    pub loop_animation: bool,
+    pub partial_update: bool,
+    pub partial_update_block_size: u32,
+    pub bind_addr: Option<String>,
+    pub ft_endpoint: Vec<String>,
+    /// `None` if the width should be queried from the flaschentaschen server
+    pub screen_width: Option<u32>,
+    /// `None` if the height should be queried from the flaschentaschen server
+    pub screen_height: Option<u32>,
+    pub viewport_width: Option<u32>,
+    pub viewport_height: Option<u32>,
+    pub image_format: ScreencastImageFormat,
+    pub max_fps: Option<u32>,
+    /// `None` if the filter should be chosen depending on the frame dimensions
+    pub resize_filter: Option<ResizeFilter>,
+    pub user_agent: Option<String>,
+    pub chrome_args: Vec<OsString>,
+    pub chrome_ws_url: Option<String>,
+    pub reconnect_max_backoff_ms: Option<u64>,
+    pub jpeg_quality: u32,
+    pub crop: Option<(Dimension, Dimension, Dimension, Dimension)>,
+    pub capture_selector: Option<String>,
+    pub gamma: Option<f32>,
+    pub brightness: Option<f32>,
+    pub contrast: Option<f32>,
+    pub saturation: Option<f32>,
+    pub once: bool,
+    pub wait_for_selector: Option<String>,
+    pub wait_for_navigation: bool,
+    pub inject_css_file: Option<PathBuf>,
+    pub inject_js_file: Option<PathBuf>,
+    pub stats_interval: Option<u64>,
+    pub dither: bool,
+    pub dither_levels: u8,
+    pub orientation: Orientation,
+    pub error_threshold: u32,
+    pub error_window_secs: u64,
+    pub scaling_mode: ScalingMode,
+    pub pad_color: Rgb<u8>,
+    pub frame_queue_capacity: usize,
+    pub log_format: LogFormat,
+    pub load_timeout_secs: u64,
+    pub headers: Vec<(String, String)>,
+    pub basic_auth: Option<(String, String)>,
+    pub validate_frames: bool,
+    pub ppm_ascii: bool,
+    pub dump_frames: Option<PathBuf>,
+    pub dump_every: u64,
+    pub record: Option<PathBuf>,
+    pub device_scale_factor: f32,
+    pub verify_server: bool,
+    pub reload_interval: Option<u64>,
+    pub offset_x: Dimension,
+    pub offset_y: Dimension,
+    pub layer: u8,
+    pub proxy: Option<String>,
+    pub proxy_bypass_list: Option<String>,
+    pub frame_timeout: Option<u64>,
+    pub max_bytes_per_sec: Option<u64>,
+    pub clear_on_exit: bool,
+    pub fade_on_exit: Option<u64>,
+    pub skip_unchanged: bool,
+    pub keyframe_interval: u64,
+    pub transparent: bool,
+    pub dry_run: bool,
+    pub log_page_console: bool,
+    pub cookies: Vec<Cookie>,
+    pub cookies_file: Option<PathBuf>,
+    pub health_port: Option<u16>,
+    pub health_max_frame_age: u64,
+    pub test_pattern: bool,
+    pub test_pattern_hold: u64,
+    pub control_stdin: bool,
+    pub no_sandbox: bool,
+    pub liveness_interval: Option<u64>,
+    pub liveness_failures: u32,
+    pub output_protocol: OutputProtocol,
+    pub max_runtime: Option<Duration>,
+    pub stdout_ppm: bool,
+    pub pad_datagram: Option<usize>,
+    pub pause_on_selector: Option<String>,
+    pub capture_every_nth_frame: u32,
+    pub ready_expression: Option<String>,
+    pub channel_order: ChannelOrder,
+    pub parallel_sends: usize,
+    pub serpentine: bool,
+    pub fixed_fps: Option<u32>,
+    pub module_width: Option<u32>,
+    pub module_height: Option<u32>,
+    /// The bounds (min, max) of --adaptive-quality, if enabled
+    pub adaptive_quality: Option<(u32, u32)>,
+    pub strict_dimensions: bool,
     pub verbosity: u64,
 }
+
+impl CliArgs {
+    /// Returns the final settings by merging these arguments with the config file given by `--config` (if any)
+    /// and the default values. Arguments given on the command line take precedence over the config file.
+    pub fn resolve(self) -> Result<ResolvedArgs> {
+        let CliArgs {
+            command,
+            shared: cli,
+        } = self;
+        let file = match &cli.config {
+            Some(path) => {
+                let content = fs::read_to_string(path)
+                    .wrap_err_with(|| format!("failed to read config file {}", path.display()))?;
+                toml::from_str::<SharedArgs>(&content)
+                    .wrap_err_with(|| format!("invalid config file {}", path.display()))?
+            }
+            None => SharedArgs::default(),
+        };
+
+        let orientation = match (cli.rotate.or(file.rotate), cli.flip.or(file.flip)) {
+            (Some(_), Some(_)) => return Err(eyre!("--rotate and --flip cannot be combined")),
+            (Some(orientation), None) | (None, Some(orientation)) => orientation,
+            (None, None) => Orientation::None,
+        };
+
+        let jpeg_quality = cli.jpeg_quality.or(file.jpeg_quality).unwrap_or(100);
+        if jpeg_quality > 100 {
+            return Err(eyre!(
+                "jpeg-quality {} is out of range, expected 0-100",
+                jpeg_quality
+            ));
+        }
+
+        let device_scale_factor = cli
+            .device_scale_factor
+            .or(file.device_scale_factor)
+            .unwrap_or(1.0);
+        if device_scale_factor <= 0.0 {
+            return Err(eyre!(
+                "device-scale-factor {} is invalid, expected a positive number",
+                device_scale_factor
+            ));
+        }
+
+        let contrast = cli.contrast.or(file.contrast);
+        let saturation = cli.saturation.or(file.saturation);
+        for (name, factor) in [("contrast", contrast), ("saturation", saturation)] {
+            if let Some(factor) = factor.filter(|factor| *factor < 0.0) {
+                return Err(eyre!(
+                    "{} {} is invalid, expected a non-negative number",
+                    name,
+                    factor
+                ));
+            }
+        }
+
+        let stdout_ppm = cli.stdout_ppm || file.stdout_ppm;
+        let once = cli.once || file.once;
+        if stdout_ppm && !once {
+            return Err(eyre!("--stdout-ppm requires --once"));
+        }
+
+        let pad_datagram = cli.pad_datagram.or(file.pad_datagram);
+        if let Some(size) = pad_datagram.filter(|size| *size > MAX_DATAGRAM_SIZE) {
+            return Err(eyre!(
+                "pad-datagram {} exceeds the maximum UDP datagram size of {} bytes",
+                size,
+                MAX_DATAGRAM_SIZE
+            ));
+        }
+
+        let capture_every_nth_frame = cli
+            .capture_every_nth_frame
+            .or(file.capture_every_nth_frame)
+            .unwrap_or(1);
+        if capture_every_nth_frame == 0 {
+            return Err(eyre!("capture-every-nth must be at least 1"));
+        }
+
+        let parallel_sends = cli.parallel_sends.or(file.parallel_sends).unwrap_or(1);
+        if parallel_sends == 0 {
+            return Err(eyre!("parallel-sends must be at least 1"));
+        }
+
+        let crop = cli.crop.or(file.crop);
+        let capture_selector = cli.capture_selector.or(file.capture_selector);
+        // frames captured with --once are converted before the bounds of the element are known:
+        if capture_selector.is_some() && (crop.is_some() || once) {
+            return Err(eyre!(
+                "--capture-selector cannot be combined with --crop or --once"
+            ));
+        }
+
+        let fixed_fps = cli.fixed_fps.or(file.fixed_fps);
+        if fixed_fps == Some(0) {
+            return Err(eyre!("fixed-fps must be at least 1"));
+        }
+        // both rely on the previous frame being on the LED screen, which a paced frame replaces at any time:
+        if fixed_fps.is_some()
+            && (cli.partial_update
+                || file.partial_update
+                || cli.skip_unchanged
+                || file.skip_unchanged)
+        {
+            return Err(eyre!(
+                "--fixed-fps cannot be combined with --partial-update or --skip-unchanged"
+            ));
+        }
+
+        let serpentine = cli.serpentine || file.serpentine;
+        let module_width = cli.module_width.or(file.module_width);
+        let module_height = cli.module_height.or(file.module_height);
+        if !serpentine && (module_width.is_some() || module_height.is_some()) {
+            return Err(eyre!(
+                "--module-width and --module-height require --serpentine"
+            ));
+        }
+        if module_width == Some(0) || module_height == Some(0) {
+            return Err(eyre!("module-width and module-height must be at least 1"));
+        }
+
+        let test_pattern =
+            cli.test_pattern || file.test_pattern || matches!(command, Some(Command::TestPattern));
+        let cli_sources = FrameSources {
+            url: cli.url,
+            image: cli.image,
+            playlist: cli.playlist,
+            regions: cli.regions,
+            replay: cli.replay,
+        };
+        // the subcommands give their own source, which would silently replace the one given next to them:
+        let has_own_source = matches!(
+            command,
+            Some(Command::Image { .. } | Command::TestPattern | Command::QuerySize)
+        );
+        if has_own_source && !cli_sources.is_empty() {
+            return Err(eyre!(
+                "the image, test-pattern and query-size subcommands cannot be combined with --url, --image, --playlist, --region or --replay"
+            ));
+        }
+        let sources = cli_sources.or(FrameSources::from_env()).or(FrameSources {
+            url: file.url,
+            image: file.image,
+            playlist: file.playlist,
+            regions: file.regions,
+            replay: file.replay,
+        });
+        let source = match command {
+            Some(Command::Image { path }) => FrameSource::Image(path),
+            Some(Command::TestPattern) => FrameSource::TestPattern,
+            Some(Command::QuerySize) => FrameSource::QuerySize,
+            command => match sources.into_tuple() {
+                (Some(url), None, None, None, None) => FrameSource::Url(url),
+                (None, Some(_), None, None, None) | (None, None, None, None, Some(_))
+                    if command.is_some() =>
+                {
+                    return Err(eyre!(
+                        "the screencast subcommand requires --url, --playlist or --region, use the image subcommand to send an image"
+                    ))
+                }
+                (None, Some(path), None, None, None) => FrameSource::Image(path),
+                (None, None, Some(playlist), None, None) => FrameSource::Playlist(
+                    parse_playlist(&playlist).wrap_err("invalid --playlist")?,
+                ),
+                (None, None, None, Some(regions), None) => FrameSource::Regions(regions),
+                (None, None, None, None, Some(path)) => FrameSource::Replay(path),
+                (None, None, None, None, None) if test_pattern && command.is_none() => {
+                    FrameSource::TestPattern
+                }
+                (None, None, None, None, None) => {
+                    return Err(eyre!(
+                        "missing required argument --url, --image, --playlist, --region or --replay, set it on the command line, by environment variable or in the config file"
+                    ))
+                }
+                _ => {
+                    return Err(eyre!(
+                        "only one of --url, --image, --playlist, --region and --replay can be given"
+                    ))
+                }
+            },
+        };
+
+        // JPEG has no alpha channel, transparent pages need PNG frames:
+        let transparent = cli.transparent || file.transparent;
+        let image_format = match cli.image_format.or(file.image_format) {
+            Some(ScreencastImageFormat::Jpeg) if transparent => {
+                return Err(eyre!("--transparent requires --image-format png"))
+            }
+            Some(image_format) => image_format,
+            None if transparent => ScreencastImageFormat::Png,
+            None => ScreencastImageFormat::Jpeg,
+        };
+        let adaptive_quality = cli.adaptive_quality || file.adaptive_quality;
+        let min_jpeg_quality = cli.min_jpeg_quality.or(file.min_jpeg_quality);
+        let max_jpeg_quality = cli.max_jpeg_quality.or(file.max_jpeg_quality);
+        if !adaptive_quality && (min_jpeg_quality.is_some() || max_jpeg_quality.is_some()) {
+            return Err(eyre!(
+                "--min-jpeg-quality and --max-jpeg-quality require --adaptive-quality"
+            ));
+        }
+        if adaptive_quality && image_format != ScreencastImageFormat::Jpeg {
+            return Err(eyre!("--adaptive-quality requires --image-format jpeg"));
+        }
+        let adaptive_quality = if adaptive_quality {
+            let max_jpeg_quality = max_jpeg_quality.unwrap_or(jpeg_quality);
+            let min_jpeg_quality = min_jpeg_quality.unwrap_or_else(|| 30.min(max_jpeg_quality));
+            if min_jpeg_quality > max_jpeg_quality {
+                return Err(eyre!(
+                    "--min-jpeg-quality {} is above the max quality {}",
+                    min_jpeg_quality,
+                    max_jpeg_quality
+                ));
+            }
+            Some((min_jpeg_quality, max_jpeg_quality))
+        } else {
+            None
+        };
+
+        let transport = cli
+            .transport
+            .or(file.transport)
+            .unwrap_or(EndpointTransport::Udp);
+
+        Ok(ResolvedArgs {
+            source,
+            ft_endpoint: match merge_vec(cli.ft_endpoint, file.ft_endpoint) {
+                endpoints if endpoints.is_empty() => return Err(missing_argument("ft-endpoint")),
+                endpoints => endpoints
+                    .into_iter()
+                    .map(|endpoint| match (transport, FtTransport::of(&endpoint).0) {
+                        (EndpointTransport::Tcp, FtTransport::Udp) => {
+                            format!("{}{}", FtTransport::TCP_SCHEME, endpoint)
+                        }
+                        _ => endpoint,
+                    })
+                    .collect(),
+            },
+            screen_width: cli.screen_width.or(file.screen_width),
+            screen_height: cli.screen_height.or(file.screen_height),
+            viewport_width: cli.viewport_width.or(file.viewport_width),
+            viewport_height: cli.viewport_height.or(file.viewport_height),
+            image_format,
+            max_fps: cli.max_fps.or(file.max_fps),
+            resize_filter: cli.resize_filter.or(file.resize_filter),
+            user_agent: cli.user_agent.or(file.user_agent),
+            chrome_args: merge_vec(cli.chrome_args, file.chrome_args)
+                .into_iter()
+                .map(OsString::from)
+                .collect(),
+            chrome_ws_url: cli.chrome_ws_url.or(file.chrome_ws_url),
+            reconnect_max_backoff_ms: cli
+                .reconnect_max_backoff_ms
+                .or(file.reconnect_max_backoff_ms),
+            jpeg_quality,
+            crop,
+            capture_selector,
+            gamma: cli.gamma.or(file.gamma),
+            brightness: cli.brightness.or(file.brightness),
+            contrast,
+            saturation,
+            once,
+            wait_for_selector: cli.wait_for_selector.or(file.wait_for_selector),
+            wait_for_navigation: cli.wait_for_navigation || file.wait_for_navigation,
+            inject_css_file: cli.inject_css_file.or(file.inject_css_file),
+            inject_js_file: cli.inject_js_file.or(file.inject_js_file),
+            stats_interval: cli.stats_interval.or(file.stats_interval),
+            dither: cli.dither || file.dither,
+            dither_levels: cli.dither_levels.or(file.dither_levels).unwrap_or(8),
+            orientation,
+            error_threshold: cli.error_threshold.or(file.error_threshold).unwrap_or(100),
+            error_window_secs: cli
+                .error_window_secs
+                .or(file.error_window_secs)
+                .unwrap_or(10),
+            scaling_mode: cli
+                .scaling_mode
+                .or(file.scaling_mode)
+                .unwrap_or(ScalingMode::Stretch),
+            pad_color: cli.pad_color.or(file.pad_color).unwrap_or(Rgb([0, 0, 0])),
+            frame_queue_capacity: cli
+                .frame_queue_capacity
+                .or(file.frame_queue_capacity)
+                .unwrap_or(2),
+            log_format: cli
+                .log_format
+                .or(file.log_format)
+                .unwrap_or(LogFormat::Human),
+            load_timeout_secs: cli
+                .load_timeout_secs
+                .or(file.load_timeout_secs)
+                .unwrap_or(30),
+            headers: merge_vec(cli.headers, file.headers),
+            basic_auth: cli.basic_auth.or(file.basic_auth),
+            validate_frames: cli.validate_frames || file.validate_frames,
+            ppm_ascii: cli.ppm_ascii || file.ppm_ascii,
+            dump_frames: cli.dump_frames.or(file.dump_frames),
+            dump_every: cli.dump_every.or(file.dump_every).unwrap_or(1).max(1),
+            record: cli.record.or(file.record),
+            device_scale_factor,
+            verify_server: cli.verify_server || file.verify_server,
+            loop_animation: cli.loop_animation || file.loop_animation,
+            partial_update: cli.partial_update || file.partial_update,
+            partial_update_block_size: cli
+                .partial_update_block_size
+                .or(file.partial_update_block_size)
+                .unwrap_or(8),
+            bind_addr: cli.bind_addr.or(file.bind_addr),
+            reload_interval: cli.reload_interval.or(file.reload_interval),
+            offset_x: cli
+                .offset_x
+                .or(file.offset_x)
+                .unwrap_or(Dimension::Pixels(0)),
+            offset_y: cli
+                .offset_y
+                .or(file.offset_y)
+                .unwrap_or(Dimension::Pixels(0)),
+            layer: cli.layer.or(file.layer).unwrap_or(0),
+            proxy: cli.proxy.or(file.proxy),
+            proxy_bypass_list: cli.proxy_bypass_list.or(file.proxy_bypass_list),
+            frame_timeout: cli.frame_timeout.or(file.frame_timeout),
+            max_bytes_per_sec: cli.max_bytes_per_sec.or(file.max_bytes_per_sec),
+            clear_on_exit: cli.clear_on_exit || file.clear_on_exit,
+            fade_on_exit: cli.fade_on_exit.or(file.fade_on_exit),
+            skip_unchanged: cli.skip_unchanged || file.skip_unchanged,
+            keyframe_interval: cli
+                .keyframe_interval
+                .or(file.keyframe_interval)
+                .unwrap_or(10),
+            transparent,
+            dry_run: cli.dry_run || file.dry_run,
+            log_page_console: cli.log_page_console || file.log_page_console,
+            cookies: merge_vec(cli.cookies, file.cookies),
+            cookies_file: cli.cookies_file.or(file.cookies_file),
+            health_port: cli.health_port.or(file.health_port),
+            health_max_frame_age: cli
+                .health_max_frame_age
+                .or(file.health_max_frame_age)
+                .unwrap_or(60),
+            test_pattern,
+            test_pattern_hold: cli
+                .test_pattern_hold
+                .or(file.test_pattern_hold)
+                .unwrap_or(1000),
+            control_stdin: cli.control_stdin || file.control_stdin,
+            no_sandbox: cli.no_sandbox || file.no_sandbox,
+            liveness_interval: cli.liveness_interval.or(file.liveness_interval),
+            liveness_failures: cli
+                .liveness_failures
+                .or(file.liveness_failures)
+                .unwrap_or(3),
+            output_protocol: cli
+                .output_protocol
+                .or(file.output_protocol)
+                .unwrap_or(OutputProtocol::FlaschenTaschenPpm),
+            max_runtime: cli
+                .max_runtime
+                .or(file.max_runtime)
+                .map(|max_runtime| parse_duration(&max_runtime))
+                .transpose()
+                .wrap_err("invalid --max-runtime")?,
+            stdout_ppm,
+            pad_datagram,
+            pause_on_selector: cli.pause_on_selector.or(file.pause_on_selector),
+            capture_every_nth_frame,
+            ready_expression: cli.ready_expression.or(file.ready_expression),
+            channel_order: cli
+                .channel_order
+                .or(file.channel_order)
+                .unwrap_or(ChannelOrder::Rgb),
+            parallel_sends,
+            serpentine,
+            fixed_fps,
+            module_width,
+            module_height,
+            adaptive_quality,
+            strict_dimensions: cli.strict_dimensions || file.strict_dimensions,
+            verbosity: match cli.verbosity {
+                _ if cli.quiet || file.quiet => 0,
+                0 => file.verbosity,
+                verbosity => verbosity,
+            },
+        })
+    }
+}
+
+/// The frame sources given on the command line, by environment variables or in the config file.
+/// Unlike other arguments, they are taken as a whole from the first of them giving any source,
+/// e.g. `FT_URL` must not conflict with an `--image` given on the command line.
+struct FrameSources {
+    url: Option<String>,
+    image: Option<PathBuf>,
+    playlist: Option<String>,
+    regions: Vec<RegionSpec>,
+    replay: Option<PathBuf>,
+}
+impl FrameSources {
+    /// Returns the sources given by the `FT_URL`, `FT_IMAGE`, `FT_PLAYLIST` and `FT_REPLAY` environment variables.
+    fn from_env() -> FrameSources {
+        FrameSources {
+            url: env::var("FT_URL").ok(),
+            image: env::var_os("FT_IMAGE").map(PathBuf::from),
+            playlist: env::var("FT_PLAYLIST").ok(),
+            regions: Vec::new(),
+            replay: env::var_os("FT_REPLAY").map(PathBuf::from),
+        }
+    }
+
+    /// Returns true if none of the sources is set.
+    fn is_empty(&self) -> bool {
+        self.url.is_none()
+            && self.image.is_none()
+            && self.playlist.is_none()
+            && self.regions.is_empty()
+            && self.replay.is_none()
+    }
+
+    /// Returns these sources, or the given ones if none is set.
+    fn or(self, other: FrameSources) -> FrameSources {
+        if self.is_empty() {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// Returns the sources in the order url, image, playlist, regions and replay, with `None` for no regions.
+    fn into_tuple(
+        self,
+    ) -> (
+        Option<String>,
+        Option<PathBuf>,
+        Option<String>,
+        Option<Vec<RegionSpec>>,
+        Option<PathBuf>,
+    ) {
+        (
+            self.url,
+            self.image,
+            self.playlist,
+            Some(self.regions).filter(|regions| !regions.is_empty()),
+            self.replay,
+        )
+    }
+}
+
+/// Returns the values given on the command line, or the values of the config file if none were given.
+fn merge_vec<T>(cli_values: Vec<T>, file_values: Vec<T>) -> Vec<T> {
+    if cli_values.is_empty() {
+        file_values
+    } else {
+        cli_values
+    }
+}
+
+/// Returns the error reported if a required argument is neither given on the command line nor in the config file.
+fn missing_argument(name: &str) -> eyre::Report {
+    eyre!(
+        "missing required argument --{}, set it on the command line, by environment variable or in the config file",
+        name
+    )
+}
+
+/// Parses a JPEG quality argument, rejecting values outside of 0-100.
+fn parse_jpeg_quality(value: &str) -> Result<u32, String> {
+    let quality: u32 = value
+        .parse()
+        .map_err(|_| format!("`{}` is not a valid number", value))?;
+    if quality > 100 {
+        return Err(format!("{} is out of range, expected 0-100", quality));
+    }
+    Ok(quality)
+}
+
+/// Parses a crop region argument of the form `x,y,width,height`, each value in pixels or a percentage.
+fn parse_crop(value: &str) -> Result<(Dimension, Dimension, Dimension, Dimension), String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.parse::<Dimension>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("`{}` contains an invalid value: {}", value, err))?;
+    match parts.as_slice() {
+        [_, _, width, height] if width.is_zero() || height.is_zero() => {
+            Err(format!("`{}` has an empty width or height", value))
+        }
+        [x, y, width, height] => Ok((*x, *y, *width, *height)),
+        _ => Err(format!("`{}` is not of the form x,y,width,height", value)),
+    }
+}
+
+/// Parses a rotation argument in degrees.
+fn parse_rotation(value: &str) -> Result<Orientation, String> {
+    match value {
+        "0" => Ok(Orientation::None),
+        "90" => Ok(Orientation::Rotate90),
+        "180" => Ok(Orientation::Rotate180),
+        "270" => Ok(Orientation::Rotate270),
+        _ => Err(format!(
+            "`{}` is not a valid rotation, expected 90, 180 or 270",
+            value
+        )),
+    }
+}
+
+/// Parses a flip argument.
+fn parse_flip(value: &str) -> Result<Orientation, String> {
+    match value {
+        "horizontal" => Ok(Orientation::FlipH),
+        "vertical" => Ok(Orientation::FlipV),
+        _ => Err(format!(
+            "`{}` is not a valid flip, expected horizontal or vertical",
+            value
+        )),
+    }
+}
+
+/// Deserializes a rotation in degrees from the config file, e.g. `rotate = 90`.
+fn deserialize_rotation<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Orientation>, D::Error> {
+    let degrees = u32::deserialize(deserializer)?;
+    parse_rotation(&degrees.to_string())
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Deserializes a flip from the config file, e.g. `flip = "horizontal"`.
+fn deserialize_flip<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Orientation>, D::Error> {
+    let flip = String::deserialize(deserializer)?;
+    parse_flip(&flip)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parses a color argument given as hex (`#rrggbb`) or as comma separated channels (`r,g,b`).
+pub fn parse_color(value: &str) -> Result<Rgb<u8>, String> {
+    let invalid = || {
+        format!(
+            "`{}` is not a valid color, expected #rrggbb or r,g,b",
+            value
+        )
+    };
+    if let Some(hex) = value.strip_prefix('#') {
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        return Ok(Rgb([channel(0)?, channel(2)?, channel(4)?]));
+    }
+
+    let channels = value
+        .split(',')
+        .map(|part| part.trim().parse::<u8>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    match channels.as_slice() {
+        [r, g, b] => Ok(Rgb([*r, *g, *b])),
+        _ => Err(invalid()),
+    }
+}
+
+/// Deserializes a color from the config file, see `parse_color`.
+fn deserialize_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Rgb<u8>>, D::Error> {
+    let color = String::deserialize(deserializer)?;
+    parse_color(&color)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parses a HTTP header argument of the form `Name: Value`.
+/// The value is not part of the error message as it usually contains credentials.
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, header_value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), header_value.trim().to_string()))
+        }
+        _ => Err(String::from(
+            "invalid header, expected the form `Name: Value`",
+        )),
+    }
+}
+
+/// Deserializes a list of HTTP headers from the config file, e.g. `header = ["X-Api-Key: secret"]`.
+fn deserialize_headers<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(String, String)>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|header| parse_header(header))
+        .collect::<Result<_, _>>()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parses a cookie argument, see `Cookie::parse`.
+fn parse_cookie(value: &str) -> Result<Cookie, String> {
+    Cookie::parse(value).map_err(|err| err.to_string())
+}
+
+/// Deserializes a list of cookies from the config file, e.g. `cookie = ["session=secret; domain=example.com"]`.
+fn deserialize_cookies<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Cookie>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|cookie| parse_cookie(cookie))
+        .collect::<Result<_, _>>()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parses a region argument, see `RegionSpec::parse`.
+fn parse_region(value: &str) -> Result<RegionSpec, String> {
+    RegionSpec::parse(value).map_err(|err| err.to_string())
+}
+
+/// Deserializes a list of regions from the config file, e.g. `region = ["0,0,32,32=https://example.com"]`.
+fn deserialize_regions<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<RegionSpec>, D::Error> {
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|region| parse_region(region))
+        .collect::<Result<_, _>>()
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parses basic auth credentials of the form `user:password`.
+/// The credentials are not part of the error message.
+fn parse_basic_auth(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((user, password)) if !user.is_empty() => Ok((user.to_string(), password.to_string())),
+        _ => Err(String::from(
+            "invalid basic auth credentials, expected the form `user:password`",
+        )),
+    }
+}
+
+/// Deserializes basic auth credentials from the config file, e.g. `basic-auth = "user:password"`.
+fn deserialize_basic_auth<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<(String, String)>, D::Error> {
+    let credentials = String::deserialize(deserializer)?;
+    parse_basic_auth(&credentials)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,139 @@
+use image::RgbImage;
+
+/// Color correction applied to frames before they are sent to the LED screen, see `color_correct`.
+/// LED screens are perceptually much brighter than sRGB monitors, a gamma > 1 darkens the midtones accordingly.
+/// They also tend to look flat, which a contrast or saturation above 1 compensates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColorCorrection {
+    pub gamma: f32,
+    /// A linear factor applied after the gamma correction, 1.0 keeps the brightness unchanged
+    pub brightness: f32,
+    /// A factor scaling the distance of each channel from the midtone, 1.0 keeps the contrast unchanged
+    pub contrast: f32,
+    /// A factor applied to the HSL saturation of each pixel, 0.0 turns frames gray and 1.0 keeps them unchanged
+    pub saturation: f32,
+}
+impl Default for ColorCorrection {
+    fn default() -> Self {
+        ColorCorrection {
+            gamma: 1.0,
+            brightness: 1.0,
+            contrast: 1.0,
+            saturation: 1.0,
+        }
+    }
+}
+
+/// Applies the given color correction to each pixel of the given image in a single pass.
+/// Each channel is corrected by `255 * (contrast * (brightness * (in / 255) ^ gamma - 0.5) + 0.5)`, clamped to 0-255,
+/// then the saturation of the pixel is scaled in the HSL color space.
+pub fn color_correct(img: &mut RgbImage, params: &ColorCorrection) {
+    // there are only 256 possible input values per channel, precompute their corrected values:
+    let mut lookup = [0u8; 256];
+    for (value, corrected) in lookup.iter_mut().enumerate() {
+        let normalized = params.brightness * (value as f32 / 255.0).powf(params.gamma);
+        *corrected = (255.0 * (params.contrast * (normalized - 0.5) + 0.5))
+            .round()
+            .clamp(0.0, 255.0) as u8;
+    }
+    let adjust_saturation = params.saturation != 1.0;
+
+    for pixel in img.pixels_mut() {
+        for channel in pixel.0.iter_mut() {
+            *channel = lookup[*channel as usize];
+        }
+        if adjust_saturation {
+            let (hue, saturation, lightness) = rgb_to_hsl(pixel.0);
+            pixel.0 = hsl_to_rgb(
+                hue,
+                (saturation * params.saturation).clamp(0.0, 1.0),
+                lightness,
+            );
+        }
+    }
+}
+
+/// Applies the given gamma and brightness correction to each channel of each pixel of the given image:
+/// `out = 255 * brightness * (in / 255) ^ gamma`, clamped to 0-255. See `color_correct`.
+pub fn apply_gamma(img: &mut RgbImage, gamma: f32, brightness: f32) {
+    color_correct(
+        img,
+        &ColorCorrection {
+            gamma,
+            brightness,
+            ..Default::default()
+        },
+    );
+}
+
+/// Returns the hue (0-6, one unit per sector of the color wheel), saturation and lightness (0-1) of the given pixel.
+fn rgb_to_hsl(pixel: [u8; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = pixel.map(|channel| channel as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let chroma = max - min;
+    if chroma == 0.0 {
+        return (0.0, 0.0, lightness);
+    }
+
+    let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    (hue, saturation, lightness)
+}
+
+/// Returns the pixel of the given hue, saturation and lightness, see `rgb_to_hsl`.
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [u8; 3] {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r, g, b].map(|channel| ((channel + m) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+/// Reduces each channel of the given image to `levels` evenly spaced values (at least 2)
+/// and diffuses the quantization error to the neighbouring pixels using the Floyd–Steinberg algorithm.
+/// This avoids visible banding of gradients on LED screens with a limited color depth.
+pub fn dither_floyd_steinberg(img: &mut RgbImage, levels: u8) {
+    let step = 255.0 / (levels.max(2) - 1) as f32;
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    // the accumulated error of each channel of each pixel, row by row:
+    let mut errors = vec![0f32; width * height * 3];
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = img.get_pixel_mut(x as u32, y as u32);
+            for channel in 0..3 {
+                let index = (y * width + x) * 3 + channel;
+                let value = pixel.0[channel] as f32 + errors[index];
+                let quantized = ((value / step).round() * step).clamp(0.0, 255.0);
+                pixel.0[channel] = quantized.round() as u8;
+
+                let error = value - quantized;
+                let mut diffuse = |dx: isize, dy: usize, factor: f32| {
+                    let (nx, ny) = (x as isize + dx, y + dy);
+                    if nx >= 0 && (nx as usize) < width && ny < height {
+                        errors[(ny * width + nx as usize) * 3 + channel] += error * factor;
+                    }
+                };
+                diffuse(1, 0, 7.0 / 16.0);
+                diffuse(-1, 1, 3.0 / 16.0);
+                diffuse(0, 1, 5.0 / 16.0);
+                diffuse(1, 1, 1.0 / 16.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::mem;
+use std::sync::{Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Stops a set of background threads, e.g. those of a screencasting tab, see `start_screencasting_tab`
+/// and `stop_screencasting`.
+///
+/// Threads are started with `spawn` and wait with `sleep`, which returns early once the control is stopped.
+/// Threads blocking on something else, like the frame worker waiting for the next frame,
+/// are woken up by a callback registered with `on_stop`, e.g. closing their queue.
+/// `stop` joins all threads, so everything they hold, like the context of the frame handler, is dropped once it returns.
+#[derive(Default)]
+pub struct ScreencastControl {
+    stopped: Mutex<bool>,
+    stopping: Condvar,
+    on_stop: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl ScreencastControl {
+    /// Returns a new control without any threads.
+    pub fn new() -> ScreencastControl {
+        Default::default()
+    }
+
+    /// Runs the given function in a new thread, which is joined by `stop`.
+    /// The function should return soon after the control was stopped, see `sleep` and `is_stopped`.
+    pub fn spawn<F: FnOnce() + Send + 'static>(&self, f: F) {
+        let thread = thread::spawn(f);
+        self.threads.lock().unwrap().push(thread);
+    }
+
+    /// Registers a callback run by `stop` before joining the threads, e.g. to close a queue a thread is waiting on.
+    /// The callback runs right away if the control is already stopped.
+    pub fn on_stop<F: FnOnce() + Send + 'static>(&self, callback: F) {
+        let mut on_stop = self.on_stop.lock().unwrap();
+        if self.is_stopped() {
+            drop(on_stop);
+            callback();
+        } else {
+            on_stop.push(Box::new(callback));
+        }
+    }
+
+    /// Returns true once `stop` was called.
+    pub fn is_stopped(&self) -> bool {
+        *self.stopped.lock().unwrap()
+    }
+
+    /// Blocks the current thread for the given duration and returns true,
+    /// or returns false as soon as the control is stopped.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        let mut stopped = self.stopped.lock().unwrap();
+        while !*stopped {
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            stopped = self
+                .stopping
+                .wait_timeout(stopped, deadline - now)
+                .unwrap()
+                .0;
+        }
+        false
+    }
+
+    /// Stops all threads: wakes up the sleeping ones, runs the `on_stop` callbacks and waits for all threads to exit.
+    /// Calling this from one of the threads does not wait for that thread itself. Calling it again does nothing.
+    pub fn stop(&self) {
+        // setting the flag while holding the callbacks makes sure `on_stop` either registers or runs each callback:
+        let callbacks = {
+            let mut on_stop = self.on_stop.lock().unwrap();
+            *self.stopped.lock().unwrap() = true;
+            mem::take(&mut *on_stop)
+        };
+        self.stopping.notify_all();
+        for callback in callbacks {
+            callback();
+        }
+
+        let threads = mem::take(&mut *self.threads.lock().unwrap());
+        let current = thread::current().id();
+        for thread in threads {
+            // a panicking thread already reported its panic, there is nothing left to clean up:
+            if thread.thread().id() != current {
+                let _ = thread.join();
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use eyre::{eyre, Result, WrapErr};
+use headless_chrome::protocol::cdp::Network;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A cookie set in the browser before navigating to the page, e.g. a session cookie of a dashboard
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// The domain of the cookie, the domain of the screencast URL if `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    /// The expiration date in seconds since the UNIX epoch, a session cookie if `None`
+    #[serde(
+        default,
+        alias = "expirationDate",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub expires: Option<f64>,
+}
+
+impl Cookie {
+    /// Parses a cookie of the form `name=value; domain=example.com; path=/; secure; httponly; expires=<seconds>`.
+    /// All attributes after the name and value are optional. The value is not part of error messages.
+    pub fn parse(cookie: &str) -> Result<Cookie> {
+        let mut parts = cookie.split(';').map(str::trim);
+        let (name, value) = match parts.next().and_then(|pair| pair.split_once('=')) {
+            Some((name, value)) if !name.trim().is_empty() => (name.trim(), value.trim()),
+            _ => return Err(eyre!("invalid cookie, expected the form `name=value; ...`")),
+        };
+
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            ..Default::default()
+        };
+        for attribute in parts.filter(|part| !part.is_empty()) {
+            let (key, attribute_value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), Some(value.trim())),
+                None => (attribute, None),
+            };
+            match (key.to_ascii_lowercase().as_str(), attribute_value) {
+                ("domain", Some(domain)) => cookie.domain = Some(domain.to_string()),
+                ("path", Some(path)) => cookie.path = Some(path.to_string()),
+                ("secure", None) => cookie.secure = true,
+                ("httponly", None) => cookie.http_only = true,
+                ("expires", Some(expires)) => {
+                    cookie.expires = Some(expires.parse().map_err(|_| {
+                        eyre!(
+                            "invalid expires attribute of cookie {}, expected seconds since the UNIX epoch",
+                            cookie.name
+                        )
+                    })?)
+                }
+                _ => {
+                    return Err(eyre!(
+                        "invalid attribute `{}` of cookie {}",
+                        key,
+                        cookie.name
+                    ))
+                }
+            }
+        }
+
+        Ok(cookie)
+    }
+
+    /// Returns the CDP parameters setting this cookie.
+    /// Cookies without domain are set for the given URL, chrome requires either of them.
+    pub fn to_cookie_param(&self, default_url: &str) -> Result<Network::CookieParam> {
+        let mut param = serde_json::to_value(self)?;
+        if self.domain.is_none() {
+            param["url"] = serde_json::Value::from(default_url);
+        }
+        serde_json::from_value(param)
+            .wrap_err_with(|| format!("could not convert cookie {}", self.name))
+    }
+}
+
+/// Parses the cookies of a cookies file, either a JSON array of cookie objects as exported by browser extensions,
+/// or a Netscape cookies file as written by curl and wget.
+pub fn parse_cookies_file(contents: &str) -> Result<Vec<Cookie>> {
+    if contents.trim_start().starts_with('[') {
+        return serde_json::from_str(contents).wrap_err("invalid JSON cookies file");
+    }
+
+    let mut cookies = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        // curl marks HTTP-only cookies with this prefix, all other lines starting with `#` are comments:
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(eyre!(
+                "invalid Netscape cookies file, expected 7 tab separated fields on line {}",
+                index + 1
+            ));
+        }
+        let expires: f64 = fields[4].parse().map_err(|_| {
+            eyre!(
+                "invalid Netscape cookies file, expected the expiration date on line {}",
+                index + 1
+            )
+        })?;
+        cookies.push(Cookie {
+            name: fields[5].to_string(),
+            value: fields[6].to_string(),
+            domain: Some(fields[0].to_string()),
+            path: Some(fields[2].to_string()),
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            http_only,
+            // an expiration date of 0 marks session cookies:
+            expires: Some(expires).filter(|expires| *expires > 0.0),
+        });
+    }
+
+    Ok(cookies)
+}
+
+/// Reads and parses the cookies file at the given path, see `parse_cookies_file`.
+pub fn load_cookies_file(path: &Path) -> Result<Vec<Cookie>> {
+    let contents = fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read cookies file {}", path.display()))?;
+    parse_cookies_file(&contents)
+        .wrap_err_with(|| format!("failed to parse cookies file {}", path.display()))
+}
@@ -0,0 +1,79 @@
+use image::{imageops, RgbImage};
+
+/// A rectangular region of an image, in pixels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Compares two images of the same dimensions in blocks of `block_size` x `block_size` pixels
+/// and returns the regions of `next` which differ from `prev`, together with their contents.
+/// Adjacent changed blocks of the same block row are merged into a single region.
+/// Returns the whole of `next` as a single region if the dimensions of the images differ.
+///
+/// Smaller blocks result in less unchanged pixels being sent, but in more regions,
+/// each of them being sent in a separate datagram with its own header.
+pub fn diff_regions(prev: &RgbImage, next: &RgbImage, block_size: u32) -> Vec<(Rect, RgbImage)> {
+    let (width, height) = next.dimensions();
+    if prev.dimensions() != (width, height) {
+        let rect = Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        };
+        return vec![(rect, next.clone())];
+    }
+
+    let block_size = block_size.max(1);
+    let mut regions = Vec::new();
+    for block_y in (0..height).step_by(block_size as usize) {
+        let block_height = block_size.min(height - block_y);
+        // the start of the current run of changed blocks in this block row:
+        let mut run_start: Option<u32> = None;
+        for block_x in (0..width).step_by(block_size as usize) {
+            let block_width = block_size.min(width - block_x);
+            let block = Rect {
+                x: block_x,
+                y: block_y,
+                width: block_width,
+                height: block_height,
+            };
+            match (block_changed(prev, next, block), run_start) {
+                (true, None) => run_start = Some(block_x),
+                (false, Some(start)) => {
+                    regions.push(region(next, start, block_x, block_y, block_height));
+                    run_start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = run_start {
+            regions.push(region(next, start, width, block_y, block_height));
+        }
+    }
+
+    regions
+}
+
+/// Returns true if any pixel of the given block differs between both images.
+fn block_changed(prev: &RgbImage, next: &RgbImage, block: Rect) -> bool {
+    (block.y..block.y + block.height).any(|y| {
+        (block.x..block.x + block.width).any(|x| prev.get_pixel(x, y) != next.get_pixel(x, y))
+    })
+}
+
+/// Returns the region of the given image spanning from `start_x` to `end_x` (exclusive) in the given block row.
+fn region(image: &RgbImage, start_x: u32, end_x: u32, y: u32, height: u32) -> (Rect, RgbImage) {
+    let rect = Rect {
+        x: start_x,
+        y,
+        width: end_x - start_x,
+        height,
+    };
+    let contents = imageops::crop_imm(image, rect.x, rect.y, rect.width, rect.height).to_image();
+    (rect, contents)
+}
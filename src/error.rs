@@ -0,0 +1,109 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::io;
+
+/// A result returned by the library functions of flaschentaschen-web which fail with a `FtError`.
+pub type FtResult<T> = Result<T, FtError>;
+
+/// The failure modes of the library functions of flaschentaschen-web.
+/// Match on the variants to handle specific failures, e.g. to retry sends which failed temporarily.
+#[derive(Debug)]
+pub enum FtError {
+    /// A local socket could not be set up and connected to the flaschentaschen server at the given address,
+    /// e.g. because the local address could not be bound, the server address could not be resolved
+    /// or the TCP connection could not be established
+    SocketBind { address: String, source: io::Error },
+    /// A datagram could not be sent to the flaschentaschen server at the given address
+    Send { address: String, source: io::Error },
+    /// The flaschentaschen server at the given address refused the connection, it is probably not running
+    ConnectionRefused { address: String },
+    /// A frame of the given size exceeds `MAX_DATAGRAM_SIZE` and can never be sent to the server at the given address
+    FrameTooLarge { address: String, size: usize },
+    /// The flaschentaschen server at the given address did not report the size of its LED screen,
+    /// e.g. because it does not support the size query or sent an invalid response
+    SizeQuery { address: String, reason: String },
+    /// A frame could not be decoded
+    Decode(Box<dyn Error + Send + Sync>),
+    /// A decoded frame could not be converted or encoded as PPM
+    Convert(Box<dyn Error + Send + Sync>),
+    /// A converted frame cannot be sent the configured way, e.g. an ASCII PPM at an offset or as raw RGB.
+    /// Unlike a corrupt frame, this fails for every frame, it is therefore not a decode error
+    Unsendable(Box<dyn Error + Send + Sync>),
+    /// A frame could not be written or read because of an I/O error, e.g. while encoding it
+    Io(io::Error),
+    /// A decoded frame does not have the expected dimensions (width, height) and would have to be resized,
+    /// see `ConversionOptions::strict_dimensions`
+    UnexpectedDimensions {
+        expected: (u32, u32),
+        actual: (u32, u32),
+    },
+    /// The browser could not be launched or connected to
+    BrowserLaunch(String),
+    /// The browser could not open or load the page
+    Navigate(String),
+}
+
+impl Display for FtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FtError::SocketBind { address, source } => {
+                write!(f, "failed to connect to {}: {}", address, source)
+            }
+            FtError::Send { address, source } => {
+                write!(f, "failed to send PPM to FlaschenTaschen@{}: {}", address, source)
+            }
+            FtError::ConnectionRefused { address } => write!(
+                f,
+                "FlaschenTaschen@{} refused the connection, is the server running?",
+                address
+            ),
+            FtError::FrameTooLarge { address, size } => write!(
+                f,
+                "PPM of {} bytes exceeds the maximum UDP datagram size of {} bytes supported by FlaschenTaschen@{}. \
+                A binary PPM takes 3 bytes per pixel, reduce the screen dimensions to send smaller frames",
+                size,
+                crate::MAX_DATAGRAM_SIZE,
+                address
+            ),
+            FtError::SizeQuery { address, reason } => write!(
+                f,
+                "failed to query the screen size of FlaschenTaschen@{}: {}",
+                address, reason
+            ),
+            FtError::Decode(err) => write!(f, "failed to decode frame: {}", err),
+            FtError::Convert(err) => write!(f, "failed to convert frame: {}", err),
+            FtError::Unsendable(err) => write!(f, "cannot send frame: {}", err),
+            FtError::Io(err) => write!(f, "I/O error while handling frame: {}", err),
+            FtError::UnexpectedDimensions { expected, actual } => write!(
+                f,
+                "received a frame of {}x{} pixels instead of the expected {}x{}, \
+                check the viewport size and the device scale factor",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            FtError::BrowserLaunch(msg) => write!(f, "failed to start browser: {}", msg),
+            FtError::Navigate(msg) => write!(f, "failed to load page: {}", msg),
+        }
+    }
+}
+
+impl FtError {
+    /// Returns true if this error is caused by a single frame which could not be decoded or converted,
+    /// e.g. a corrupt screenshot. Unlike send errors, these do not indicate that the server is down.
+    pub fn is_decode_error(&self) -> bool {
+        matches!(self, FtError::Decode(_) | FtError::Convert(_))
+    }
+}
+
+impl Error for FtError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FtError::SocketBind { source, .. }
+            | FtError::Send { source, .. }
+            | FtError::Io(source) => Some(source),
+            FtError::Decode(err) | FtError::Convert(err) | FtError::Unsendable(err) => {
+                Some(err.as_ref())
+            }
+            _ => None,
+        }
+    }
+}
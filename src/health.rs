@@ -0,0 +1,158 @@
+use crate::stats::Stats;
+use eyre::{eyre, Result};
+use log::{debug, info, warn};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tiny_http::{Header, Response, Server};
+
+/// Returns true if the last frame was sent within the given maximum age.
+pub fn is_healthy(stats: &Stats, max_frame_age: Duration) -> bool {
+    stats
+        .last_frame_age()
+        .map_or(false, |age| age <= max_frame_age)
+}
+
+/// Returns the counters and gauges of the given stats in the Prometheus text exposition format.
+/// The age of the last frame is `NaN` before the first frame was sent.
+pub fn render_metrics(stats: &Stats) -> String {
+    let snapshot = stats.snapshot();
+    let counters = [
+        (
+            "frames_received",
+            "Frames received from the browser",
+            snapshot.frames_received,
+        ),
+        (
+            "frames_dropped",
+            "Frames dropped before being handled",
+            snapshot.frames_dropped,
+        ),
+        (
+            "frames_skipped",
+            "Frames skipped because they were unchanged",
+            snapshot.frames_skipped,
+        ),
+        (
+            "frames_sent",
+            "Frames sent to the flaschentaschen servers",
+            snapshot.frames_sent,
+        ),
+        (
+            "bytes_sent",
+            "PPM bytes sent to the flaschentaschen servers",
+            snapshot.bytes_sent,
+        ),
+        (
+            "errors",
+            "Frames which could not be handled",
+            snapshot.errors,
+        ),
+        (
+            "decode_errors",
+            "Frames which could not be decoded or converted",
+            snapshot.decode_errors,
+        ),
+        (
+            "send_errors",
+            "Frames which could not be sent",
+            snapshot.send_errors,
+        ),
+    ];
+
+    let mut metrics = String::new();
+    for (name, help, value) in counters.iter() {
+        let _ = writeln!(
+            metrics,
+            "# HELP flaschentaschen_web_{}_total {}",
+            name, help
+        );
+        let _ = writeln!(metrics, "# TYPE flaschentaschen_web_{}_total counter", name);
+        let _ = writeln!(metrics, "flaschentaschen_web_{}_total {}", name, value);
+    }
+    let last_frame_age = stats
+        .last_frame_age()
+        .map_or(f64::NAN, |age| age.as_secs_f64());
+    let gauges = [
+        (
+            "queue_depth",
+            "Frames waiting in the frame queue",
+            snapshot.queue_depth,
+        ),
+        (
+            "peak_queue_depth",
+            "The highest number of frames waiting in the frame queue so far",
+            snapshot.peak_queue_depth,
+        ),
+    ];
+    for (name, help, value) in gauges.iter() {
+        let _ = writeln!(metrics, "# HELP flaschentaschen_web_{} {}", name, help);
+        let _ = writeln!(metrics, "# TYPE flaschentaschen_web_{} gauge", name);
+        let _ = writeln!(metrics, "flaschentaschen_web_{} {}", name, value);
+    }
+
+    let latency_micros_to_secs = |micros: u64| micros as f64 / 1_000_000.0;
+    metrics.push_str("# HELP flaschentaschen_web_frame_latency_seconds Seconds from capturing a frame in chrome until it was sent\n");
+    metrics.push_str("# TYPE flaschentaschen_web_frame_latency_seconds summary\n");
+    let _ = writeln!(
+        metrics,
+        "flaschentaschen_web_frame_latency_seconds_sum {}",
+        latency_micros_to_secs(snapshot.total_latency_micros)
+    );
+    let _ = writeln!(
+        metrics,
+        "flaschentaschen_web_frame_latency_seconds_count {}",
+        snapshot.latency_samples
+    );
+    metrics.push_str(
+        "# HELP flaschentaschen_web_max_frame_latency_seconds The highest frame latency so far\n",
+    );
+    metrics.push_str("# TYPE flaschentaschen_web_max_frame_latency_seconds gauge\n");
+    let _ = writeln!(
+        metrics,
+        "flaschentaschen_web_max_frame_latency_seconds {}",
+        latency_micros_to_secs(snapshot.max_latency_micros)
+    );
+
+    metrics.push_str(
+        "# HELP flaschentaschen_web_last_frame_age_seconds Seconds since the last frame was sent\n",
+    );
+    metrics.push_str("# TYPE flaschentaschen_web_last_frame_age_seconds gauge\n");
+    let _ = writeln!(
+        metrics,
+        "flaschentaschen_web_last_frame_age_seconds {}",
+        last_frame_age
+    );
+    metrics
+}
+
+/// Spawns a thread serving a health check at `/healthz` and the metrics of the given stats at `/metrics`
+/// on the given port of all interfaces. `/healthz` answers 200 if a frame was sent within `max_frame_age`, 503 otherwise.
+pub fn spawn_health_server(
+    stats: Arc<Stats>,
+    port: u16,
+    max_frame_age: Duration,
+) -> Result<JoinHandle<()>> {
+    let server = Server::http(("0.0.0.0", port))
+        .map_err(|err| eyre!("failed to start health server on port {}: {}", port, err))?;
+    info!("serving /healthz and /metrics on port {}", port);
+
+    Ok(thread::spawn(move || {
+        for request in server.incoming_requests() {
+            debug!("health server: {} {}", request.method(), request.url());
+            let response = match request.url() {
+                "/healthz" if is_healthy(&stats, max_frame_age) => Response::from_string("ok\n"),
+                "/healthz" => Response::from_string("no recent frames\n").with_status_code(503),
+                "/metrics" => Response::from_string(render_metrics(&stats)).with_header(
+                    Header::from_bytes(&b"Content-Type"[..], &b"text/plain; version=0.0.4"[..])
+                        .expect("invalid content type header"),
+                ),
+                _ => Response::from_string("not found\n").with_status_code(404),
+            };
+            if let Err(err) = request.respond(response) {
+                warn!("health server failed to respond: {}", err);
+            }
+        }
+    }))
+}
@@ -1,17 +1,69 @@
 use base64;
-use eyre::{eyre, Result};
-use headless_chrome::protocol::cdp::Page::{self, StartScreencastFormatOption};
-use headless_chrome::{protocol::cdp::types::Event, Browser};
+use circuit_breaker::CircuitBreaker;
+use clap::ArgEnum;
+use color::{color_correct, dither_floyd_steinberg, ColorCorrection};
+use control::ScreencastControl;
+use cookies::Cookie;
+use error::{FtError, FtResult};
+use eyre::{eyre, Result, WrapErr};
+use headless_chrome::protocol::cdp::Page::{
+    self, CaptureScreenshotFormatOption, StartScreencastFormatOption,
+};
+use headless_chrome::protocol::cdp::{Emulation, Log, Network, Runtime, DOM};
+use headless_chrome::{protocol::cdp::types::Event, Browser, Tab};
+use image::imageops::{self, FilterType};
 use image::pnm::{PNMSubtype, SampleEncoding};
-use image::ImageOutputFormat;
-use image::{load_from_memory_with_format, ImageFormat};
-use log::{error, info, trace};
+use image::{load_from_memory_with_format, ImageError, ImageFormat};
+use image::{DynamicImage, GenericImageView, ImageOutputFormat, Rgb, RgbImage, RgbaImage};
+use log::{debug, error, info, trace, warn};
+use playlist::PlaylistEntry;
+use ppm::{ppm_pixel_data, validate_ppm};
+use queue::FrameQueue;
+use region::RegionSpec;
+use serde::Deserialize;
 use serde_json;
-use std::net::UdpSocket;
-use std::sync::Mutex;
+#[cfg(unix)]
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+#[cfg(unix)]
+use signal_hook::iterator::Signals;
+use stats::Stats;
+use std::ffi::OsString;
+use std::io::{ErrorKind, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::{fmt::Display, sync::Arc};
 
+// the tab passed to `ScreencastOptions::on_ready` is a headless_chrome type, this spares users matching the version:
+pub use headless_chrome;
+
+pub mod animation;
+pub mod circuit_breaker;
 pub mod cli;
+pub mod color;
+pub mod control;
+pub mod cookies;
+pub mod diff;
+pub mod error;
+pub mod health;
+pub mod liveness;
+pub mod logging;
+pub mod pattern;
+pub mod playlist;
+pub mod ppm;
+pub mod queue;
+pub mod record;
+pub mod region;
+pub mod stats;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 /// Wraps a Result value with a compatible error type and returns a new result with an eyre-compatible Report error type.
 /// The given message is prepended to the display result of the original error.
@@ -19,151 +71,3624 @@ fn map_err<T, E: Display>(value: std::result::Result<T, E>, msg: &str) -> Result
     value.map_err(|err| eyre!("{}: {}", msg, err))
 }
 
+/// The image format chrome uses to encode screencast frames
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScreencastImageFormat {
+    Jpeg,
+    Png,
+}
+impl ScreencastImageFormat {
+    fn as_screencast_format(&self) -> StartScreencastFormatOption {
+        match self {
+            ScreencastImageFormat::Jpeg => StartScreencastFormatOption::Jpeg,
+            ScreencastImageFormat::Png => StartScreencastFormatOption::Png,
+        }
+    }
+
+    fn as_screenshot_format(&self) -> CaptureScreenshotFormatOption {
+        match self {
+            ScreencastImageFormat::Jpeg => CaptureScreenshotFormatOption::Jpeg,
+            ScreencastImageFormat::Png => CaptureScreenshotFormatOption::Png,
+        }
+    }
+
+    fn as_image_format(&self) -> ImageFormat {
+        match self {
+            ScreencastImageFormat::Jpeg => ImageFormat::Jpeg,
+            ScreencastImageFormat::Png => ImageFormat::Png,
+        }
+    }
+}
+impl From<ScreencastImageFormat> for ImageFormat {
+    fn from(format: ScreencastImageFormat) -> Self {
+        format.as_image_format()
+    }
+}
+
+/// The filter used to resize frames to the dimensions of the LED screen
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResizeFilter {
+    Nearest,
+    Triangle,
+    CatmullRom,
+    Gaussian,
+    Lanczos3,
+}
+impl From<ResizeFilter> for FilterType {
+    fn from(filter: ResizeFilter) -> Self {
+        match filter {
+            ResizeFilter::Nearest => FilterType::Nearest,
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::CatmullRom => FilterType::CatmullRom,
+            ResizeFilter::Gaussian => FilterType::Gaussian,
+            ResizeFilter::Lanczos3 => FilterType::Lanczos3,
+        }
+    }
+}
+
+/// Defines how frames are scaled to the dimensions of the LED screen if their aspect ratios differ
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScalingMode {
+    /// Scales the frame to exactly the screen dimensions, distorting it if the aspect ratios differ
+    Stretch,
+    /// Scales the frame to fit into the screen preserving its aspect ratio and pads the remaining space
+    Fit,
+    /// Scales the frame to cover the screen preserving its aspect ratio and crops the overlapping parts
+    Fill,
+}
+
+/// A length given in pixels or as percentage of a total length, e.g. `32` or `50%` of the frame width.
+/// Percentages keep crops and offsets working when the viewport or the LED screen changes its size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dimension {
+    Pixels(u32),
+    /// A percentage between 0 and 100
+    Percent(f32),
+}
+impl Dimension {
+    /// Returns this dimension in pixels of the given total length, percentages are rounded to whole pixels.
+    pub fn resolve(self, total: u32) -> u32 {
+        match self {
+            Dimension::Pixels(pixels) => pixels,
+            Dimension::Percent(percent) => (total as f32 * percent / 100.0).round() as u32,
+        }
+    }
+
+    /// Returns true if this dimension is zero regardless of the total length.
+    pub fn is_zero(self) -> bool {
+        match self {
+            Dimension::Pixels(pixels) => pixels == 0,
+            Dimension::Percent(percent) => percent == 0.0,
+        }
+    }
+}
+impl FromStr for Dimension {
+    type Err = String;
+
+    /// Parses a number of pixels, or a percentage if suffixed with `%`.
+    fn from_str(value: &str) -> std::result::Result<Dimension, String> {
+        let value = value.trim();
+        match value.strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<f32>() {
+                Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(Dimension::Percent(percent)),
+                _ => Err(format!(
+                    "`{}` is not a valid percentage, expected 0% to 100%",
+                    value
+                )),
+            },
+            None => value
+                .parse::<u32>()
+                .map(Dimension::Pixels)
+                .map_err(|_| format!("`{}` is neither a number of pixels nor a percentage", value)),
+        }
+    }
+}
+impl<'de> Deserialize<'de> for Dimension {
+    /// Deserializes a number of pixels, e.g. `32`, or a string of pixels or a percentage, e.g. `"50%"`.
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawDimension {
+            Pixels(u32),
+            Text(String),
+        }
+        match RawDimension::deserialize(deserializer)? {
+            RawDimension::Pixels(pixels) => Ok(Dimension::Pixels(pixels)),
+            RawDimension::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// The order of the color channels of each pixel expected by the LED screen, e.g. for controllers wired as BGR
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChannelOrder {
+    Rgb,
+    Bgr,
+    Grb,
+}
+impl ChannelOrder {
+    /// Reorders the channels of all pixels of the given RGB image in place.
+    pub fn apply(&self, image: &mut RgbImage) {
+        match self {
+            ChannelOrder::Rgb => {}
+            ChannelOrder::Bgr => image.pixels_mut().for_each(|pixel| pixel.0.swap(0, 2)),
+            ChannelOrder::Grb => image.pixels_mut().for_each(|pixel| pixel.0.swap(0, 1)),
+        }
+    }
+}
+
+/// The orientation transformation applied to frames for LED screens which are not mounted upright
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Orientation {
+    None,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipH,
+    FlipV,
+}
+impl Orientation {
+    /// Returns true if this orientation swaps the width and height of a frame.
+    /// Frames must then be rendered at the swapped LED screen dimensions to match the screen after rotating them.
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(self, Orientation::Rotate90 | Orientation::Rotate270)
+    }
+
+    /// Returns the dimensions a frame needs to have before applying this orientation
+    /// to end up with the given dimensions.
+    pub fn source_dimensions(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        if self.swaps_dimensions() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    fn apply(&self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Orientation::None => image,
+            Orientation::Rotate90 => image.rotate90(),
+            Orientation::Rotate180 => image.rotate180(),
+            Orientation::Rotate270 => image.rotate270(),
+            Orientation::FlipH => image.fliph(),
+            Orientation::FlipV => image.flipv(),
+        }
+    }
+}
+
+/// Defines where the browser used for screencasting comes from
+#[derive(Clone, Debug, PartialEq)]
+pub enum BrowserSource {
+    /// Launches a new headless chrome instance
+    Launch,
+    /// Connects to an already running chrome instance using its remote debugging websocket URL,
+    /// e.g. `ws://127.0.0.1:9222/devtools/browser/<id>`
+    Connect(String),
+}
+
 /// Screencast options passed to `start_screencasting`
+#[derive(Clone)]
 pub struct ScreencastOptions {
     pub url: String,
     pub width: u32,
     pub height: u32,
+    pub format: ScreencastImageFormat,
+    /// The maximum number of frames per second forwarded to `on_frame`, unlimited if `None`
+    pub max_fps: Option<u32>,
+    /// A custom user agent used by the browser, chrome's default if `None`
+    pub user_agent: Option<String>,
+    /// Additional command line arguments passed to chrome, e.g. `--disable-gpu`
+    pub extra_chrome_args: Vec<OsString>,
+    /// Whether to launch a new browser or to connect to a running one.
+    /// `extra_chrome_args` are ignored when connecting to a running browser.
+    pub browser_source: BrowserSource,
+    /// The compression quality (0-100) of JPEG screencast frames, chrome's default if `None`.
+    /// Validated by `ScreencastOptionsBuilder::build`.
+    pub jpeg_quality: Option<u32>,
+    /// If set, screencasting starts as soon as an element matching this CSS selector appears on the page
+    pub wait_for_selector: Option<String>,
+    /// If true, screencasting starts as soon as the page finished navigating
+    pub wait_for_navigation_idle: bool,
+    /// CSS injected into the page, e.g. to hide cookie banners
+    pub inject_css: Option<String>,
+    /// JavaScript evaluated on the page once its DOM is loaded
+    pub inject_js: Option<String>,
+    /// If set, received, dropped and failed frames are counted in these stats
+    pub stats: Option<Arc<Stats>>,
+    /// Screencasting stops after more than this number of consecutive frame errors within `error_window`,
+    /// see `CircuitBreaker`. Frames which could not be decoded are only logged, see `FtError::is_decode_error`
+    pub error_threshold: u32,
+    /// The maximum time between two frame errors to count them as consecutive
+    pub error_window: Duration,
+    /// If set, a `ShutdownReason::CircuitBreakerTripped` is sent to this channel once screencasting stopped because of errors,
+    /// and a `ShutdownReason::BrowserCrashed` once chrome stopped responding
+    pub shutdown_sender: Option<Sender<ShutdownReason>>,
+    /// The maximum number of frames waiting to be handled by `on_frame`.
+    /// If the queue is full, the oldest frame is dropped in favour of the latest one.
+    pub frame_queue_capacity: usize,
+    /// The maximum number of seconds to wait for the page to load and the first frame to arrive
+    pub page_load_timeout_secs: u64,
+    /// Additional HTTP headers sent with every request of the page, e.g. `("X-Api-Key", "...")`
+    pub extra_headers: Vec<(String, String)>,
+    /// The user and password sent as basic auth `Authorization` header with every request of the page
+    pub basic_auth: Option<(String, String)>,
+    /// Cookies set in the browser before navigating to the page, e.g. a session cookie
+    pub cookies: Vec<Cookie>,
+    /// The device pixel ratio chrome renders the page at. Values above 1.0 render sharper text,
+    /// but produce larger frames which take longer to transfer, decode and downscale to the LED screen.
+    pub device_scale_factor: f32,
+    /// If set, the page is reloaded with this interval while screencasting, e.g. for dashboards without live updates.
+    /// Injected CSS/JS and extra headers are applied to the reloaded page too.
+    pub reload_interval: Option<Duration>,
+    /// The position (x, y) of the frames on the LED screen, used by frame handlers sending them with
+    /// `FlaschenTaschen::send_ppm_at`, e.g. to tile a large LED screen with multiple instances
+    pub offset_x: u32,
+    pub offset_y: u32,
+    /// The layer frames are sent to, used like `offset_x` and `offset_y`
+    pub layer: u8,
+    /// If true, chrome is launched with `--no-sandbox`, which is required to run it as root, e.g. in Docker.
+    /// Without the sandbox, a malicious page exploiting chrome gains the privileges of the chrome process.
+    /// Ignored when connecting to a running browser, like `extra_chrome_args`.
+    pub no_sandbox: bool,
+    /// The proxy chrome sends all requests through, e.g. `http://proxy.local:3128`.
+    /// Ignored when connecting to a running browser, like `extra_chrome_args`.
+    pub proxy: Option<String>,
+    /// A semicolon-separated list of hosts which are requested without the proxy, e.g. `localhost;*.local`
+    pub proxy_bypass_list: Option<String>,
+    /// If true, chrome renders pages without a default background, so pages without an explicit background
+    /// produce transparent frames. Requires `ScreencastImageFormat::Png`, see `ConversionOptions::transparent_overlay`.
+    pub transparent_background: bool,
+    /// If not empty, the pages of this playlist are shown in rotation, each for its duration.
+    /// `url` must be the URL of the first entry, which is shown first.
+    pub playlist: Vec<PlaylistEntry>,
+    /// If set, the tab navigates to each URL received on this channel while screencasting,
+    /// e.g. to change the page without restarting. Navigation errors are logged.
+    /// Shared by clones of these options, each URL is only navigated to by one of their screencasts.
+    pub navigation_receiver: Option<Arc<Mutex<Receiver<String>>>>,
+    /// If true, console messages and log entries of the page are logged at debug level, prefixed with `[page]`
+    pub log_page_console: bool,
+    /// If set, the screencast is restarted when no frame arrived within this duration, e.g. because the page froze.
+    /// If the restarted screencast does not deliver a frame within this duration either,
+    /// a `ShutdownReason::FramesStalled` is sent to `shutdown_sender`.
+    pub frame_timeout: Option<Duration>,
+    /// If set, the JPEG quality is adapted to the ratio of queued frames which were dropped, measured every
+    /// `ADAPTIVE_QUALITY_WINDOW`: by the full frame queue and, if `stats` is set, on the send path, i.e. to respect
+    /// the rate limit (`Stats::frames_rate_limited`) or because sending failed (`Stats::send_errors`).
+    /// It is lowered while many frames are dropped and slowly raised again while hardly any are.
+    /// The quality stays within these bounds (min, max), starting at `jpeg_quality` or the max. Ignored for PNG frames.
+    pub adaptive_quality: Option<(u32, u32)>,
+    /// If set, the screencast is paused while an element matching this CSS selector is on the page,
+    /// e.g. a maintenance banner, and resumed once it disappeared. The LED screen keeps the last frame while paused.
+    /// The page is checked every `PAUSE_POLL_INTERVAL`.
+    pub pause_on_selector: Option<String>,
+    /// Chrome only captures every nth frame of the page, e.g. 2 for half of the frames. Unlike `max_fps`,
+    /// skipped frames are never rendered into a screenshot, which saves CPU in the browser. Must be at least 1.
+    pub capture_every_nth_frame: u32,
+    /// If set, the screencast only starts once this JavaScript expression evaluates to a truthy value on the page,
+    /// e.g. `window.chartLoaded === true`. The expression is polled every `READY_POLL_INTERVAL` for up to
+    /// `READY_EXPRESSION_TIMEOUT` and checked again after each reload of `reload_interval` or `reload_pages`.
+    pub ready_expression: Option<String>,
+    /// If set, called with the tab once the page is ready, after `wait_for_selector` and `ready_expression`,
+    /// and before the screencast starts, e.g. to accept a cookie banner or to log in.
+    /// An error aborts starting the screencast. It is not called again after reloads or page switches.
+    pub on_ready: Option<TabInteraction>,
+    /// If set, the bounding box of this element is looked up every `CAPTURE_ELEMENT_POLL_INTERVAL`,
+    /// frames converted with the same element in `ConversionOptions::capture_element` are cropped to it.
+    pub capture_element: Option<TrackedElement>,
 }
 
-/// Provides a connection context to a flaschentaschen server
-pub struct FlaschenTaschen {
-    address: String,
-    pub socket: UdpSocket,
+/// Custom interactions run on the tab before screencasting, see `ScreencastOptions::on_ready`.
+/// The tab is re-exported as `flaschentaschen_web::headless_chrome::Tab`.
+pub type TabInteraction = Arc<dyn Fn(&Tab) -> Result<()> + Send + Sync>;
+
+impl ScreencastOptions {
+    /// Returns a builder for screencasting the given URL at the given dimensions,
+    /// using the defaults of `ScreencastOptionsBuilder` for all other options.
+    pub fn builder(url: impl Into<String>, width: u32, height: u32) -> ScreencastOptionsBuilder {
+        ScreencastOptionsBuilder::new(url, width, height)
+    }
+
+    /// Returns options screencasting the given region with the same settings as these options.
+    /// The region is rendered at its own dimensions and offset by its position, see `ScreencastOptions::offset_x`.
+    /// Playlists and navigation are not copied, a region always shows its own URL.
+    pub fn for_region(&self, region: &RegionSpec) -> ScreencastOptions {
+        ScreencastOptions {
+            url: region.url.clone(),
+            width: region.width,
+            height: region.height,
+            offset_x: self.offset_x + region.x,
+            offset_y: self.offset_y + region.y,
+            playlist: Vec::new(),
+            navigation_receiver: None,
+            ..self.clone()
+        }
+    }
 }
-impl FlaschenTaschen {
-    /// Returns a new flaschentaschen instance for the given host/port.
-    pub fn new(host_port: String) -> Result<FlaschenTaschen> {
-        let socket = UdpSocket::bind("[::]:0")?; // bind local UDP socket
-        socket.connect(&host_port)?;
-        Ok(FlaschenTaschen {
-            address: host_port,
-            socket,
-        })
+
+/// Builds `ScreencastOptions` with chainable setters, so callers only need to set the options they care about.
+/// Options which are not set keep their defaults: JPEG frames, a launched headless chrome, no fps limit,
+/// an error threshold of 100 errors within 10s, 2 queued frames, a page load timeout of 30s and a device scale factor of 1.
+pub struct ScreencastOptionsBuilder {
+    opts: ScreencastOptions,
+}
+impl ScreencastOptionsBuilder {
+    /// Returns a new builder for screencasting the given URL at the given dimensions.
+    pub fn new(url: impl Into<String>, width: u32, height: u32) -> ScreencastOptionsBuilder {
+        ScreencastOptionsBuilder {
+            opts: ScreencastOptions {
+                url: url.into(),
+                width,
+                height,
+                format: ScreencastImageFormat::Jpeg,
+                max_fps: None,
+                user_agent: None,
+                extra_chrome_args: Vec::new(),
+                browser_source: BrowserSource::Launch,
+                jpeg_quality: None,
+                wait_for_selector: None,
+                wait_for_navigation_idle: false,
+                inject_css: None,
+                inject_js: None,
+                stats: None,
+                error_threshold: 100,
+                error_window: Duration::from_secs(10),
+                shutdown_sender: None,
+                frame_queue_capacity: 2,
+                page_load_timeout_secs: 30,
+                extra_headers: Vec::new(),
+                basic_auth: None,
+                cookies: Vec::new(),
+                device_scale_factor: 1.0,
+                reload_interval: None,
+                offset_x: 0,
+                offset_y: 0,
+                layer: 0,
+                no_sandbox: false,
+                proxy: None,
+                proxy_bypass_list: None,
+                transparent_background: false,
+                playlist: Vec::new(),
+                navigation_receiver: None,
+                log_page_console: false,
+                frame_timeout: None,
+                adaptive_quality: None,
+                pause_on_selector: None,
+                capture_every_nth_frame: 1,
+                ready_expression: None,
+                on_ready: None,
+                capture_element: None,
+            },
+        }
     }
 
-    /// Sends a given PPM byte slice this flaschentaschen server.
-    pub fn send_ppm(&self, ppm: &[u8]) -> Result<usize> {
-        self.socket
-            .send(ppm)
-            .map_err(|err| eyre!("failed to send PPM to {}: {}", self, err))
+    /// Sets `ScreencastOptions::format`.
+    pub fn format(mut self, format: ScreencastImageFormat) -> Self {
+        self.opts.format = format;
+        self
+    }
+
+    /// Sets `ScreencastOptions::max_fps`.
+    pub fn max_fps(mut self, max_fps: impl Into<Option<u32>>) -> Self {
+        self.opts.max_fps = max_fps.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::user_agent`.
+    pub fn user_agent(mut self, user_agent: impl Into<Option<String>>) -> Self {
+        self.opts.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::extra_chrome_args`.
+    pub fn extra_chrome_args(mut self, extra_chrome_args: Vec<OsString>) -> Self {
+        self.opts.extra_chrome_args = extra_chrome_args;
+        self
+    }
+
+    /// Sets `ScreencastOptions::browser_source`.
+    pub fn browser_source(mut self, browser_source: BrowserSource) -> Self {
+        self.opts.browser_source = browser_source;
+        self
+    }
+
+    /// Sets `ScreencastOptions::jpeg_quality`.
+    pub fn jpeg_quality(mut self, jpeg_quality: impl Into<Option<u32>>) -> Self {
+        self.opts.jpeg_quality = jpeg_quality.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::wait_for_selector`.
+    pub fn wait_for_selector(mut self, wait_for_selector: impl Into<Option<String>>) -> Self {
+        self.opts.wait_for_selector = wait_for_selector.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::wait_for_navigation_idle`.
+    pub fn wait_for_navigation_idle(mut self, wait_for_navigation_idle: bool) -> Self {
+        self.opts.wait_for_navigation_idle = wait_for_navigation_idle;
+        self
+    }
+
+    /// Sets `ScreencastOptions::inject_css`.
+    pub fn inject_css(mut self, inject_css: impl Into<Option<String>>) -> Self {
+        self.opts.inject_css = inject_css.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::inject_js`.
+    pub fn inject_js(mut self, inject_js: impl Into<Option<String>>) -> Self {
+        self.opts.inject_js = inject_js.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::stats`.
+    pub fn stats(mut self, stats: impl Into<Option<Arc<Stats>>>) -> Self {
+        self.opts.stats = stats.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::error_threshold`.
+    pub fn error_threshold(mut self, error_threshold: u32) -> Self {
+        self.opts.error_threshold = error_threshold;
+        self
+    }
+
+    /// Sets `ScreencastOptions::error_window`.
+    pub fn error_window(mut self, error_window: Duration) -> Self {
+        self.opts.error_window = error_window;
+        self
+    }
+
+    /// Sets `ScreencastOptions::shutdown_sender`.
+    pub fn shutdown_sender(
+        mut self,
+        shutdown_sender: impl Into<Option<Sender<ShutdownReason>>>,
+    ) -> Self {
+        self.opts.shutdown_sender = shutdown_sender.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::frame_queue_capacity`.
+    pub fn frame_queue_capacity(mut self, frame_queue_capacity: usize) -> Self {
+        self.opts.frame_queue_capacity = frame_queue_capacity;
+        self
+    }
+
+    /// Sets `ScreencastOptions::page_load_timeout_secs`.
+    pub fn page_load_timeout_secs(mut self, page_load_timeout_secs: u64) -> Self {
+        self.opts.page_load_timeout_secs = page_load_timeout_secs;
+        self
+    }
+
+    /// Sets `ScreencastOptions::extra_headers`.
+    pub fn extra_headers(mut self, extra_headers: Vec<(String, String)>) -> Self {
+        self.opts.extra_headers = extra_headers;
+        self
+    }
+
+    /// Sets `ScreencastOptions::basic_auth`.
+    pub fn basic_auth(mut self, basic_auth: impl Into<Option<(String, String)>>) -> Self {
+        self.opts.basic_auth = basic_auth.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::cookies`.
+    pub fn cookies(mut self, cookies: Vec<Cookie>) -> Self {
+        self.opts.cookies = cookies;
+        self
+    }
+
+    /// Sets `ScreencastOptions::device_scale_factor`.
+    pub fn device_scale_factor(mut self, device_scale_factor: f32) -> Self {
+        self.opts.device_scale_factor = device_scale_factor;
+        self
+    }
+
+    /// Sets `ScreencastOptions::reload_interval`.
+    pub fn reload_interval(mut self, reload_interval: impl Into<Option<Duration>>) -> Self {
+        self.opts.reload_interval = reload_interval.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::offset_x`, `ScreencastOptions::offset_y` and `ScreencastOptions::layer`.
+    pub fn offset(mut self, x: u32, y: u32, layer: u8) -> Self {
+        self.opts.offset_x = x;
+        self.opts.offset_y = y;
+        self.opts.layer = layer;
+        self
+    }
+
+    /// Sets `ScreencastOptions::no_sandbox`.
+    pub fn no_sandbox(mut self, no_sandbox: bool) -> Self {
+        self.opts.no_sandbox = no_sandbox;
+        self
+    }
+
+    /// Sets `ScreencastOptions::proxy`.
+    pub fn proxy(mut self, proxy: impl Into<Option<String>>) -> Self {
+        self.opts.proxy = proxy.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::proxy_bypass_list`.
+    pub fn proxy_bypass_list(mut self, proxy_bypass_list: impl Into<Option<String>>) -> Self {
+        self.opts.proxy_bypass_list = proxy_bypass_list.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::transparent_background`.
+    pub fn transparent_background(mut self, transparent_background: bool) -> Self {
+        self.opts.transparent_background = transparent_background;
+        self
+    }
+
+    /// Sets `ScreencastOptions::playlist`.
+    pub fn playlist(mut self, playlist: Vec<PlaylistEntry>) -> Self {
+        self.opts.playlist = playlist;
+        self
+    }
+
+    /// Sets `ScreencastOptions::navigation_receiver`.
+    pub fn navigation_receiver(
+        mut self,
+        navigation_receiver: impl Into<Option<Receiver<String>>>,
+    ) -> Self {
+        self.opts.navigation_receiver = navigation_receiver
+            .into()
+            .map(|receiver| Arc::new(Mutex::new(receiver)));
+        self
+    }
+
+    /// Sets `ScreencastOptions::log_page_console`.
+    pub fn log_page_console(mut self, log_page_console: bool) -> Self {
+        self.opts.log_page_console = log_page_console;
+        self
+    }
+
+    /// Sets `ScreencastOptions::frame_timeout`.
+    pub fn frame_timeout(mut self, frame_timeout: impl Into<Option<Duration>>) -> Self {
+        self.opts.frame_timeout = frame_timeout.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::adaptive_quality`.
+    pub fn adaptive_quality(mut self, bounds: impl Into<Option<(u32, u32)>>) -> Self {
+        self.opts.adaptive_quality = bounds.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::pause_on_selector`.
+    pub fn pause_on_selector(mut self, selector: impl Into<Option<String>>) -> Self {
+        self.opts.pause_on_selector = selector.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::capture_every_nth_frame`.
+    pub fn capture_every_nth_frame(mut self, every_nth_frame: u32) -> Self {
+        self.opts.capture_every_nth_frame = every_nth_frame;
+        self
+    }
+
+    /// Sets `ScreencastOptions::ready_expression`.
+    pub fn ready_expression(mut self, expression: impl Into<Option<String>>) -> Self {
+        self.opts.ready_expression = expression.into();
+        self
+    }
+
+    /// Sets `ScreencastOptions::on_ready`.
+    pub fn on_ready(
+        mut self,
+        on_ready: impl Fn(&Tab) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.opts.on_ready = Some(Arc::new(on_ready));
+        self
+    }
+
+    /// Sets `ScreencastOptions::capture_element`.
+    pub fn capture_element(mut self, element: impl Into<Option<TrackedElement>>) -> Self {
+        self.opts.capture_element = element.into();
+        self
+    }
+
+    /// Returns the built options after validating them.
+    pub fn build(self) -> Result<ScreencastOptions> {
+        let opts = self.opts;
+        if opts.width == 0 || opts.height == 0 {
+            return Err(eyre!(
+                "invalid dimensions {}x{}, expected a non-zero width and height",
+                opts.width,
+                opts.height
+            ));
+        }
+        if let Some(quality) = opts.jpeg_quality {
+            if quality > 100 {
+                return Err(eyre!("invalid JPEG quality {}, expected 0-100", quality));
+            }
+        }
+        if let Some((min_quality, max_quality)) = opts.adaptive_quality {
+            if min_quality > max_quality || max_quality > 100 {
+                return Err(eyre!(
+                    "invalid adaptive quality bounds {}-{}, expected 0-100 with the min not above the max",
+                    min_quality,
+                    max_quality
+                ));
+            }
+        }
+        if opts.capture_every_nth_frame == 0 {
+            return Err(eyre!(
+                "invalid capture_every_nth_frame 0, expected at least 1"
+            ));
+        }
+        if opts.device_scale_factor <= 0.0 {
+            return Err(eyre!(
+                "invalid device scale factor {}, expected a positive number",
+                opts.device_scale_factor
+            ));
+        }
+        if let Some(proxy) = &opts.proxy {
+            // chrome silently ignores credentials in --proxy-server, the proxy itself is therefore not printed:
+            if proxy.contains('@') {
+                return Err(eyre!(
+                    "invalid proxy, chrome does not support proxy credentials, use a proxy without authentication"
+                ));
+            }
+        }
+        if opts.transparent_background && opts.format == ScreencastImageFormat::Jpeg {
+            return Err(eyre!(
+                "transparent backgrounds require PNG frames, JPEG has no alpha channel"
+            ));
+        }
+        Ok(opts)
     }
 }
-impl Display for FlaschenTaschen {
+
+/// The reason why the process should shut down, see `wait_for_shutdown`
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShutdownReason {
+    /// The process received the given signal
+    Signal(i32),
+    /// Screencasting stopped because too many frames failed
+    CircuitBreakerTripped,
+    /// The chrome instance crashed or the connection to it was lost
+    BrowserCrashed,
+    /// Chrome stopped sending frames, even after restarting the screencast
+    FramesStalled,
+    /// The maximum runtime of the process elapsed
+    MaxRuntimeElapsed,
+    /// A flaschentaschen server stopped answering the liveness probe, see `liveness::spawn_liveness_probe`
+    ServerUnresponsive,
+}
+impl Display for ShutdownReason {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "FlaschenTaschen@{}", self.address)
+        match self {
+            ShutdownReason::Signal(sig) => write!(f, "received signal {}", sig),
+            ShutdownReason::CircuitBreakerTripped => {
+                write!(f, "screencasting stopped after too many consecutive errors")
+            }
+            ShutdownReason::BrowserCrashed => write!(f, "chrome is not responding anymore"),
+            ShutdownReason::FramesStalled => {
+                write!(
+                    f,
+                    "chrome stopped sending frames, even after restarting the screencast"
+                )
+            }
+            ShutdownReason::MaxRuntimeElapsed => write!(f, "the maximum runtime elapsed"),
+            ShutdownReason::ServerUnresponsive => {
+                write!(
+                    f,
+                    "a flaschentaschen server stopped answering the liveness probe"
+                )
+            }
+        }
     }
 }
 
-/// Starts the screencasting process by:
-/// 1. spawing a new chrome instance
-/// 2. navigating to the given URL
-/// 3. attaching an event handler for incoming frames which forwards them to the given `on_frame` callback.
-/// This method will return the created browser instance. It is important to keep the returned instance in scope.
-/// If it goes out of scope or the main thread terminates, the browser will be stopped too and screencasting halts.
-///
-/// This function will call the provided callback for each received frame together with the given static context.
-/// This is necessary because the callback will run in a separate thread.
-pub fn start_screencasting<F, C>(
-    opts: ScreencastOptions,
-    on_frame: F,
-    on_frame_context: &'static C,
-) -> Result<Browser>
-where
-    C: Send + Sync,
-    F: 'static + Fn(&Page::events::ScreencastFrameEvent, &'static C) -> Result<()> + Send + Sync,
-{
-    info!(
-        "starting chrome in headless mode with dimensions {}x{}",
-        opts.width, opts.height
-    );
+/// How often the watchdog started by `start_screencasting` checks if chrome is still responding.
+const BROWSER_WATCHDOG_INTERVAL: Duration = Duration::from_secs(2);
 
-    // open the browser on the provided URL:
-    let browser = map_err(
-        Browser::new(headless_chrome::LaunchOptions {
-            headless: true,
-            window_size: Some((opts.width, opts.height)),
-            ..Default::default()
-        }),
-        "Failed to launch browser",
-    )?;
-    let tab = map_err(browser.wait_for_initial_tab(), "Could not open new tab")?;
-    map_err(
-        tab.navigate_to(opts.url.as_str()),
-        format!("Could not navigate to {}", opts.url).as_str(),
-    )?;
-    let closure_tab = tab.clone();
+/// The number of consecutive failed checks after which the watchdog considers chrome crashed.
+const BROWSER_WATCHDOG_MAX_FAILURES: u32 = 3;
 
-    // register the event handler for incoming screencast frames.
-    // `consecutive_err_count` will count consecutive errors while handling incoming frames to stop screencasting
-    // as soon as a threshold is reached.
-    let consecutive_err_count = Arc::new(Mutex::new(0));
-    let event_listener = move |event: &Event| match event {
-        Event::PageScreencastFrame(frame) => {
-            let mut current_err_count = consecutive_err_count.lock().unwrap();
-            trace!(
-                "got frame: {}",
-                frame.params.metadata.timestamp.expect("missing timestamp")
-            );
+/// The number of queued frames from which the frame queue of `start_screencasting` counts as backlogged,
+/// capped at `ScreencastOptions::frame_queue_capacity`.
+const QUEUE_BACKLOG_THRESHOLD: usize = 2;
 
-            // we do catch potential errors but only log them and continue with the next frame.
-            // if we get more than a fixed threshold of consecutive errors, we stop the screencasting
-            let callback_result = on_frame(frame, on_frame_context);
-            if callback_result.is_ok() {
-                *current_err_count = 0;
-            } else {
-                *current_err_count += 1;
-                error!(
-                    "frame handler failed (consecutive errors: {}): {}",
-                    current_err_count,
-                    callback_result.unwrap_err()
-                );
-            }
+/// How long the frame queue needs to stay backlogged before a warning is logged, at most once per period.
+const QUEUE_BACKLOG_WARNING_PERIOD: Duration = Duration::from_secs(5);
 
-            // TODO: for some reason, UdpSocket.send will return Ok() even if the server is not reachable.
-            // this will wrongly reset the consecutive error count.
-            if *current_err_count > 1000 {
-                let _ = closure_tab
-                    .call_method(Page::StopScreencast(Some(serde_json::value::Value::Null)));
-            } else {
-                let _ = closure_tab.call_method(Page::ScreencastFrameAck {
-                    session_id: frame.params.session_id,
-                });
-            }
+/// How often the worker thread started by `start_screencasting` logs the rate of handled frames at debug level.
+const THROUGHPUT_LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How often the frame watchdog started by `start_screencasting` checks when the last frame arrived.
+const FRAME_WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The period over which the ratio of dropped frames is measured, see `ScreencastOptions::adaptive_quality`.
+const ADAPTIVE_QUALITY_WINDOW: Duration = Duration::from_secs(5);
+/// The amount the JPEG quality is lowered by per window in which many frames were dropped.
+const ADAPTIVE_QUALITY_LOWER_STEP: u32 = 10;
+/// The JPEG quality is lowered by `ADAPTIVE_QUALITY_LOWER_STEP` if more than this ratio of frames was dropped
+/// within a window.
+const ADAPTIVE_QUALITY_LOWER_RATIO: f64 = 0.1;
+/// The JPEG quality is raised if at most this ratio of frames was dropped within a window.
+const ADAPTIVE_QUALITY_RAISE_RATIO: f64 = 0.01;
+/// The amount the JPEG quality is raised by per window, lower than `ADAPTIVE_QUALITY_LOWER_STEP` to approach
+/// the max slowly instead of oscillating around the quality the link can handle.
+const ADAPTIVE_QUALITY_RAISE_STEP: u32 = 5;
+
+/// How often the page is checked for the element of `ScreencastOptions::pause_on_selector`.
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the bounding box of `ScreencastOptions::capture_element` is looked up.
+const CAPTURE_ELEMENT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the thread of `ScreencastOptions::navigation_receiver` waits for a URL before checking if it was stopped.
+const NAVIGATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// An element of the page frames are cropped to, e.g. a single widget of a dashboard.
+/// Clones share the bounds: the screencast updates them while the page reflows, see `ScreencastOptions::capture_element`,
+/// and the conversion crops frames to them, see `ConversionOptions::capture_element`.
+#[derive(Clone, Debug)]
+pub struct TrackedElement {
+    pub selector: String,
+    bounds: Arc<Mutex<Option<(u32, u32, u32, u32)>>>,
+}
+impl TrackedElement {
+    /// Returns a new element for the given CSS selector whose bounds are not known yet.
+    pub fn new(selector: impl Into<String>) -> TrackedElement {
+        TrackedElement {
+            selector: selector.into(),
+            bounds: Arc::new(Mutex::new(None)),
         }
-        _ => {}
-    };
-    map_err(
-        tab.add_event_listener(Arc::new(event_listener)),
-        "Failed to attach event listener to tab",
-    )?;
+    }
 
-    // tell chrome to start screencasting:
-    map_err(
-        tab.call_method(Page::StartScreencast {
-            every_nth_frame: Some(1),
-            format: Some(StartScreencastFormatOption::Jpeg),
-            max_height: Some(opts.height),
-            max_width: Some(opts.width),
-            quality: Some(100),
-        }),
-        "failed to start screencasting",
-    )?;
+    /// Returns the last known bounding box (x, y, width, height) of the element in frame pixels,
+    /// or `None` if it is not on the page.
+    pub fn bounds(&self) -> Option<(u32, u32, u32, u32)> {
+        *self.bounds.lock().unwrap()
+    }
 
-    Ok(browser)
+    /// Sets the bounding box of the element, `None` if it is not on the page.
+    pub fn set_bounds(&self, bounds: Option<(u32, u32, u32, u32)>) {
+        *self.bounds.lock().unwrap() = bounds;
+    }
 }
 
-/// Accepts a base64 encoded string of a JPEG image and returns its PPM counterpart as a byte vector.
-pub fn get_ppm_from_jpeg(base64_str: &String) -> Result<Vec<u8>> {
-    let buffer = base64::decode(base64_str)?;
-    let input_image = load_from_memory_with_format(buffer.as_slice(), ImageFormat::Jpeg)?;
+/// Returns the border box of the element matching the given selector in frame pixels,
+/// i.e. scaled by the device scale factor. Elements without visible area have no bounds.
+fn element_bounds(
+    tab: &Tab,
+    selector: &str,
+    device_scale_factor: f32,
+) -> Option<(u32, u32, u32, u32)> {
+    let viewport = tab
+        .find_element(selector)
+        .and_then(|element| element.get_box_model())
+        .ok()?
+        .border_viewport();
+    let scale = |value: f64| (value.max(0.0) * device_scale_factor as f64).round() as u32;
+    match (scale(viewport.width), scale(viewport.height)) {
+        (0, _) | (_, 0) => None,
+        (width, height) => Some((scale(viewport.x), scale(viewport.y), width, height)),
+    }
+}
 
-    let mut output: Vec<u8> = Vec::new();
-    input_image.write_to(
-        &mut output,
-        // PPM with magic P6:
-        ImageOutputFormat::Pnm(PNMSubtype::Pixmap(SampleEncoding::Binary)),
-    )?;
+/// The initial delay before reconnecting to a flaschentaschen server after a failed send.
+/// The delay is doubled after each failed attempt until `FlaschenTaschen::max_reconnect_backoff` is reached.
+const INITIAL_RECONNECT_BACKOFF: Duration = Duration::from_millis(50);
 
-    Ok(output)
+/// When a `FlaschenTaschen` reconnects after failed sends, see `FlaschenTaschen::max_reconnect_backoff`
+struct ReconnectBackoff {
+    /// The delay before the attempt following the next failed one
+    delay: Duration,
+    /// The earliest time of the next attempt, `None` if the last send succeeded
+    next_attempt: Option<Instant>,
+}
+impl Default for ReconnectBackoff {
+    fn default() -> Self {
+        ReconnectBackoff {
+            delay: INITIAL_RECONNECT_BACKOFF,
+            next_attempt: None,
+        }
+    }
+}
+
+/// The maximum payload of a single UDP datagram over IPv4 (65535 bytes minus the IP and UDP headers).
+pub const MAX_DATAGRAM_SIZE: usize = 65507;
+
+/// The maximum time to wait for a TCP connection to a flaschentaschen server, see `TcpSink`.
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The maximum time a frame may take to be written to a TCP connection. Writes block while the server
+/// does not keep up, which throttles the screencast, but a server which stopped reading fails the send.
+const TCP_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The datagram sent by `FlaschenTaschen::query_size` to ask a server for its dimensions.
+pub const SIZE_QUERY: &[u8] = b"?\n";
+
+/// The maximum time to wait for the response of a `SIZE_QUERY`.
+pub const SIZE_QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Parses the response to a `SIZE_QUERY` of the form `<width>x<height>` or `<width> <height>`.
+fn parse_size_response(response: &[u8]) -> Option<(u32, u32)> {
+    let response = std::str::from_utf8(response).ok()?;
+    let mut parts = response
+        .split(|c: char| c == 'x' || c.is_ascii_whitespace())
+        .filter(|part| !part.is_empty())
+        .map(str::parse::<u32>);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(width)), Some(Ok(height)), None) if width > 0 && height > 0 => {
+            Some((width, height))
+        }
+        _ => None,
+    }
+}
+
+/// Resolves the given host/port to the first of its socket addresses.
+fn resolve_address(host_port: &str) -> std::io::Result<SocketAddr> {
+    host_port.to_socket_addrs()?.next().ok_or_else(|| {
+        std::io::Error::new(
+            ErrorKind::NotFound,
+            "the host did not resolve to any address",
+        )
+    })
+}
+
+/// The encoding of the frames sent to a server
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputProtocol {
+    /// Binary PPM datagrams as expected by flaschentaschen servers
+    #[clap(name = "ppm")]
+    #[serde(rename = "ppm")]
+    FlaschenTaschenPpm,
+    /// Only the RGB bytes of the pixels without any header, e.g. for LED controllers expecting a raw framebuffer
+    RawRgb,
+}
+
+/// How frames are sent to a server, given by the scheme of its endpoint, see `FtTransport::of`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FtTransport {
+    /// UDP datagrams to a host/port, e.g. `localhost:1337`
+    Udp,
+    /// Datagrams to a Unix domain socket, e.g. `unix:/run/ft-bridge.sock` of a local bridge. Only supported on Unix
+    UnixDatagram,
+    /// A stream of PPMs over a persistent TCP connection to a host/port, e.g. `tcp:localhost:1337`,
+    /// for servers accepting frames over TCP. Unlike UDP, lost frames and unreachable servers fail the send.
+    /// See `TcpSink`.
+    Tcp,
+}
+impl FtTransport {
+    /// The scheme of endpoints of Unix domain sockets, followed by the path of the socket
+    pub const UNIX_SCHEME: &'static str = "unix:";
+    /// The scheme of endpoints connected over TCP, followed by the host/port
+    pub const TCP_SCHEME: &'static str = "tcp:";
+
+    /// Returns the transport of the given endpoint and its address without the scheme,
+    /// e.g. `(FtTransport::UnixDatagram, "/run/ft-bridge.sock")` for `unix:/run/ft-bridge.sock`.
+    /// Endpoints without a scheme are UDP host/ports.
+    pub fn of(endpoint: &str) -> (FtTransport, &str) {
+        if let Some(path) = endpoint.strip_prefix(FtTransport::UNIX_SCHEME) {
+            return (FtTransport::UnixDatagram, path);
+        }
+        match endpoint.strip_prefix(FtTransport::TCP_SCHEME) {
+            Some(host_port) => (FtTransport::Tcp, host_port),
+            None => (FtTransport::Udp, endpoint),
+        }
+    }
+
+    /// Returns true if frames are sent as single datagrams, which limits them to `MAX_DATAGRAM_SIZE`.
+    pub fn is_datagram(self) -> bool {
+        self != FtTransport::Tcp
+    }
+}
+
+/// A persistent TCP connection to a flaschentaschen server, see `FtTransport::Tcp`.
+/// Each frame is written completely before the next one, the PPM header delimits the frames in the stream.
+/// Once a write failed, the connection is dropped and re-established by the next send,
+/// a half-written frame would corrupt the stream otherwise.
+pub struct TcpSink {
+    remote_addr: SocketAddr,
+    stream: Mutex<Option<TcpStream>>,
+}
+impl TcpSink {
+    /// Connects to the given host/port, failing if the server does not accept the connection
+    /// within `TCP_CONNECT_TIMEOUT`.
+    pub fn connect(host_port: &str) -> std::io::Result<TcpSink> {
+        let remote_addr = resolve_address(host_port)?;
+        let stream = TcpSink::open_stream(remote_addr)?;
+        Ok(TcpSink {
+            remote_addr,
+            stream: Mutex::new(Some(stream)),
+        })
+    }
+
+    fn open_stream(remote_addr: SocketAddr) -> std::io::Result<TcpStream> {
+        let stream = TcpStream::connect_timeout(&remote_addr, TCP_CONNECT_TIMEOUT)?;
+        // frames are written in one go, waiting for more data only delays them:
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(TCP_WRITE_TIMEOUT))?;
+        Ok(stream)
+    }
+
+    /// Writes the given frame to the connection, reconnecting first if a previous write failed.
+    /// Blocks until the frame was handed to the operating system, at most `TCP_WRITE_TIMEOUT`.
+    pub fn send(&self, frame: &[u8]) -> std::io::Result<usize> {
+        let mut stream = self.stream.lock().unwrap();
+        if stream.is_none() {
+            *stream = Some(TcpSink::open_stream(self.remote_addr)?);
+        }
+        match stream.as_mut().unwrap().write_all(frame) {
+            Ok(()) => Ok(frame.len()),
+            Err(err) => {
+                *stream = None;
+                Err(err)
+            }
+        }
+    }
+}
+
+/// The connected socket of a `FlaschenTaschen`, see `FtTransport`
+enum FtSocket {
+    Udp(UdpSocket),
+    #[cfg(unix)]
+    UnixDatagram(UnixDatagram),
+    Tcp(TcpSink),
+}
+impl FtSocket {
+    fn send(&self, buffer: &[u8]) -> std::io::Result<usize> {
+        match self {
+            FtSocket::Udp(socket) => socket.send(buffer),
+            #[cfg(unix)]
+            FtSocket::UnixDatagram(socket) => socket.send(buffer),
+            FtSocket::Tcp(sink) => sink.send(buffer),
+        }
+    }
+
+    /// Receives a datagram. TCP connections never receive anything, servers only read the stream of frames.
+    fn recv(&self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            FtSocket::Udp(socket) => socket.recv(buffer),
+            #[cfg(unix)]
+            FtSocket::UnixDatagram(socket) => socket.recv(buffer),
+            FtSocket::Tcp(_) => Err(std::io::Error::new(
+                ErrorKind::Other,
+                "flaschentaschen servers do not answer over TCP",
+            )),
+        }
+    }
+
+    /// Returns and clears the pending error of the socket, e.g. a connection-refused error reported by an ICMP message.
+    /// TCP connections report their errors on the next send instead.
+    fn take_error(&self) -> std::io::Result<Option<std::io::Error>> {
+        match self {
+            FtSocket::Udp(socket) => socket.take_error(),
+            #[cfg(unix)]
+            FtSocket::UnixDatagram(socket) => socket.take_error(),
+            FtSocket::Tcp(_) => Ok(None),
+        }
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            FtSocket::Udp(socket) => socket.set_read_timeout(timeout),
+            #[cfg(unix)]
+            FtSocket::UnixDatagram(socket) => socket.set_read_timeout(timeout),
+            FtSocket::Tcp(_) => Ok(()),
+        }
+    }
+}
+
+/// Provides a connection context to a flaschentaschen server
+pub struct FlaschenTaschen {
+    address: String,
+    bind_addr: Option<String>,
+    socket: RwLock<FtSocket>,
+    /// The maximum delay between two reconnection attempts after a failed send.
+    /// If `None`, failed sends are not retried.
+    pub max_reconnect_backoff: Option<Duration>,
+    reconnect_backoff: Mutex<ReconnectBackoff>,
+    /// If true, frames are only logged instead of being sent, e.g. to test the rendering without a server.
+    pub dry_run: bool,
+    /// The encoding `send_ppm` sends frames with, PPM by default.
+    pub output_protocol: OutputProtocol,
+    /// If set, smaller datagrams are padded with zeros to this size, for receivers expecting datagrams of a fixed size.
+    /// Servers ignore the bytes after a complete PPM. Larger datagrams are sent unchanged.
+    /// Must not exceed `MAX_DATAGRAM_SIZE`. Ignored for `FtTransport::Tcp`, the zeros would corrupt the stream.
+    pub pad_datagram: Option<usize>,
+    padded: Mutex<Vec<u8>>,
+}
+impl FlaschenTaschen {
+    /// Returns a new flaschentaschen instance for the given host/port, for the given Unix domain socket
+    /// if prefixed with `unix:`, or connected over TCP if prefixed with `tcp:`, see `FtTransport`.
+    pub fn new(host_port: String) -> FtResult<FlaschenTaschen> {
+        FlaschenTaschen::with_bind(host_port, None)
+    }
+
+    /// Returns a new flaschentaschen instance for the given host/port, sending from the given local address,
+    /// e.g. `192.168.1.10:0` to send from a specific interface.
+    /// If `bind_addr` is `None`, the wildcard address of the address family of the resolved host is used.
+    /// `bind_addr` is ignored for Unix domain sockets, they are sent from an unnamed socket, and for TCP connections.
+    /// Connecting over TCP fails if the server does not accept the connection.
+    pub fn with_bind(host_port: String, bind_addr: Option<String>) -> FtResult<FlaschenTaschen> {
+        let socket = FlaschenTaschen::connect_socket(&host_port, bind_addr.as_deref())?;
+        Ok(FlaschenTaschen::with_socket(host_port, bind_addr, socket))
+    }
+
+    /// Returns a new flaschentaschen instance for the given endpoint which only logs frames, see `dry_run`.
+    /// Nothing is connected, so the server does not need to be running, not even for TCP connections.
+    pub fn new_dry_run(host_port: String) -> FtResult<FlaschenTaschen> {
+        // frames are never sent, the unconnected socket only takes the place of the connection:
+        let socket = UdpSocket::bind("127.0.0.1:0").map_err(|source| FtError::SocketBind {
+            address: host_port.clone(),
+            source,
+        })?;
+        let mut flaschentaschen =
+            FlaschenTaschen::with_socket(host_port, None, FtSocket::Udp(socket));
+        flaschentaschen.dry_run = true;
+        Ok(flaschentaschen)
+    }
+
+    fn with_socket(
+        host_port: String,
+        bind_addr: Option<String>,
+        socket: FtSocket,
+    ) -> FlaschenTaschen {
+        FlaschenTaschen {
+            address: host_port,
+            bind_addr,
+            socket: RwLock::new(socket),
+            max_reconnect_backoff: None,
+            reconnect_backoff: Mutex::new(ReconnectBackoff::default()),
+            dry_run: false,
+            output_protocol: OutputProtocol::FlaschenTaschenPpm,
+            pad_datagram: None,
+            padded: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Binds a new local socket to the given address and connects it to the given endpoint, see `FtTransport`.
+    fn connect_socket(endpoint: &str, bind_addr: Option<&str>) -> FtResult<FtSocket> {
+        let bind_error = |address: &str, source| FtError::SocketBind {
+            address: address.to_string(),
+            source,
+        };
+        let host_port = match FtTransport::of(endpoint) {
+            (FtTransport::Udp, host_port) => host_port,
+            (FtTransport::Tcp, host_port) => {
+                let sink = TcpSink::connect(host_port).map_err(|source| match source.kind() {
+                    ErrorKind::ConnectionRefused => FtError::ConnectionRefused {
+                        address: endpoint.to_string(),
+                    },
+                    _ => bind_error(host_port, source),
+                })?;
+                return Ok(FtSocket::Tcp(sink));
+            }
+            #[cfg(unix)]
+            (FtTransport::UnixDatagram, path) => {
+                let socket = UnixDatagram::unbound().map_err(|source| bind_error(path, source))?;
+                socket
+                    .connect(path)
+                    .map_err(|source| bind_error(path, source))?;
+                return Ok(FtSocket::UnixDatagram(socket));
+            }
+            #[cfg(not(unix))]
+            (FtTransport::UnixDatagram, path) => {
+                return Err(bind_error(
+                    path,
+                    std::io::Error::new(
+                        ErrorKind::Other,
+                        "Unix domain sockets are not supported on this platform",
+                    ),
+                ))
+            }
+        };
+        let remote_addr =
+            resolve_address(host_port).map_err(|source| bind_error(host_port, source))?;
+        // not all platforms support sending to IPv4 addresses from IPv6 sockets,
+        // the wildcard address therefore needs to match the family of the remote address:
+        let bind_addr = bind_addr.unwrap_or(if remote_addr.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        });
+        // reports the server instead of the local wildcard address, which would not tell which endpoint failed:
+        let socket = UdpSocket::bind(bind_addr).map_err(|source| bind_error(host_port, source))?;
+        socket
+            .connect(remote_addr)
+            .map_err(|source| bind_error(host_port, source))?;
+        Ok(FtSocket::Udp(socket))
+    }
+
+    /// Returns a new instance for the same server and local address as this one, but with its own socket,
+    /// e.g. to query the server on another thread without receiving on the socket frames are sent from.
+    /// The port of the local address is chosen by the OS, the socket of this instance may still be bound to it.
+    pub fn with_own_socket(&self) -> FtResult<FlaschenTaschen> {
+        let bind_addr =
+            self.bind_addr
+                .as_deref()
+                .map(|bind_addr| match bind_addr.parse::<SocketAddr>() {
+                    Ok(mut bind_addr) => {
+                        bind_addr.set_port(0);
+                        bind_addr.to_string()
+                    }
+                    Err(_) => bind_addr.to_string(),
+                });
+        FlaschenTaschen::with_bind(self.address.clone(), bind_addr)
+    }
+
+    /// Returns how frames are sent to this server.
+    pub fn transport(&self) -> FtTransport {
+        FtTransport::of(&self.address).0
+    }
+
+    /// Returns the local address frames are sent from.
+    /// Fails for Unix domain sockets, they are sent from an unnamed socket, and for TCP connections.
+    pub fn local_addr(&self) -> FtResult<SocketAddr> {
+        match &*self.socket.read().unwrap() {
+            FtSocket::Udp(socket) => socket.local_addr(),
+            FtSocket::Tcp(_) => Err(std::io::Error::new(
+                ErrorKind::Other,
+                "the local address of TCP connections changes with each reconnect",
+            )),
+            #[cfg(unix)]
+            FtSocket::UnixDatagram(_) => Err(std::io::Error::new(
+                ErrorKind::Other,
+                "Unix domain sockets are sent from an unnamed socket without address",
+            )),
+        }
+        .map_err(|source| self.send_error(source))
+    }
+
+    /// Replaces the socket of this instance by a newly bound one connected to the same server.
+    /// This method only requires a shared reference, so it can be called while frames are handled on other threads.
+    pub fn reconnect(&self) -> FtResult<()> {
+        let socket = FlaschenTaschen::connect_socket(&self.address, self.bind_addr.as_deref())?;
+        *self.socket.write().unwrap() = socket;
+        info!("reconnected to {}", self);
+        Ok(())
+    }
+
+    /// Returns a new flaschentaschen instance for the given host/port after making sure the server is reachable.
+    /// See `probe` for the platform caveats of this check.
+    pub fn new_probed(host_port: String) -> FtResult<FlaschenTaschen> {
+        let flaschentaschen = FlaschenTaschen::new(host_port)?;
+        flaschentaschen.probe()?;
+        Ok(flaschentaschen)
+    }
+
+    /// Checks if this flaschentaschen server is reachable by sending an empty datagram
+    /// and looking for a connection-refused error on the connected socket.
+    ///
+    /// Caveats: UDP is connectionless, a refused connection can only be detected if the remote host answers
+    /// with an ICMP port-unreachable message and the platform reports it on the connected socket (e.g. Linux, macOS).
+    /// On other platforms, or if the ICMP message is filtered or delayed, this check will pass even if no server is listening.
+    pub fn probe(&self) -> FtResult<()> {
+        self.socket
+            .read()
+            .unwrap()
+            .send(&[])
+            .map_err(|source| self.send_error(source))?;
+        // give the remote host a moment to answer with an ICMP message:
+        thread::sleep(Duration::from_millis(50));
+        self.check_connection_refused()
+    }
+
+    /// Sends a given PPM byte slice this flaschentaschen server.
+    /// If `output_protocol` is `OutputProtocol::RawRgb`, only the pixel data of the PPM is sent, see `send_raw_rgb`.
+    /// If sending fails and `max_reconnect_backoff` is set, this method reconnects and retries the send once.
+    /// While sends keep failing, the following ones fail right away and only reconnect again after an exponential
+    /// backoff of up to `max_reconnect_backoff`, so callers sending the next frame are never stalled by the backoff.
+    pub fn send_ppm(&self, ppm: &[u8]) -> FtResult<usize> {
+        match self.output_protocol {
+            OutputProtocol::FlaschenTaschenPpm => self.send_datagram(ppm),
+            OutputProtocol::RawRgb => {
+                let (width, height, pixels) =
+                    ppm_pixel_data(ppm).map_err(|err| FtError::Unsendable(err.into()))?;
+                self.send_raw_rgb(pixels, width, height)
+            }
+        }
+    }
+
+    /// Sends the given RGB bytes of a frame of the given dimensions to this server without any header,
+    /// failing if their length is not `width * height * 3`. Retries like `send_ppm`.
+    pub fn send_raw_rgb(&self, rgb: &[u8], width: u32, height: u32) -> FtResult<usize> {
+        let expected_len = width as usize * height as usize * 3;
+        if rgb.len() != expected_len {
+            return Err(FtError::Unsendable(
+                format!(
+                    "raw RGB frame has {} bytes, expected {} for {}x{} pixels",
+                    rgb.len(),
+                    expected_len,
+                    width,
+                    height
+                )
+                .into(),
+            ));
+        }
+        self.send_datagram(rgb)
+    }
+
+    /// Sends the given bytes as a single datagram to this server, see `send_ppm`.
+    /// The datagram is padded first, see `FlaschenTaschen::pad_datagram`.
+    /// Over TCP, the bytes are written to the stream unchanged and are not limited to `MAX_DATAGRAM_SIZE`.
+    fn send_datagram(&self, datagram: &[u8]) -> FtResult<usize> {
+        let is_datagram = self.transport().is_datagram();
+        let padded;
+        let datagram = match self.pad_datagram {
+            Some(size) if is_datagram && datagram.len() < size => {
+                // reuses the buffer of the previous frame instead of allocating one for each frame:
+                let mut buffer = self.padded.lock().unwrap();
+                buffer.clear();
+                buffer.extend_from_slice(datagram);
+                buffer.resize(size, 0);
+                padded = buffer;
+                padded.as_slice()
+            }
+            _ => datagram,
+        };
+        // flaschentaschen expects each frame in a single datagram, larger frames can never be sent successfully:
+        if is_datagram && datagram.len() > MAX_DATAGRAM_SIZE {
+            return Err(FtError::FrameTooLarge {
+                address: self.address.clone(),
+                size: datagram.len(),
+            });
+        }
+        if self.dry_run {
+            debug!("dry run, not sending {} bytes to {}", datagram.len(), self);
+            return Ok(datagram.len());
+        }
+
+        let mut send_result = self.socket.read().unwrap().send(datagram);
+
+        if let Some(max_backoff) = self.max_reconnect_backoff {
+            let mut backoff = self.reconnect_backoff.lock().unwrap();
+            let attempt_due = backoff
+                .next_attempt
+                .map_or(true, |next_attempt| Instant::now() >= next_attempt);
+            if send_result.is_err() && attempt_due {
+                match self.reconnect() {
+                    Ok(()) => send_result = self.socket.read().unwrap().send(datagram),
+                    Err(err) => error!("{}", err),
+                }
+                if send_result.is_err() {
+                    let delay = backoff.delay.min(max_backoff);
+                    error!(
+                        "failed to send PPM to {}, reconnecting again in {}ms",
+                        self,
+                        delay.as_millis()
+                    );
+                    backoff.next_attempt = Some(Instant::now() + delay);
+                    backoff.delay = (delay * 2).min(max_backoff);
+                }
+            }
+            if send_result.is_ok() {
+                *backoff = ReconnectBackoff::default();
+            }
+        }
+
+        send_result.map_err(|source| self.send_error(source))
+    }
+
+    /// Returns a `FtError::Send` for this server caused by the given error.
+    fn send_error(&self, source: std::io::Error) -> FtError {
+        FtError::Send {
+            address: self.address.clone(),
+            source,
+        }
+    }
+
+    /// Sends a given PPM byte slice to this flaschentaschen server like `send_ppm`, but returns an error
+    /// if a previous send triggered a connection-refused error.
+    ///
+    /// Caveats: the ICMP message answering a send arrives asynchronously, an unreachable server is therefore
+    /// reported by the send following the failed one. See `probe` for the platform limitations of this check.
+    pub fn send_ppm_checked(&self, ppm: &[u8]) -> FtResult<usize> {
+        self.check_connection_refused()?;
+        self.send_ppm(ppm)
+    }
+
+    /// Returns an error if the connected socket has a pending connection-refused error.
+    /// The pending error is taken from the socket without reading from it or changing it to non-blocking,
+    /// which would race with sends and size queries on other threads.
+    fn check_connection_refused(&self) -> FtResult<()> {
+        let pending_error = self
+            .socket
+            .read()
+            .unwrap()
+            .take_error()
+            .map_err(|source| self.send_error(source))?;
+
+        match pending_error {
+            Some(err) if err.kind() == ErrorKind::ConnectionRefused => {
+                Err(FtError::ConnectionRefused {
+                    address: self.address.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Queries this flaschentaschen server for the dimensions (width, height) of its LED screen.
+    ///
+    /// Sends `SIZE_QUERY` and waits up to `SIZE_QUERY_TIMEOUT` for a response containing the width and height
+    /// separated by whitespace or an `x`, e.g. `64x32`. The stock flaschentaschen server does not answer this query,
+    /// in which case an error is returned and the dimensions must be configured explicitly.
+    pub fn query_size(&self) -> FtResult<(u32, u32)> {
+        let size_query_error = |reason: String| FtError::SizeQuery {
+            address: self.address.clone(),
+            reason,
+        };
+        // the query would end up in the stream of frames:
+        if !self.transport().is_datagram() {
+            return Err(size_query_error(String::from(
+                "TCP connections do not support the size query",
+            )));
+        }
+        let socket = self.socket.read().unwrap();
+        socket
+            .send(SIZE_QUERY)
+            .map_err(|source| self.send_error(source))?;
+
+        let mut buffer = [0u8; 64];
+        socket
+            .set_read_timeout(Some(SIZE_QUERY_TIMEOUT))
+            .map_err(FtError::Io)?;
+        let recv_result = socket.recv(&mut buffer);
+        socket.set_read_timeout(None).map_err(FtError::Io)?;
+        let len = recv_result.map_err(|err| {
+            size_query_error(format!("no answer, the server may not support it: {}", err))
+        })?;
+
+        parse_size_response(&buffer[..len]).ok_or_else(|| {
+            size_query_error(format!(
+                "invalid response `{}`",
+                String::from_utf8_lossy(&buffer[..len]).trim()
+            ))
+        })
+    }
+
+    /// Sends a given PPM byte slice to this flaschentaschen server,
+    /// positioned at the given x/y offset on the given layer.
+    pub fn send_ppm_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> FtResult<usize> {
+        let ppm =
+            insert_ft_offset(ppm, x, y, layer).map_err(|err| FtError::Unsendable(err.into()))?;
+        self.send_ppm(ppm.as_slice())
+    }
+
+    /// Like `send_ppm_at`, but returns an error if a previous send triggered a connection-refused error,
+    /// see `send_ppm_checked`.
+    pub fn send_ppm_at_checked(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> FtResult<usize> {
+        self.check_connection_refused()?;
+        self.send_ppm_at(ppm, x, y, layer)
+    }
+}
+impl Display for FlaschenTaschen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FlaschenTaschen@{}", self.address)
+    }
+}
+
+/// A group of flaschentaschen servers all receiving the same frames, e.g. to mirror content across multiple LED screens.
+pub struct FlaschenTaschenGroup {
+    /// Shared with the sending threads if frames are sent concurrently, see `parallel_sends`
+    pub members: Vec<Arc<FlaschenTaschen>>,
+    /// The maximum number of servers a frame is sent to concurrently, 1 by default to send sequentially.
+    /// Concurrent sends bound the time to send a frame by the slowest server instead of the sum of all servers.
+    /// The sending threads are started by the first concurrent send and kept for all further frames.
+    pub parallel_sends: usize,
+    send_pool: Mutex<Option<SendPool>>,
+}
+impl FlaschenTaschenGroup {
+    /// Returns a new group with a flaschentaschen instance for each of the given host/port addresses.
+    pub fn new(host_ports: Vec<String>) -> FtResult<FlaschenTaschenGroup> {
+        FlaschenTaschenGroup::with_bind(host_ports, None)
+    }
+
+    /// Like `new`, but all instances send from the given local address, see `FlaschenTaschen::with_bind`.
+    pub fn with_bind(
+        host_ports: Vec<String>,
+        bind_addr: Option<String>,
+    ) -> FtResult<FlaschenTaschenGroup> {
+        let members = host_ports
+            .into_iter()
+            .map(|host_port| FlaschenTaschen::with_bind(host_port, bind_addr.clone()))
+            .collect::<FtResult<Vec<_>>>()?;
+        Ok(FlaschenTaschenGroup::from_members(members))
+    }
+
+    /// Returns a new group of the given flaschentaschen instances, e.g. to configure each of them differently.
+    pub fn from_members(members: Vec<FlaschenTaschen>) -> FlaschenTaschenGroup {
+        FlaschenTaschenGroup {
+            members: members.into_iter().map(Arc::new).collect(),
+            parallel_sends: 1,
+            send_pool: Mutex::new(None),
+        }
+    }
+
+    /// Sends a given PPM byte slice to all servers of this group and returns the result of each send
+    /// in the order of `members`. A failing server does not prevent sending to the others.
+    /// The servers may receive the frame in any order if `parallel_sends` is greater than 1.
+    pub fn send_ppm(&self, ppm: &[u8]) -> Vec<FtResult<usize>> {
+        self.send_to_members(ppm, MemberSend::Plain)
+    }
+
+    /// Like `send_ppm`, but uses `FlaschenTaschen::send_ppm_checked` for each server.
+    pub fn send_ppm_checked(&self, ppm: &[u8]) -> Vec<FtResult<usize>> {
+        self.send_to_members(ppm, MemberSend::Checked)
+    }
+
+    /// Like `send_ppm`, but uses `FlaschenTaschen::send_ppm_at` for each server.
+    pub fn send_ppm_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> Vec<FtResult<usize>> {
+        self.send_to_members(ppm, MemberSend::At(x, y, layer))
+    }
+
+    /// Like `send_ppm`, but uses `FlaschenTaschen::send_ppm_at_checked` for each server.
+    pub fn send_ppm_at_checked(
+        &self,
+        ppm: &[u8],
+        x: u32,
+        y: u32,
+        layer: u8,
+    ) -> Vec<FtResult<usize>> {
+        self.send_to_members(ppm, MemberSend::AtChecked(x, y, layer))
+    }
+
+    /// Sends the given PPM to each member, on up to `parallel_sends` threads of the send pool, and returns
+    /// the results in the order of `members` regardless of the order the sends completed in.
+    fn send_to_members(&self, ppm: &[u8], send: MemberSend) -> Vec<FtResult<usize>> {
+        if self.parallel_sends <= 1 || self.members.len() <= 1 {
+            return self
+                .members
+                .iter()
+                .map(|member| send.send(member, ppm))
+                .collect();
+        }
+
+        let (result_sender, result_receiver) = mpsc::channel();
+        {
+            let mut send_pool = self.send_pool.lock().unwrap();
+            // `parallel_sends` is public, it may have changed since the pool was started:
+            if !matches!(&*send_pool, Some(pool) if pool.parallel_sends == self.parallel_sends) {
+                *send_pool = Some(SendPool::start(&self.members, self.parallel_sends));
+            }
+            let job = Arc::new(SendJob {
+                ppm: ppm.to_vec(),
+                send,
+            });
+            for worker in send_pool.as_ref().unwrap().workers.iter() {
+                let _ = worker.send((job.clone(), result_sender.clone()));
+            }
+        }
+        drop(result_sender);
+
+        let mut results: Vec<Option<FtResult<usize>>> = self.members.iter().map(|_| None).collect();
+        for (index, result) in result_receiver {
+            results[index] = Some(result);
+        }
+        results
+            .into_iter()
+            .zip(self.members.iter())
+            .map(|(result, member)| {
+                // only missing if a sending thread panicked:
+                result.unwrap_or_else(|| {
+                    Err(FtError::Send {
+                        address: member.address.clone(),
+                        source: std::io::Error::new(
+                            ErrorKind::Other,
+                            "the sending thread panicked",
+                        ),
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+/// How a frame is sent to each member of a group, see the send methods of `FlaschenTaschenGroup`.
+#[derive(Clone, Copy)]
+enum MemberSend {
+    Plain,
+    Checked,
+    At(u32, u32, u8),
+    AtChecked(u32, u32, u8),
+}
+impl MemberSend {
+    fn send(self, member: &FlaschenTaschen, ppm: &[u8]) -> FtResult<usize> {
+        match self {
+            MemberSend::Plain => member.send_ppm(ppm),
+            MemberSend::Checked => member.send_ppm_checked(ppm),
+            MemberSend::At(x, y, layer) => member.send_ppm_at(ppm, x, y, layer),
+            MemberSend::AtChecked(x, y, layer) => member.send_ppm_at_checked(ppm, x, y, layer),
+        }
+    }
+}
+
+/// A frame to send to all members of a group, shared by the threads of the send pool.
+struct SendJob {
+    ppm: Vec<u8>,
+    send: MemberSend,
+}
+
+/// The threads sending frames concurrently for a group, each to its own chunk of the members.
+/// Each thread sends the frames in the order they were queued, so every server receives them in order.
+/// The threads exit once the pool is dropped.
+struct SendPool {
+    parallel_sends: usize,
+    workers: Vec<Sender<(Arc<SendJob>, Sender<(usize, FtResult<usize>)>)>>,
+    handles: Vec<thread::JoinHandle<()>>,
+}
+impl SendPool {
+    fn start(members: &[Arc<FlaschenTaschen>], parallel_sends: usize) -> SendPool {
+        let chunk_size = (members.len() + parallel_sends - 1) / parallel_sends;
+        let mut workers = Vec::new();
+        let mut handles = Vec::new();
+        for (chunk_index, chunk) in members.chunks(chunk_size).enumerate() {
+            let chunk: Vec<(usize, Arc<FlaschenTaschen>)> = chunk
+                .iter()
+                .enumerate()
+                .map(|(index, member)| (chunk_index * chunk_size + index, member.clone()))
+                .collect();
+            let (job_sender, job_receiver) =
+                mpsc::channel::<(Arc<SendJob>, Sender<(usize, FtResult<usize>)>)>();
+            handles.push(thread::spawn(move || {
+                for (job, result_sender) in job_receiver {
+                    for (index, member) in chunk.iter() {
+                        let _ = result_sender.send((*index, job.send.send(member, &job.ppm)));
+                    }
+                }
+            }));
+            workers.push(job_sender);
+        }
+        SendPool {
+            parallel_sends,
+            workers,
+            handles,
+        }
+    }
+}
+impl Drop for SendPool {
+    fn drop(&mut self) {
+        // the threads exit once their queue is closed:
+        self.workers.clear();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+impl Display for FlaschenTaschenGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let members: Vec<String> = self.members.iter().map(ToString::to_string).collect();
+        write!(f, "[{}]", members.join(", "))
+    }
+}
+
+/// Returns a browser instance for the given options, either by launching a new chrome instance
+/// or by connecting to a running one.
+fn get_browser(opts: &ScreencastOptions) -> FtResult<Browser> {
+    match &opts.browser_source {
+        BrowserSource::Launch => {
+            info!(
+                "starting chrome in headless mode with dimensions {}x{}",
+                opts.width, opts.height
+            );
+            let mut args = opts.extra_chrome_args.clone();
+            if opts.no_sandbox {
+                warn!(
+                    "SECURITY WARNING: chrome runs without its sandbox (--no-sandbox), \
+                    a malicious page exploiting chrome gains full access to this user account. \
+                    Only screencast trusted pages, or run as a non-root user with the sandbox enabled instead"
+                );
+                args.push(OsString::from("--no-sandbox"));
+            }
+            if let Some(proxy) = &opts.proxy {
+                info!("sending all requests through proxy {}", proxy);
+                args.push(OsString::from(format!("--proxy-server={}", proxy)));
+            }
+            if let Some(bypass_list) = &opts.proxy_bypass_list {
+                args.push(OsString::from(format!(
+                    "--proxy-bypass-list={}",
+                    bypass_list
+                )));
+            }
+            Browser::new(headless_chrome::LaunchOptions {
+                headless: true,
+                window_size: Some((opts.width, opts.height)),
+                args: args.iter().map(OsString::as_os_str).collect(),
+                ..Default::default()
+            })
+            .map_err(|err| FtError::BrowserLaunch(err.to_string()))
+        }
+        BrowserSource::Connect(ws_url) => {
+            if opts.proxy.is_some() || opts.proxy_bypass_list.is_some() {
+                warn!("ignoring the proxy settings when connecting to a running chrome instance");
+            }
+            info!("connecting to running chrome instance at {}", ws_url);
+            Browser::connect(ws_url.clone()).map_err(|err| {
+                FtError::BrowserLaunch(format!("could not connect to {}: {}", ws_url, err))
+            })
+        }
+    }
+}
+
+/// The maximum time to wait for the element given by `ScreencastOptions::wait_for_selector` to appear.
+const WAIT_FOR_SELECTOR_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum time to wait for `ScreencastOptions::ready_expression` to become truthy.
+const READY_EXPRESSION_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often `ScreencastOptions::ready_expression` is evaluated while waiting for it.
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Evaluates the given JavaScript expression on the page until it returns a truthy value.
+/// Errors thrown by the expression count as not ready, e.g. while the objects it refers to are not defined yet.
+fn wait_for_ready_expression(tab: &Tab, expression: &str) -> Result<()> {
+    // objects are not returned by value, converting the result to a boolean lets javascript decide what is truthy:
+    let predicate = format!("!!({})", expression);
+    let started = Instant::now();
+    loop {
+        match tab.evaluate(&predicate, false) {
+            Ok(result) if result.value == Some(serde_json::Value::Bool(true)) => return Ok(()),
+            Ok(_) => {}
+            Err(err) => debug!("ready expression `{}` failed: {}", expression, err),
+        }
+        if started.elapsed() >= READY_EXPRESSION_TIMEOUT {
+            return Err(eyre!(
+                "ready expression `{}` did not become truthy within {}s",
+                expression,
+                READY_EXPRESSION_TIMEOUT.as_secs()
+            ));
+        }
+        thread::sleep(READY_POLL_INTERVAL);
+    }
+}
+
+/// Returns a `FtError::Navigate` for the URL of the given options caused by the given error.
+/// Chrome reports proxy failures with rather cryptic network error codes, they are explained if a proxy is used.
+fn navigate_error<E: Display>(opts: &ScreencastOptions, err: E) -> FtError {
+    let message = err.to_string();
+    if let Some(proxy) = &opts.proxy {
+        if message.contains("ERR_PROXY_AUTH")
+            || message.contains("ERR_TUNNEL_CONNECTION_FAILED")
+            || message.contains("ERR_INVALID_AUTH_CREDENTIALS")
+        {
+            return FtError::Navigate(format!(
+                "{}: proxy {} rejected the request, it probably requires authentication which is not supported ({})",
+                opts.url, proxy, message
+            ));
+        }
+        if message.contains("ERR_PROXY_CONNECTION_FAILED") {
+            return FtError::Navigate(format!(
+                "{}: could not connect to proxy {} ({})",
+                opts.url, proxy, message
+            ));
+        }
+    }
+    FtError::Navigate(format!("{}: {}", opts.url, message))
+}
+
+/// Returns the extra HTTP headers of the given options, including the `Authorization` header for basic auth.
+fn get_request_headers(opts: &ScreencastOptions) -> Vec<(String, String)> {
+    let mut headers = opts.extra_headers.clone();
+    if let Some((user, password)) = &opts.basic_auth {
+        let credentials = base64::encode(format!("{}:{}", user, password));
+        headers.push((
+            String::from("Authorization"),
+            format!("Basic {}", credentials),
+        ));
+    }
+    headers
+}
+
+/// Returns the initial tab of the given browser after navigating it to the URL of the given options.
+/// Depending on the options, this function blocks until the page is ready to be captured.
+fn open_tab(browser: &Browser, opts: &ScreencastOptions) -> Result<Arc<Tab>> {
+    let tab = map_err(browser.wait_for_initial_tab(), "Could not open new tab")?;
+    prepare_tab(tab, opts)
+}
+
+/// Applies the given options to the given tab and navigates it to `opts.url`, like `open_tab`.
+fn prepare_tab(tab: Arc<Tab>, opts: &ScreencastOptions) -> Result<Arc<Tab>> {
+    // navigating and waiting for elements must not take longer than the page load timeout:
+    tab.set_default_timeout(Duration::from_secs(opts.page_load_timeout_secs));
+    if let Some(user_agent) = &opts.user_agent {
+        map_err(
+            tab.set_user_agent(user_agent.as_str(), None, None),
+            "Could not set user agent",
+        )?;
+    }
+    // `max_width` and `max_height` of the screencast are only upper bounds, frames of a window which is not exactly
+    // the requested size would be smaller. The viewport is therefore fixed to the requested dimensions,
+    // stray-sized frames are still resized to the screen dimensions by the conversion:
+    map_err(
+        tab.call_method(Emulation::SetDeviceMetricsOverride {
+            width: opts.width,
+            height: opts.height,
+            device_scale_factor: opts.device_scale_factor as f64,
+            mobile: false,
+            scale: None,
+            screen_width: None,
+            screen_height: None,
+            position_x: None,
+            position_y: None,
+            dont_set_visible_size: None,
+            screen_orientation: None,
+            viewport: None,
+            display_feature: None,
+        }),
+        "Could not set the viewport size",
+    )?;
+    if opts.transparent_background {
+        map_err(
+            tab.call_method(Emulation::SetDefaultBackgroundColorOverride {
+                color: Some(DOM::RGBA {
+                    r: 0,
+                    g: 0,
+                    b: 0,
+                    a: Some(0.0),
+                }),
+            }),
+            "Could not set a transparent background",
+        )?;
+    }
+    let headers = get_request_headers(opts);
+    if !headers.is_empty() {
+        // only the names are logged, the values usually contain credentials:
+        let names: Vec<&str> = headers.iter().map(|(name, _)| name.as_str()).collect();
+        debug!("sending extra HTTP headers: {}", names.join(", "));
+        map_err(
+            tab.set_extra_http_headers(
+                headers
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str()))
+                    .collect(),
+            ),
+            "Could not set extra HTTP headers",
+        )?;
+    }
+    if !opts.cookies.is_empty() {
+        // only the names are logged, like the extra headers:
+        let names: Vec<&str> = opts
+            .cookies
+            .iter()
+            .map(|cookie| cookie.name.as_str())
+            .collect();
+        debug!("setting cookies: {}", names.join(", "));
+        let cookies = opts
+            .cookies
+            .iter()
+            .map(|cookie| cookie.to_cookie_param(&opts.url))
+            .collect::<Result<Vec<_>>>()?;
+        map_err(
+            tab.call_method(Network::SetCookies { cookies }),
+            "Could not set cookies",
+        )?;
+    }
+    if let Some(script) = get_injection_script(opts) {
+        // chrome evaluates this script on each new document, the injection is therefore reapplied after reloads:
+        map_err(
+            tab.call_method(Page::AddScriptToEvaluateOnNewDocument {
+                source: script,
+                world_name: None,
+                include_command_line_api: None,
+            }),
+            "Could not inject CSS/JS into the page",
+        )?;
+    }
+    if opts.log_page_console {
+        // subscribing before navigating also catches the messages logged while the page loads:
+        map_err(
+            tab.add_event_listener(Arc::new(log_page_console)),
+            "Could not attach console listener to tab",
+        )?;
+        map_err(
+            tab.call_method(Runtime::Enable(None)),
+            "Could not enable the page console",
+        )?;
+        map_err(
+            tab.call_method(Log::Enable(None)),
+            "Could not enable the page log",
+        )?;
+    }
+    tab.navigate_to(opts.url.as_str())
+        .map_err(|err| navigate_error(opts, err))?;
+
+    if opts.wait_for_navigation_idle {
+        tab.wait_until_navigated()
+            .map_err(|err| navigate_error(opts, err))?;
+    }
+    if let Some(selector) = &opts.wait_for_selector {
+        tab.wait_for_element_with_custom_timeout(selector.as_str(), WAIT_FOR_SELECTOR_TIMEOUT)
+            .map_err(|err| {
+                eyre!(
+                    "element `{}` did not appear on {} within {}s: {}",
+                    selector,
+                    opts.url,
+                    WAIT_FOR_SELECTOR_TIMEOUT.as_secs(),
+                    err
+                )
+            })?;
+    }
+
+    if let Some(expression) = &opts.ready_expression {
+        wait_for_ready_expression(&tab, expression)
+            .map_err(|err| eyre!("{} is not ready: {}", opts.url, err))?;
+    }
+
+    if let Some(on_ready) = &opts.on_ready {
+        on_ready(&tab).wrap_err_with(|| format!("failed to prepare {}", opts.url))?;
+    }
+
+    Ok(tab)
+}
+
+/// Logs the console messages and log entries (e.g. failed requests) of a page, see `ScreencastOptions::log_page_console`.
+fn log_page_console(event: &Event) {
+    match event {
+        Event::RuntimeConsoleAPICalled(call) => {
+            let message: Vec<String> = call
+                .params
+                .args
+                .iter()
+                .map(|arg| match (&arg.value, &arg.description) {
+                    (Some(serde_json::Value::String(value)), _) => value.clone(),
+                    (Some(value), _) => value.to_string(),
+                    (None, Some(description)) => description.clone(),
+                    (None, None) => String::from("undefined"),
+                })
+                .collect();
+            debug!("[page] console: {}", message.join(" "));
+        }
+        Event::LogEntryAdded(entry) => {
+            let entry = &entry.params.entry;
+            match &entry.url {
+                Some(url) => debug!("[page] {:?}: {} ({})", entry.level, entry.text, url),
+                None => debug!("[page] {:?}: {}", entry.level, entry.text),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns a script injecting the CSS and JS of the given options into a page as soon as its DOM is loaded,
+/// or `None` if there is nothing to inject.
+fn get_injection_script(opts: &ScreencastOptions) -> Option<String> {
+    if opts.inject_css.is_none() && opts.inject_js.is_none() {
+        return None;
+    }
+
+    let mut injection = String::new();
+    if let Some(css) = &opts.inject_css {
+        // serializing the CSS as JSON string gives us a properly escaped JS string literal:
+        injection.push_str(&format!(
+            "const style = document.createElement('style'); style.textContent = {}; document.head.appendChild(style);\n",
+            serde_json::value::Value::String(css.clone())
+        ));
+    }
+    if let Some(js) = &opts.inject_js {
+        injection.push_str(&format!(
+            "try {{\n{}\n}} catch (err) {{ console.error(err); }}\n",
+            js
+        ));
+    }
+
+    Some(format!(
+        "(function () {{\nconst inject = () => {{\n{}}};\nif (document.readyState === 'loading') {{ document.addEventListener('DOMContentLoaded', inject); }} else {{ inject(); }}\n}})();",
+        injection
+    ))
+}
+
+/// Captures a single frame of the given website and returns it as PPM, converted using the given conversion options.
+/// Unlike `start_screencasting`, this function waits for the page to be loaded and takes a single screenshot.
+/// The browser is stopped before this function returns.
+pub fn capture_single_frame(
+    opts: &ScreencastOptions,
+    conversion_opts: &ConversionOptions,
+) -> Result<Vec<u8>> {
+    let browser = get_browser(opts)?;
+    let tab = open_tab(&browser, opts)?;
+    tab.wait_until_navigated()
+        .map_err(|err| navigate_error(opts, err))?;
+
+    let screenshot = map_err(
+        tab.capture_screenshot(
+            opts.format.as_screenshot_format(),
+            opts.jpeg_quality,
+            None,
+            true,
+        ),
+        "Failed to capture screenshot",
+    )?;
+
+    Ok(get_ppm_from_buffer(
+        screenshot.as_slice(),
+        opts.format,
+        conversion_opts,
+    )?)
+}
+
+/// Returns true if the given frame handler error is caused by a `FtError` which `FtError::is_decode_error`.
+/// All other errors, including those of unknown types, count as send errors.
+fn is_decode_failure(err: &eyre::Report) -> bool {
+    err.chain().any(|cause| {
+        cause
+            .downcast_ref::<FtError>()
+            .map_or(false, FtError::is_decode_error)
+    })
+}
+
+/// Returns the time since chrome captured a frame with the given timestamp
+/// (seconds since the UNIX epoch, see `ScreencastFrameMetadata::timestamp`), e.g. to measure the latency of frame handlers.
+/// Returns `None` if the timestamp lies in the future, e.g. because the clock of a connected browser is ahead.
+pub fn frame_latency(capture_timestamp: f64) -> Option<Duration> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs_f64();
+    (0.0..=now)
+        .contains(&capture_timestamp)
+        .then(|| Duration::from_secs_f64(now - capture_timestamp))
+}
+
+/// Renders the given URL in a newly launched headless chrome instance at the given dimensions
+/// and returns a single screenshot of it as PPM of the same dimensions.
+/// This is the synchronous counterpart of `start_screencasting`, see `capture_single_frame` for more options.
+///
+/// ```no_run
+/// // launches chrome, which is not available to doc tests:
+/// let ppm = flaschentaschen_web::url_to_ppm("https://example.com", 64, 32)?;
+/// assert!(ppm.starts_with(b"P6\n64 32\n"));
+/// # Ok::<(), eyre::Report>(())
+/// ```
+pub fn url_to_ppm(url: &str, width: u32, height: u32) -> Result<Vec<u8>> {
+    // PNG screenshots avoid the compression artifacts of JPEG, which are clearly visible on small screens:
+    let opts = ScreencastOptions::builder(url, width, height)
+        .format(ScreencastImageFormat::Png)
+        .build()?;
+    capture_single_frame(
+        &opts,
+        &ConversionOptions {
+            target_size: Some((width, height)),
+            ..Default::default()
+        },
+    )
+}
+
+/// Starts the screencasting process by:
+/// 1. spawing a new chrome instance or connecting to a running one, depending on `opts.browser_source`
+/// 2. navigating to the given URL and running `opts.on_ready` on the tab, if set
+/// 3. attaching an event handler for incoming frames which forwards them to the given `on_frame` callback.
+/// This method will return the created browser instance. It is important to keep the returned instance in scope.
+/// If it goes out of scope or the main thread terminates, the browser will be stopped too and screencasting halts.
+///
+/// This function will call the provided callback for each received frame together with the given shared context.
+/// The callback runs in a separate worker thread, fed by a queue of received frames, which holds a reference
+/// to the context while it runs. `stop_screencasting` stops and joins the worker, the context is dropped
+/// once all other references are gone too.
+/// Frames are acknowledged to chrome once the callback handled them, so chrome produces frames no faster than
+/// the callback handles them instead of building up a growing lag.
+pub fn start_screencasting<F, C>(
+    opts: ScreencastOptions,
+    on_frame: F,
+    on_frame_context: Arc<C>,
+) -> Result<Browser>
+where
+    C: 'static + Send + Sync,
+    F: 'static + Fn(&Page::events::ScreencastFrameEvent, &C) -> Result<()> + Send + Sync,
+{
+    // on errors, `browser` is dropped when returning, which stops the chrome instance:
+    let browser = launch_browser(&opts)?;
+    let tab = map_err(browser.wait_for_initial_tab(), "Could not open new tab")?;
+    start_screencasting_tab(tab, opts, on_frame, on_frame_context)?;
+    Ok(browser)
+}
+
+/// Returns a browser instance for the given options, see `ScreencastOptions::browser_source`.
+/// Use this with `start_screencasting_tab` to screencast multiple tabs of the same browser.
+pub fn launch_browser(opts: &ScreencastOptions) -> Result<Browser> {
+    Ok(get_browser(opts)?)
+}
+
+/// Screencasts the given tab like `start_screencasting`, e.g. a tab opened with `Browser::new_tab`
+/// to show multiple pages in different regions of the LED screen, see `RegionSpec` and `ScreencastOptions::for_region`.
+/// Each tab has its own frame queue and circuit breaker, and fails independently of the other tabs.
+/// Browser-wide options like `ScreencastOptions::browser_source` are ignored, they apply when launching the browser.
+pub fn start_screencasting_tab<F, C>(
+    tab: Arc<Tab>,
+    mut opts: ScreencastOptions,
+    on_frame: F,
+    on_frame_context: Arc<C>,
+) -> Result<()>
+where
+    C: 'static + Send + Sync,
+    F: 'static + Fn(&Page::events::ScreencastFrameEvent, &C) -> Result<()> + Send + Sync,
+{
+    // open the tab on the provided URL:
+    let started = Instant::now();
+    let tab = prepare_tab(tab, &opts)?;
+    let closure_tab = tab.clone();
+    let screencast = Arc::new(TabScreencast {
+        tab: tab.clone(),
+        control: ScreencastControl::new(),
+        ready_expression: opts.ready_expression.clone(),
+        start_command: Mutex::new(start_screencast_command(&opts)),
+        state: Mutex::new(ScreencastState::default()),
+        started,
+        last_frame_millis: AtomicU64::new(0),
+    });
+
+    // frames are passed from the event listener to a worker thread calling `on_frame`,
+    // so slow frame handlers do not delay the acknowledgement of new frames.
+    let frame_queue: Arc<FrameQueue<Page::events::ScreencastFrameEvent>> =
+        Arc::new(FrameQueue::new(opts.frame_queue_capacity));
+    let stats = opts.stats.clone();
+
+    // the worker thread handling queued frames.
+    // `circuit_breaker` will count consecutive errors while handling incoming frames to stop screencasting
+    // as soon as a threshold is reached.
+    let circuit_breaker = Arc::new(Mutex::new(CircuitBreaker::new(
+        opts.error_threshold,
+        opts.error_window,
+    )));
+    let worker_queue = frame_queue.clone();
+    let stop_queue = frame_queue.clone();
+    screencast.control.on_stop(move || stop_queue.close());
+    let playlist_queue = frame_queue.clone();
+    let navigation_queue = frame_queue.clone();
+    let worker_tab = tab.clone();
+    let worker_stats = stats.clone();
+    let worker_circuit_breaker = circuit_breaker.clone();
+    let shutdown_sender = opts.shutdown_sender.clone();
+    screencast.control.spawn(move || {
+        // the number of frames handled since `throughput_start`, to log the rate chrome is throttled to:
+        let mut handled_frames = 0;
+        let mut throughput_start = Instant::now();
+        while let Some(frame) = worker_queue.pop() {
+            if let Some(stats) = &worker_stats {
+                stats.record_queue_depth(worker_queue.len());
+            }
+            // we do catch potential errors but only log them and continue with the next frame.
+            // if the circuit breaker trips because of too many consecutive errors, we stop the screencasting
+            let callback_result = on_frame(&frame, &*on_frame_context);
+            let mut circuit_breaker = worker_circuit_breaker.lock().unwrap();
+            match callback_result {
+                Ok(()) => {
+                    circuit_breaker.record_success();
+                    // the end-to-end latency, from the capture in chrome until the frame was sent:
+                    let latency = frame.params.metadata.timestamp.and_then(frame_latency);
+                    if let (Some(stats), Some(latency)) = (&worker_stats, latency) {
+                        stats.record_frame_latency(latency);
+                    }
+                }
+                // a corrupt frame says nothing about the server, it neither resets nor adds to the consecutive errors:
+                Err(err) if is_decode_failure(&err) => {
+                    if let Some(stats) = &worker_stats {
+                        Stats::add(&stats.errors, 1);
+                        Stats::add(&stats.decode_errors, 1);
+                    }
+                    warn!("skipping frame which could not be decoded: {}", err);
+                }
+                Err(err) => {
+                    let tripped = circuit_breaker.record_error();
+                    if let Some(stats) = &worker_stats {
+                        Stats::add(&stats.errors, 1);
+                        Stats::add(&stats.send_errors, 1);
+                    }
+                    error!(
+                        "frame handler failed (consecutive errors: {}): {}",
+                        circuit_breaker.consecutive_errors(),
+                        err
+                    );
+
+                    // UdpSocket.send will return Ok() even if the server is not reachable.
+                    // Frame handlers should use `FlaschenTaschen::send_ppm_checked` to not wrongly reset the consecutive error count.
+                    if tripped {
+                        error!("too many consecutive frame errors, stopping screencast");
+                        let _ = worker_tab.call_method(Page::StopScreencast(Some(
+                            serde_json::value::Value::Null,
+                        )));
+                        worker_queue.close();
+                        if let Some(sender) = &shutdown_sender {
+                            let _ = sender.send(ShutdownReason::CircuitBreakerTripped);
+                        }
+                    }
+                }
+            }
+
+            // chrome sends the next frame only after this one was acknowledged,
+            // acknowledging it after handling it therefore throttles chrome to the throughput of the frame handler.
+            // The breaker is released first, the other threads checking it should not wait for chrome to answer:
+            let tripped = circuit_breaker.is_tripped();
+            drop(circuit_breaker);
+            if !tripped {
+                let _ = worker_tab.call_method(Page::ScreencastFrameAck {
+                    session_id: frame.params.session_id,
+                });
+            }
+
+            handled_frames += 1;
+            let elapsed = throughput_start.elapsed();
+            if elapsed >= THROUGHPUT_LOG_INTERVAL {
+                debug!(
+                    "handled {:.1} fps, chrome is throttled to this rate",
+                    handled_frames as f64 / elapsed.as_secs_f64()
+                );
+                handled_frames = 0;
+                throughput_start = Instant::now();
+            }
+        }
+    });
+
+    // register the event handler for incoming screencast frames.
+    // `last_forwarded_frame` keeps track of when the last frame was queued to enforce `max_fps`.
+    let min_frame_interval = opts
+        .max_fps
+        .filter(|fps| *fps > 0)
+        .map(|fps| Duration::from_millis(1000 / fps as u64));
+    let last_forwarded_frame: Arc<Mutex<Option<Instant>>> = Arc::new(Mutex::new(None));
+    // `first_frame_sender` notifies this thread about the first received frame, see below.
+    let (first_frame_sender, first_frame_receiver) = mpsc::channel();
+    let first_frame_sender = Mutex::new(Some(first_frame_sender));
+    let listener_screencast = screencast.clone();
+    let watchdog_circuit_breaker = circuit_breaker.clone();
+    let playlist_circuit_breaker = circuit_breaker.clone();
+    let pause_circuit_breaker = circuit_breaker.clone();
+    let element_circuit_breaker = circuit_breaker.clone();
+    let quality_circuit_breaker = circuit_breaker.clone();
+    // since when the queue holds at least `backlog_threshold` frames after each push, see below:
+    let backlog_threshold = QUEUE_BACKLOG_THRESHOLD.min(opts.frame_queue_capacity.max(1));
+    let backlog_since: Mutex<Option<Instant>> = Mutex::new(None);
+    // the frames queued and dropped from the full queue since the last adjustment of `adaptive_quality`:
+    let queued_frames = Arc::new(AtomicU64::new(0));
+    let overflowed_frames = Arc::new(AtomicU64::new(0));
+    let listener_queued_frames = queued_frames.clone();
+    let listener_overflowed_frames = overflowed_frames.clone();
+    let event_listener = move |event: &Event| match event {
+        Event::PageScreencastFrame(frame) => {
+            trace!("got frame: {:?}", frame.params.metadata.timestamp);
+            if let Some(sender) = first_frame_sender.lock().unwrap().take() {
+                let _ = sender.send(());
+            }
+            listener_screencast.record_frame();
+            if let Some(stats) = &stats {
+                Stats::add(&stats.frames_received, 1);
+            }
+
+            // drop frames arriving faster than allowed by `max_fps`:
+            let mut forward = true;
+            if let Some(min_interval) = min_frame_interval {
+                let mut last_forwarded = last_forwarded_frame.lock().unwrap();
+                let now = Instant::now();
+                if last_forwarded.map_or(false, |last| now.duration_since(last) < min_interval) {
+                    trace!("dropping frame to respect the max fps");
+                    forward = false;
+                } else {
+                    *last_forwarded = Some(now);
+                }
+            }
+
+            // queued frames are acknowledged by the worker thread once handled.
+            // Dropped frames are acknowledged right away, otherwise chrome stops sending new frames.
+            // If the queue is full, the oldest frame is dropped in favour of the latest one:
+            let dropped = if forward {
+                listener_queued_frames.fetch_add(1, Ordering::Relaxed);
+                frame_queue.push(frame.clone()).map(|oldest| {
+                    trace!("frame queue is full, dropped the oldest frame");
+                    listener_overflowed_frames.fetch_add(1, Ordering::Relaxed);
+                    oldest.params.session_id
+                })
+            } else {
+                Some(frame.params.session_id)
+            };
+
+            // frames handled slower than chrome sends them pile up in the queue, until they are dropped:
+            let depth = frame_queue.len();
+            if let Some(stats) = &stats {
+                stats.record_queue_depth(depth);
+            }
+            let mut backlog_since = backlog_since.lock().unwrap();
+            match *backlog_since {
+                _ if depth < backlog_threshold => *backlog_since = None,
+                None => *backlog_since = Some(Instant::now()),
+                Some(since) if since.elapsed() >= QUEUE_BACKLOG_WARNING_PERIOD => {
+                    warn!(
+                        "the frame queue held {} or more frames for {}s, frames are handled slower than chrome sends them. \
+                        Lower --max-fps or --jpeg-quality, or use --skip-unchanged",
+                        backlog_threshold,
+                        since.elapsed().as_secs()
+                    );
+                    *backlog_since = Some(Instant::now());
+                }
+                Some(_) => {}
+            }
+            if let Some(session_id) = dropped {
+                if let Some(stats) = &stats {
+                    Stats::add(&stats.frames_dropped, 1);
+                }
+                if !circuit_breaker.lock().unwrap().is_tripped() {
+                    let _ = closure_tab.call_method(Page::ScreencastFrameAck { session_id });
+                }
+            }
+        }
+        _ => {}
+    };
+    // the worker started above must not outlive a screencast which failed to start:
+    let listener_result = map_err(
+        tab.add_event_listener(Arc::new(event_listener)),
+        "Failed to attach event listener to tab",
+    );
+    if listener_result.is_err() {
+        screencast.control.stop();
+    }
+    listener_result?;
+
+    // tell chrome to start screencasting:
+    let command = screencast.current_start_command();
+    let start_result = map_err(tab.call_method(command), "failed to start screencasting");
+    if start_result.is_err() {
+        screencast.control.stop();
+    }
+    start_result?;
+
+    // a page which never renders would leave us waiting silently forever:
+    let page_load_timeout = Duration::from_secs(opts.page_load_timeout_secs);
+    if first_frame_receiver
+        .recv_timeout(page_load_timeout.saturating_sub(started.elapsed()))
+        .is_err()
+    {
+        screencast.control.stop();
+        let _ = tab.call_method(Page::StopScreencast(Some(serde_json::value::Value::Null)));
+        return Err(FtError::Navigate(format!(
+            "{} did not render a frame within {}s",
+            opts.url, opts.page_load_timeout_secs
+        ))
+        .into());
+    }
+
+    if let Some(interval) = opts.reload_interval {
+        let reload_screencast = screencast.clone();
+        screencast.control.spawn(move || loop {
+            if !reload_screencast.control.sleep(interval) {
+                return;
+            }
+            debug!("reloading page");
+            reload_screencast.reload();
+        });
+    }
+
+    if opts.playlist.len() > 1 {
+        let playlist = opts.playlist.clone();
+        let playlist_stats = opts.stats.clone();
+        let playlist_screencast = screencast.clone();
+        screencast.control.spawn(move || {
+            // the first entry is already shown:
+            for (index, entry) in playlist.iter().enumerate().cycle() {
+                if !playlist_screencast.control.sleep(entry.duration)
+                    || playlist_circuit_breaker.lock().unwrap().is_tripped()
+                {
+                    return;
+                }
+
+                let next = &playlist[(index + 1) % playlist.len()];
+                info!("switching to {}", next.url);
+                let switch_result = switch_page(
+                    &playlist_screencast,
+                    &playlist_queue,
+                    &next.url,
+                    playlist_stats.as_deref(),
+                );
+                if let Err(err) = switch_result {
+                    error!("failed to switch to {}: {}", next.url, err);
+                }
+            }
+        });
+    }
+
+    if let Some(receiver) = opts.navigation_receiver.take() {
+        let navigation_stats = opts.stats.clone();
+        let navigation_screencast = screencast.clone();
+        screencast.control.spawn(move || loop {
+            if navigation_screencast.control.is_stopped() {
+                return;
+            }
+            // the lock is only contended by clones of the options, see `ScreencastOptions::navigation_receiver`:
+            let url = match receiver
+                .lock()
+                .unwrap()
+                .recv_timeout(NAVIGATION_POLL_INTERVAL)
+            {
+                Ok(url) => url,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+            info!("navigating to {}", url);
+            let switch_result = switch_page(
+                &navigation_screencast,
+                &navigation_queue,
+                &url,
+                navigation_stats.as_deref(),
+            );
+            if let Err(err) = switch_result {
+                error!("failed to navigate to {}: {}", url, err);
+            }
+        });
+    }
+
+    if let Some(element) = opts.capture_element.clone() {
+        let element_tab = tab.clone();
+        let device_scale_factor = opts.device_scale_factor;
+        let element_screencast = screencast.clone();
+        screencast.control.spawn(move || loop {
+            if element_circuit_breaker.lock().unwrap().is_tripped() {
+                return;
+            }
+            let bounds = element_bounds(&element_tab, &element.selector, device_scale_factor);
+            match (element.bounds(), bounds) {
+                (Some(_), None) => warn!(
+                    "{} disappeared, sending the full frame until it appears again",
+                    element.selector
+                ),
+                (None, Some(bounds)) => info!("capturing {} at {:?}", element.selector, bounds),
+                (Some(previous), Some(bounds)) if previous != bounds => {
+                    debug!("{} moved to {:?}", element.selector, bounds)
+                }
+                _ => {}
+            }
+            element.set_bounds(bounds);
+            if !element_screencast
+                .control
+                .sleep(CAPTURE_ELEMENT_POLL_INTERVAL)
+            {
+                return;
+            }
+        });
+    }
+
+    if let Some(bounds) = opts
+        .adaptive_quality
+        .filter(|_| opts.format == ScreencastImageFormat::Jpeg)
+    {
+        let quality_screencast = screencast.clone();
+        let quality_stats = opts.stats.clone();
+        screencast.control.spawn(move || {
+            let mut previous_send_drops = send_path_drops(quality_stats.as_deref());
+            loop {
+                if !quality_screencast.control.sleep(ADAPTIVE_QUALITY_WINDOW)
+                    || quality_circuit_breaker.lock().unwrap().is_tripped()
+                {
+                    return;
+                }
+                let queued = queued_frames.swap(0, Ordering::Relaxed);
+                let overflowed = overflowed_frames.swap(0, Ordering::Relaxed);
+                let send_drops = send_path_drops(quality_stats.as_deref());
+                let dropped = overflowed + send_drops.saturating_sub(previous_send_drops);
+                previous_send_drops = send_drops;
+                // static pages send no frames, there is nothing to measure:
+                if queued == 0 {
+                    continue;
+                }
+                // frames queued in the previous window may be dropped in this one:
+                let drop_ratio = (dropped as f64 / queued as f64).min(1.0);
+                let mut command = quality_screencast.start_command.lock().unwrap();
+                let quality = command.quality.unwrap_or(bounds.1);
+                let adapted = adapted_jpeg_quality(quality, bounds, drop_ratio);
+                if adapted == quality {
+                    continue;
+                }
+                info!(
+                    "dropped {:.0}% of the frames within {}s, {} the JPEG quality from {} to {}",
+                    drop_ratio * 100.0,
+                    ADAPTIVE_QUALITY_WINDOW.as_secs(),
+                    if adapted < quality {
+                        "lowering"
+                    } else {
+                        "raising"
+                    },
+                    quality,
+                    adapted
+                );
+                command.quality = Some(adapted);
+                drop(command);
+                quality_screencast.restart_with(|| ());
+                // frames dropped before the restart must not count against the new quality:
+                queued_frames.store(0, Ordering::Relaxed);
+                overflowed_frames.store(0, Ordering::Relaxed);
+                previous_send_drops = send_path_drops(quality_stats.as_deref());
+            }
+        });
+    }
+
+    if let Some(selector) = opts.pause_on_selector.clone() {
+        let pause_tab = tab.clone();
+        let pause_screencast = screencast.clone();
+        screencast.control.spawn(move || loop {
+            if !pause_screencast.control.sleep(PAUSE_POLL_INTERVAL)
+                || pause_circuit_breaker.lock().unwrap().is_tripped()
+            {
+                return;
+            }
+            // `find_element` does not wait for the element, it fails right away if there is none:
+            let present = pause_tab.find_element(&selector).is_ok();
+            if present != pause_screencast.is_paused() {
+                if present {
+                    info!("{} appeared, pausing the screencast", selector);
+                } else {
+                    info!("{} disappeared, resuming the screencast", selector);
+                }
+                pause_screencast.set_paused(present);
+            }
+        });
+    }
+
+    // a frozen page or a crashed renderer stops the frames while chrome itself keeps responding.
+    // Chrome sends a new frame as soon as the screencast was restarted, even for static pages,
+    // so the wall is only considered stalled if no frame arrives after restarting it either:
+    if let Some(frame_timeout) = opts.frame_timeout {
+        let shutdown_sender = opts.shutdown_sender.clone();
+        let watchdog_screencast = screencast.clone();
+        screencast.control.spawn(move || {
+            let mut restarted = false;
+            loop {
+                // after a normal stop, the missing frames must neither restart the screencast nor trigger a shutdown:
+                if !watchdog_screencast.control.sleep(FRAME_WATCHDOG_INTERVAL)
+                    || watchdog_circuit_breaker.lock().unwrap().is_tripped()
+                {
+                    return;
+                }
+                // a paused screencast, or one waiting for a reloaded page, sends no frames on purpose:
+                if !watchdog_screencast.is_expecting_frames() {
+                    watchdog_screencast.record_frame();
+                    restarted = false;
+                    continue;
+                }
+                let since_last_frame = watchdog_screencast.since_last_frame();
+                if since_last_frame < frame_timeout {
+                    restarted = false;
+                    continue;
+                }
+                if restarted {
+                    error!(
+                        "no frame received for {}s after restarting the screencast",
+                        since_last_frame.as_secs()
+                    );
+                    if let Some(sender) = &shutdown_sender {
+                        let _ = sender.send(ShutdownReason::FramesStalled);
+                    }
+                    return;
+                }
+
+                warn!(
+                    "no frame received for {}s, restarting the screencast",
+                    since_last_frame.as_secs()
+                );
+                // the restarted screencast gets a full timeout to deliver its first frame, see `restart_with`:
+                watchdog_screencast.restart_with(|| ());
+                restarted = true;
+            }
+        });
+    }
+
+    // if chrome dies, the event listener silently stops receiving frames.
+    // This watchdog detects it by regularly querying the tab, which fails once the connection to chrome is lost:
+    if let Some(sender) = opts.shutdown_sender {
+        let watchdog_tab = tab.clone();
+        let watchdog_screencast = screencast.clone();
+        screencast.control.spawn(move || {
+            let mut failures = 0;
+            while failures < BROWSER_WATCHDOG_MAX_FAILURES {
+                if !watchdog_screencast.control.sleep(BROWSER_WATCHDOG_INTERVAL) {
+                    return;
+                }
+                match watchdog_tab.get_target_info() {
+                    Ok(_) => failures = 0,
+                    Err(err) => {
+                        failures += 1;
+                        warn!("chrome did not respond to the watchdog: {}", err);
+                    }
+                }
+            }
+            error!(
+                "chrome did not respond {} times in a row, it probably crashed",
+                failures
+            );
+            let _ = sender.send(ShutdownReason::BrowserCrashed);
+        });
+    }
+
+    SCREENCASTS.lock().unwrap().push(screencast);
+    Ok(())
+}
+
+/// A tab screencasted by `start_screencasting_tab`, shared by the threads handling it.
+/// All threads stopping and restarting the screencast go through `restart_with` and `set_paused`,
+/// so none of them resumes a screencast another one paused.
+struct TabScreencast {
+    tab: Arc<Tab>,
+    control: ScreencastControl,
+    /// see `ScreencastOptions::ready_expression`, checked again after each `reload`
+    ready_expression: Option<String>,
+    /// the command (re)starting the screencast, its quality is adapted by `ScreencastOptions::adaptive_quality`
+    start_command: Mutex<Page::StartScreencast>,
+    state: Mutex<ScreencastState>,
+    started: Instant,
+    /// the milliseconds between `started` and the last received frame, used by the frame watchdog
+    last_frame_millis: AtomicU64,
+}
+
+/// Why a `TabScreencast` is not sending frames at the moment
+#[derive(Default)]
+struct ScreencastState {
+    /// true while the element of `ScreencastOptions::pause_on_selector` is shown
+    paused: bool,
+    /// the number of threads currently restarting the screencast, e.g. while waiting for a reloaded page
+    restarting: usize,
+}
+
+impl TabScreencast {
+    /// Stops the screencast, runs the given function, e.g. reloading the page, and starts the screencast again
+    /// unless it was paused meanwhile or another thread is still restarting it, which then starts it instead.
+    /// Errors are only logged, a failed restart is detected by the frame watchdog if `frame_timeout` is set.
+    fn restart_with<T, F: FnOnce() -> T>(&self, f: F) -> T {
+        self.state.lock().unwrap().restarting += 1;
+        if let Err(err) = self
+            .tab
+            .call_method(Page::StopScreencast(Some(serde_json::value::Value::Null)))
+        {
+            warn!("failed to stop the screencast: {}", err);
+        }
+        let result = f();
+
+        let mut state = self.state.lock().unwrap();
+        state.restarting -= 1;
+        if state.restarting == 0 && !state.paused {
+            if let Err(err) = self.tab.call_method(self.current_start_command()) {
+                warn!("failed to restart the screencast: {}", err);
+            }
+        }
+        drop(state);
+        // restarting does not count as stalled frames, the restarted screencast gets a full `frame_timeout`:
+        self.record_frame();
+        result
+    }
+
+    /// Reloads the page, see `ScreencastOptions::reload_interval`. With a `ready_expression`, the screencast is
+    /// stopped until the reloaded page is ready again, the LED screen keeps the last frame meanwhile.
+    fn reload(&self) {
+        let expression = match &self.ready_expression {
+            Some(expression) => expression,
+            None => {
+                if let Err(err) = self.tab.reload(false, None) {
+                    warn!("failed to reload page: {}", err);
+                }
+                return;
+            }
+        };
+        self.restart_with(|| {
+            if let Err(err) = self.tab.reload(false, None) {
+                warn!("failed to reload page: {}", err);
+            } else if let Err(err) = wait_for_ready_expression(&self.tab, expression) {
+                warn!("{}, showing the reloaded page anyway", err);
+            }
+        });
+    }
+
+    /// Pauses or resumes the screencast, see `ScreencastOptions::pause_on_selector`.
+    /// While another thread restarts the screencast, it is left to that thread to resume it or not.
+    fn set_paused(&self, paused: bool) {
+        let mut state = self.state.lock().unwrap();
+        if state.paused == paused {
+            return;
+        }
+        state.paused = paused;
+        if state.restarting > 0 {
+            return;
+        }
+        let result = if paused {
+            map_err(
+                self.tab
+                    .call_method(Page::StopScreencast(Some(serde_json::value::Value::Null))),
+                "failed to pause the screencast",
+            )
+            .map(|_| ())
+        } else {
+            map_err(
+                self.tab.call_method(self.current_start_command()),
+                "failed to resume the screencast",
+            )
+            .map(|_| ())
+        };
+        // the change is retried with the next poll:
+        if let Err(err) = result {
+            warn!("{}", err);
+            state.paused = !paused;
+        }
+    }
+
+    /// Returns true while the element of `ScreencastOptions::pause_on_selector` is shown.
+    fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    /// Returns true if chrome is supposed to send frames, i.e. the screencast is neither paused nor restarting.
+    fn is_expecting_frames(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        !state.paused && state.restarting == 0
+    }
+
+    /// Records that a frame was received now, see `since_last_frame`.
+    fn record_frame(&self) {
+        self.last_frame_millis
+            .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the time since the last call of `record_frame`.
+    fn since_last_frame(&self) -> Duration {
+        let last_frame = Duration::from_millis(self.last_frame_millis.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last_frame)
+    }
+
+    /// Returns a copy of `start_command`, which is not locked while chrome answers it.
+    fn current_start_command(&self) -> Page::StartScreencast {
+        self.start_command.lock().unwrap().clone()
+    }
+}
+
+/// The screencasts started by `start_screencasting_tab` and not stopped yet, see `stop_screencasting`.
+static SCREENCASTS: Mutex<Vec<Arc<TabScreencast>>> = Mutex::new(Vec::new());
+
+/// Removes and returns the screencasts of the given tabs from `SCREENCASTS`.
+fn take_screencasts(tabs: &[Arc<Tab>]) -> Vec<Arc<TabScreencast>> {
+    let mut screencasts = SCREENCASTS.lock().unwrap();
+    let (taken, kept): (Vec<_>, Vec<_>) = screencasts
+        .drain(..)
+        .partition(|screencast| tabs.iter().any(|tab| Arc::ptr_eq(tab, &screencast.tab)));
+    *screencasts = kept;
+    taken
+}
+
+/// Navigates the given screencasting tab to the given URL, see `ScreencastOptions::playlist`.
+/// The screencast is stopped while navigating and the frames of the previous page still waiting in the given queue
+/// are discarded, so none of them are shown after the switch. The screencast is restarted even if navigating failed,
+/// unless it was paused meanwhile, see `TabScreencast::restart_with`.
+fn switch_page(
+    screencast: &TabScreencast,
+    frame_queue: &FrameQueue<Page::events::ScreencastFrameEvent>,
+    url: &str,
+    stats: Option<&Stats>,
+) -> Result<()> {
+    let navigate_result = screencast.restart_with(|| {
+        let navigate_result = screencast
+            .tab
+            .navigate_to(url)
+            .and_then(|tab| tab.wait_until_navigated())
+            .map(|_| ());
+
+        let discarded = frame_queue.clear();
+        if let Some(stats) = stats {
+            Stats::add(&stats.frames_dropped, discarded as u64);
+        }
+        navigate_result
+    });
+
+    map_err(navigate_result, "navigation failed")
+}
+
+/// Returns the number of frames dropped on the send path so far, see `ScreencastOptions::adaptive_quality`.
+fn send_path_drops(stats: Option<&Stats>) -> u64 {
+    stats.map_or(0, |stats| {
+        stats.frames_rate_limited.load(Ordering::Relaxed)
+            + stats.send_errors.load(Ordering::Relaxed)
+    })
+}
+
+/// Returns the JPEG quality for the next window of `ScreencastOptions::adaptive_quality` after the given ratio
+/// of frames was dropped at the given quality: lowered by `ADAPTIVE_QUALITY_LOWER_STEP` above `ADAPTIVE_QUALITY_LOWER_RATIO`,
+/// raised by `ADAPTIVE_QUALITY_RAISE_STEP` up to `ADAPTIVE_QUALITY_RAISE_RATIO`, and always within the bounds (min, max).
+pub fn adapted_jpeg_quality(
+    quality: u32,
+    (min_quality, max_quality): (u32, u32),
+    drop_ratio: f64,
+) -> u32 {
+    let adapted = if drop_ratio > ADAPTIVE_QUALITY_LOWER_RATIO {
+        quality.saturating_sub(ADAPTIVE_QUALITY_LOWER_STEP)
+    } else if drop_ratio <= ADAPTIVE_QUALITY_RAISE_RATIO {
+        quality + ADAPTIVE_QUALITY_RAISE_STEP
+    } else {
+        quality
+    };
+    adapted.clamp(min_quality, max_quality)
+}
+
+/// Returns the command starting the screencast of a tab with the given options.
+fn start_screencast_command(opts: &ScreencastOptions) -> Page::StartScreencast {
+    Page::StartScreencast {
+        every_nth_frame: Some(opts.capture_every_nth_frame),
+        format: Some(opts.format.as_screencast_format()),
+        // frames are requested at their full resolution and downscaled by the frame handler:
+        max_height: Some(scaled_dimension(opts.height, opts.device_scale_factor)),
+        max_width: Some(scaled_dimension(opts.width, opts.device_scale_factor)),
+        quality: match opts.adaptive_quality {
+            Some((min_quality, max_quality)) => Some(
+                opts.jpeg_quality
+                    .unwrap_or(max_quality)
+                    .clamp(min_quality, max_quality),
+            ),
+            None => opts.jpeg_quality,
+        },
+    }
+}
+
+/// Returns the number of physical pixels chrome renders for the given number of CSS pixels
+/// at the given device scale factor.
+fn scaled_dimension(css_pixels: u32, device_scale_factor: f32) -> u32 {
+    (css_pixels as f32 * device_scale_factor).round() as u32
+}
+
+/// Reloads the pages of all tabs of the given browser, e.g. on SIGHUP.
+/// The tabs are shared with the browser, the returned closure therefore keeps working while the browser is running.
+/// Screencasting tabs are reloaded like with `ScreencastOptions::reload_interval`, waiting for the `ready_expression`
+/// in the background, so the closure returns right away and does not delay handling a shutdown signal.
+pub fn reload_pages(browser: &Browser) -> impl Fn() + Send + 'static {
+    let tabs = browser.get_tabs().clone();
+    move || {
+        let tabs = tabs.lock().unwrap().clone();
+        let screencasts = SCREENCASTS.lock().unwrap().clone();
+        for tab in tabs.iter() {
+            match screencasts
+                .iter()
+                .find(|screencast| Arc::ptr_eq(&screencast.tab, tab))
+            {
+                Some(screencast) => {
+                    let reloaded = screencast.clone();
+                    screencast.control.spawn(move || reloaded.reload());
+                }
+                None => {
+                    if let Err(err) = tab.reload(false, None) {
+                        warn!("failed to reload page: {}", err);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Stops the screencast of all tabs of the given browser and waits briefly for chrome to flush pending frames.
+/// The threads of each screencast, from the worker handling the frames to the watchdogs, are stopped and joined first,
+/// so none of them restarts a screencast afterwards, and the frame handler contexts are dropped.
+/// Call this before dropping the browser to make sure the chrome process is shut down cleanly.
+pub fn stop_screencasting(browser: &Browser) -> Result<()> {
+    // the tabs are not locked while joining the threads, which may still be talking to chrome:
+    let tabs = browser.get_tabs().lock().unwrap().clone();
+    for screencast in take_screencasts(&tabs) {
+        screencast.control.stop();
+    }
+    for tab in tabs.iter() {
+        map_err(
+            tab.call_method(Page::StopScreencast(Some(serde_json::value::Value::Null))),
+            "failed to stop screencasting",
+        )?;
+    }
+    thread::sleep(Duration::from_millis(500));
+
+    Ok(())
+}
+
+/// What the process does when receiving a signal, see `signal_action`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SignalAction {
+    /// Shut down gracefully, e.g. stopping the screencast and clearing the screen
+    Shutdown,
+    /// Reload the screencasted page
+    Reload,
+    /// Keep running, for signals not handled by this process
+    Ignore,
+}
+
+/// Returns the action for the given signal: SIGINT and SIGTERM (sent by e.g. `systemctl stop`) shut down,
+/// SIGHUP reloads the page.
+#[cfg(unix)]
+pub fn signal_action(sig: i32) -> SignalAction {
+    match sig {
+        SIGINT | SIGTERM => SignalAction::Shutdown,
+        SIGHUP => SignalAction::Reload,
+        _ => SignalAction::Ignore,
+    }
+}
+
+/// Blocks the current thread until the process is asked to shut down, either by a SIGINT or SIGTERM signal
+/// or by a `ShutdownReason` sent to the given channel from another thread, e.g. by `start_screencasting`.
+/// `sender` must belong to the same channel as `receiver`, it is used to forward received signals.
+/// `on_reload` is called for each SIGHUP, see `signal_action`.
+#[cfg(unix)]
+pub fn wait_for_shutdown(
+    sender: Sender<ShutdownReason>,
+    receiver: Receiver<ShutdownReason>,
+    on_reload: impl Fn() + Send + 'static,
+) -> Result<ShutdownReason> {
+    let mut signals = Signals::new(&[SIGINT, SIGTERM, SIGHUP])?;
+    thread::spawn(move || {
+        for sig in signals.forever() {
+            match signal_action(sig) {
+                SignalAction::Shutdown => {
+                    let _ = sender.send(ShutdownReason::Signal(sig));
+                    return;
+                }
+                SignalAction::Reload => {
+                    info!("received signal {}, reloading...", sig);
+                    on_reload();
+                }
+                SignalAction::Ignore => {}
+            }
+        }
+    });
+
+    let reason = receiver.recv()?;
+    info!("{}, exiting...", reason);
+    Ok(reason)
+}
+
+/// Blocks the current thread until the process is asked to shut down, either by a CTRL_C_EVENT
+/// or by a `ShutdownReason` sent to the given channel from another thread, e.g. by `start_screencasting`.
+/// `sender` must belong to the same channel as `receiver`, it is used to forward received events.
+/// Windows has no equivalent of SIGHUP, `on_reload` is never called.
+#[cfg(windows)]
+pub fn wait_for_shutdown(
+    sender: Sender<ShutdownReason>,
+    receiver: Receiver<ShutdownReason>,
+    _on_reload: impl Fn() + Send + 'static,
+) -> Result<ShutdownReason> {
+    // `ctrlc` requires a `Sync` handler:
+    let sender = Mutex::new(sender);
+    ctrlc::set_handler(move || {
+        // CTRL_C_EVENT is reported as SIGINT, like the C runtime does:
+        let _ = sender.lock().unwrap().send(ShutdownReason::Signal(2));
+    })?;
+
+    let reason = receiver.recv()?;
+    info!("{}, exiting...", reason);
+    Ok(reason)
+}
+
+/// Options applied while converting a screencast frame to PPM
+#[derive(Clone, Debug)]
+pub struct ConversionOptions {
+    /// If set, the frame is resized to exactly these dimensions (width, height).
+    /// This guarantees that the PPM matches the LED screen even if chrome returns frames of a slightly different size.
+    /// The target size refers to the frame after applying `orientation`.
+    pub target_size: Option<(u32, u32)>,
+    /// The filter used to resize frames to `target_size`
+    pub resize_filter: FilterType,
+    /// If true, `FilterType::Nearest` is used instead of `resize_filter` for frames which are an exact integer multiple
+    /// of `target_size`, see `is_integer_multiple`. This keeps the pixels of pixel art crisp.
+    pub nearest_for_integer_multiples: bool,
+    /// How frames are resized to `target_size` if their aspect ratios differ
+    pub scaling_mode: ScalingMode,
+    /// The background color used to pad frames resized with `ScalingMode::Fit`
+    pub pad_color: Rgb<u8>,
+    /// If set, only this region (x, y, width, height) of the frame is converted. It is applied before resizing.
+    /// Percentages refer to the width or height of each frame before cropping.
+    pub crop: Option<(Dimension, Dimension, Dimension, Dimension)>,
+    /// If set, frames are cropped to the current bounds of this element instead of `crop`,
+    /// or converted completely while it is not on the page. See `ScreencastOptions::capture_element`.
+    pub capture_element: Option<TrackedElement>,
+    /// If set, the given color correction is applied after resizing.
+    pub color_correction: Option<ColorCorrection>,
+    /// If true, frames are dithered to `dither_levels` values per channel as last conversion step
+    pub dither: bool,
+    /// The number of values per channel used when dithering
+    pub dither_levels: u8,
+    /// The rotation or flip applied to frames after resizing them.
+    /// When rotating by 90° or 270°, frames are resized to the swapped `target_size` before rotating,
+    /// so the frame dimensions match the LED screen after rotating.
+    pub orientation: Orientation,
+    /// If true, the converted PPM is checked to be complete and to match `target_size` using `ppm::validate_ppm`.
+    /// ASCII PPMs (see `ascii_output`) are not validated.
+    pub validate_output: bool,
+    /// If true, frames are encoded as ASCII PPM (magic P3) instead of binary PPM (magic P6).
+    /// ASCII PPMs are several times larger and only intended for inspecting frames while debugging.
+    pub ascii_output: bool,
+    /// PPM has no alpha channel, transparent pixels are therefore blended onto `pad_color` by default.
+    /// If true, transparent pixels are written black instead, which flaschentaschen servers treat as transparent
+    /// on layers above 0, and opaque black pixels are brightened to `Rgb([1, 1, 1])` to stay visible.
+    /// Use this for overlays rendered on top of another layer.
+    pub transparent_overlay: bool,
+    /// The order the color channels of each pixel are written in, applied as last conversion step
+    pub channel_order: ChannelOrder,
+    /// If set, frames are remapped for LED modules of this size (width, height) wired in a serpentine layout,
+    /// see `remap_serpentine`. It is applied after all other steps changing the position of pixels.
+    pub serpentine: Option<(u32, u32)>,
+    /// If true, screencast frames which do not match `target_size` after cropping (before rotating, see `orientation`)
+    /// fail with `FtError::UnexpectedDimensions` instead of being resized, e.g. for pages rendered pixel-perfect
+    /// at the size of the LED screen. Unlike decode errors, these errors count towards the `CircuitBreaker`.
+    /// Only applies to the frames converted by `PpmConverter` and `start_screencasting_with_encoder`,
+    /// images of other sizes, e.g. those of `get_ppm_from_dynamic_image`, are still resized.
+    pub strict_dimensions: bool,
+}
+impl Default for ConversionOptions {
+    fn default() -> Self {
+        ConversionOptions {
+            target_size: None,
+            resize_filter: FilterType::Triangle,
+            nearest_for_integer_multiples: false,
+            scaling_mode: ScalingMode::Stretch,
+            pad_color: Rgb([0, 0, 0]),
+            crop: None,
+            capture_element: None,
+            color_correction: None,
+            dither: false,
+            dither_levels: 8,
+            orientation: Orientation::None,
+            validate_output: false,
+            ascii_output: false,
+            transparent_overlay: false,
+            channel_order: ChannelOrder::Rgb,
+            serpentine: None,
+            strict_dimensions: false,
+        }
+    }
+}
+
+/// Accepts a base64 encoded string of a JPEG image and returns its PPM counterpart as a byte vector.
+pub fn get_ppm_from_jpeg(base64_str: &String) -> FtResult<Vec<u8>> {
+    let buffer = base64::decode(base64_str).map_err(|err| FtError::Decode(err.into()))?;
+    let image = load_from_memory_with_format(&buffer, ImageFormat::Jpeg)
+        .map_err(|err| FtError::Decode(err.into()))?;
+    image_to_ppm(&image)
+}
+
+/// Returns the given image as binary PPM ready to be sent to a flaschentaschen server, without resizing it,
+/// e.g. for images of an own pipeline or a camera. Transparent pixels are sent black, see `flatten_alpha`.
+/// Use `get_ppm_from_dynamic_image` to apply `ConversionOptions`, e.g. to resize the image to the LED screen.
+///
+/// ```
+/// use flaschentaschen_web::{image_to_ppm, FlaschenTaschen};
+/// use image::{DynamicImage, Rgb, RgbImage};
+///
+/// # let server = std::net::UdpSocket::bind("127.0.0.1:0")?;
+/// # let address = server.local_addr()?.to_string();
+/// let image = RgbImage::from_pixel(2, 1, Rgb([255, 0, 0]));
+/// let ppm = image_to_ppm(&DynamicImage::ImageRgb8(image))?;
+/// assert!(ppm.starts_with(b"P6"));
+/// assert!(ppm.ends_with(&[255, 0, 0, 255, 0, 0]));
+///
+/// FlaschenTaschen::new(address)?.send_ppm(&ppm)?;
+/// # Ok::<(), eyre::Report>(())
+/// ```
+pub fn image_to_ppm(img: &DynamicImage) -> FtResult<Vec<u8>> {
+    let encoder = PpmEncoder::default();
+    match img {
+        DynamicImage::ImageRgb8(_) => encoder.encode(img),
+        _ if img.color().has_alpha() => encoder.encode(&DynamicImage::ImageRgb8(flatten_alpha(
+            &img.to_rgba8(),
+            Rgb([0, 0, 0]),
+        ))),
+        _ => encoder.encode(&DynamicImage::ImageRgb8(img.to_rgb8())),
+    }
+}
+
+/// Accepts a base64 encoded string of an image in the given format and returns its PPM counterpart as a byte vector.
+/// The format is either a `ScreencastImageFormat` or any `ImageFormat` the image crate decodes, e.g. `ImageFormat::WebP`.
+/// The given conversion options are applied to the decoded image before encoding it as PPM.
+pub fn get_ppm_from_image(
+    base64_str: &String,
+    format: impl Into<ImageFormat>,
+    opts: &ConversionOptions,
+) -> FtResult<Vec<u8>> {
+    let buffer = base64::decode(base64_str).map_err(|err| FtError::Decode(err.into()))?;
+    get_ppm_from_buffer(buffer.as_slice(), format, opts)
+}
+
+/// Accepts the raw bytes of an image in the given format and returns its PPM counterpart as a byte vector,
+/// see `get_ppm_from_image` for the supported formats.
+/// The given conversion options are applied to the decoded image before encoding it as PPM.
+pub fn get_ppm_from_buffer(
+    buffer: &[u8],
+    format: impl Into<ImageFormat>,
+    opts: &ConversionOptions,
+) -> FtResult<Vec<u8>> {
+    let input_image = load_from_memory_with_format(buffer, format.into())
+        .map_err(|err| FtError::Decode(err.into()))?;
+    get_ppm_from_dynamic_image(input_image, opts)
+}
+
+/// Returns the format of the given image file contents, detected from their magic bytes
+/// or from the extension of the given path if the contents are not recognized.
+pub fn guess_image_format(buffer: &[u8], path: &Path) -> Option<ImageFormat> {
+    image::guess_format(buffer)
+        .ok()
+        .or_else(|| ImageFormat::from_path(path).ok())
+}
+
+/// Accepts an already decoded image, e.g. loaded from a file, and returns its PPM counterpart as a byte vector.
+/// The given conversion options are applied to the image before encoding it as PPM.
+pub fn get_ppm_from_dynamic_image(
+    input_image: DynamicImage,
+    opts: &ConversionOptions,
+) -> FtResult<Vec<u8>> {
+    let output_image = transform_image(input_image, opts, false)?;
+
+    let output = PpmEncoder {
+        ascii: opts.ascii_output,
+    }
+    .encode(&output_image)?;
+    validate_output(&output, opts)?;
+
+    Ok(output)
+}
+
+/// A custom transformation applied to each decoded frame, e.g. to draw an overlay.
+pub type FrameTransform = Box<dyn Fn(DynamicImage) -> Result<DynamicImage> + Send + Sync>;
+
+/// Converts base64 encoded frames to PPM like `get_ppm_from_image`, but reuses its internal buffers
+/// for the decoded frame and the PPM output between frames to avoid allocating them for each frame.
+pub struct PpmConverter {
+    format: ScreencastImageFormat,
+    opts: ConversionOptions,
+    transform: Option<FrameTransform>,
+    decoded: Vec<u8>,
+    output: Vec<u8>,
+    output_dimensions: (u32, u32),
+    keep_last_image: bool,
+    last_image: Option<DynamicImage>,
+}
+impl PpmConverter {
+    /// Returns a new converter for frames of the given format.
+    pub fn new(format: ScreencastImageFormat, opts: ConversionOptions) -> PpmConverter {
+        PpmConverter {
+            format,
+            opts,
+            transform: None,
+            decoded: Vec::new(),
+            output: Vec::new(),
+            output_dimensions: (0, 0),
+            keep_last_image: false,
+            last_image: None,
+        }
+    }
+
+    /// Returns a new converter for frames of the given format, applying the given custom transformation
+    /// to each frame after the conversion options and before encoding it as PPM.
+    pub fn with_transform(
+        format: ScreencastImageFormat,
+        opts: ConversionOptions,
+        transform: FrameTransform,
+    ) -> PpmConverter {
+        PpmConverter {
+            transform: Some(transform),
+            ..PpmConverter::new(format, opts)
+        }
+    }
+
+    /// Accepts a base64 encoded string of an image and returns its PPM counterpart.
+    /// The returned slice is only valid until the next call of this method.
+    pub fn convert(&mut self, base64_str: &str) -> FtResult<&[u8]> {
+        self.decoded.clear();
+        base64::decode_config_buf(base64_str, base64::STANDARD, &mut self.decoded)
+            .map_err(|err| FtError::Decode(err.into()))?;
+        let input_image =
+            load_from_memory_with_format(self.decoded.as_slice(), self.format.as_image_format())
+                .map_err(|err| FtError::Decode(err.into()))?;
+        let mut output_image =
+            transform_image(input_image, &self.opts, self.opts.strict_dimensions)?;
+        if let Some(transform) = &self.transform {
+            output_image = transform(output_image).map_err(|err| FtError::Convert(err.into()))?;
+        }
+
+        self.output.clear();
+        write_ppm(&output_image, self.opts.ascii_output, &mut self.output)?;
+        validate_output(&self.output, &self.opts)?;
+        self.output_dimensions = output_image.dimensions();
+        if self.keep_last_image {
+            self.last_image = Some(output_image);
+        }
+
+        Ok(self.output.as_slice())
+    }
+
+    /// Sets whether the image encoded by `convert` is kept for `last_image`, which is disabled by default
+    /// to not hold on to a full frame which is never used.
+    pub fn set_keep_last_image(&mut self, keep: bool) {
+        self.keep_last_image = keep;
+        if !keep {
+            self.last_image = None;
+        }
+    }
+
+    /// Returns true if the image encoded by `convert` is kept, see `set_keep_last_image`.
+    pub fn keeps_last_image(&self) -> bool {
+        self.keep_last_image
+    }
+
+    /// Returns the image encoded by the last successful call of `convert`, e.g. to save it for debugging.
+    /// Always returns `None` unless enabled by `set_keep_last_image`.
+    pub fn last_image(&self) -> Option<&DynamicImage> {
+        self.last_image.as_ref()
+    }
+
+    /// Converts the given screencast frame like `convert` and returns its metadata alongside its PPM.
+    /// The returned slice is only valid until the next conversion.
+    pub fn convert_frame(
+        &mut self,
+        frame: &Page::events::ScreencastFrameEvent,
+    ) -> FtResult<(&[u8], FrameInfo)> {
+        self.convert(&frame.params.data)?;
+        let (width, height) = self.output_dimensions;
+        let info = FrameInfo {
+            width,
+            height,
+            capture_timestamp: frame.params.metadata.timestamp,
+            source_bytes: self.decoded.len(),
+            ppm_bytes: self.output.len(),
+        };
+        Ok((self.output.as_slice(), info))
+    }
+}
+
+/// Metadata of a converted frame, returned alongside its PPM by `PpmConverter::convert_frame`,
+/// e.g. for the callback of `start_screencasting_with_info`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameInfo {
+    /// The width of the converted frame, after applying the conversion options
+    pub width: u32,
+    /// The height of the converted frame, after applying the conversion options
+    pub height: u32,
+    /// When chrome captured the frame in seconds since the UNIX epoch, see `frame_latency`
+    pub capture_timestamp: Option<f64>,
+    /// The size of the JPEG or PNG frame sent by chrome, in bytes
+    pub source_bytes: usize,
+    /// The size of the converted PPM, in bytes
+    pub ppm_bytes: usize,
+}
+
+/// Encodes converted frames into the byte layout expected by a sink, e.g. PPM for flaschentaschen servers.
+/// Implement this trait to target LED controllers expecting other formats, see `start_screencasting_with_encoder`.
+pub trait FrameEncoder: Send + Sync {
+    /// Returns the encoded bytes of the given frame.
+    fn encode(&self, img: &DynamicImage) -> FtResult<Vec<u8>>;
+}
+
+/// Encodes frames as binary PPM (magic P6), or as ASCII PPM (magic P3) if `ascii` is true
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PpmEncoder {
+    pub ascii: bool,
+}
+impl FrameEncoder for PpmEncoder {
+    fn encode(&self, img: &DynamicImage) -> FtResult<Vec<u8>> {
+        let mut output = Vec::new();
+        write_ppm(img, self.ascii, &mut output)?;
+        Ok(output)
+    }
+}
+
+/// Encodes the given image as binary PPM (magic P6), or as ASCII PPM (magic P3) if `ascii` is true,
+/// and appends it to the given output buffer.
+fn write_ppm(image: &DynamicImage, ascii: bool, output: &mut Vec<u8>) -> FtResult<()> {
+    let encoding = if ascii {
+        SampleEncoding::Ascii
+    } else {
+        SampleEncoding::Binary
+    };
+    image
+        .write_to(output, ImageOutputFormat::Pnm(PNMSubtype::Pixmap(encoding)))
+        .map_err(|err| match err {
+            ImageError::IoError(err) => FtError::Io(err),
+            err => FtError::Convert(err.into()),
+        })?;
+
+    Ok(())
+}
+
+/// Validates the given converted PPM if enabled by the given options.
+fn validate_output(ppm: &[u8], opts: &ConversionOptions) -> FtResult<()> {
+    match opts.target_size {
+        Some((width, height)) if opts.validate_output && !opts.ascii_output => {
+            validate_ppm(ppm, width, height).map_err(|err| FtError::Convert(err.into()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Applies the given conversion options to a decoded frame.
+/// Fails for crops not covering any pixel of the frame and, if `strict_dimensions` is true,
+/// for frames of unexpected dimensions, see `ConversionOptions::strict_dimensions`.
+fn transform_image(
+    mut image: DynamicImage,
+    opts: &ConversionOptions,
+    strict_dimensions: bool,
+) -> FtResult<DynamicImage> {
+    match (&opts.capture_element, opts.crop) {
+        (Some(element), _) => {
+            // the element moves with the page, it is cut off silently where it leaves the frame:
+            let bounds = element
+                .bounds()
+                .map(|bounds| intersect_frame(bounds, image.dimensions()))
+                .filter(|(_, _, width, height)| *width > 0 && *height > 0);
+            if let Some((x, y, width, height)) = bounds {
+                image = image.crop_imm(x, y, width, height);
+            }
+        }
+        (None, Some((x, y, width, height))) => {
+            let (frame_width, frame_height) = image.dimensions();
+            let crop = (
+                x.resolve(frame_width),
+                y.resolve(frame_height),
+                width.resolve(frame_width),
+                height.resolve(frame_height),
+            );
+            let (x, y, width, height) = clamp_crop(crop, image.dimensions())?;
+            image = image.crop_imm(x, y, width, height);
+        }
+        (None, None) => {}
+    }
+
+    if let Some(target_size) = opts.target_size {
+        let (width, height) = opts.orientation.source_dimensions(target_size);
+        if strict_dimensions && image.dimensions() != (width, height) {
+            return Err(FtError::UnexpectedDimensions {
+                expected: (width, height),
+                actual: image.dimensions(),
+            });
+        }
+        if image.dimensions() != (width, height) {
+            trace!(
+                "resizing frame from {:?} to {}x{}",
+                image.dimensions(),
+                width,
+                height
+            );
+            let filter = if opts.nearest_for_integer_multiples
+                && is_integer_multiple(image.dimensions(), (width, height))
+            {
+                FilterType::Nearest
+            } else {
+                opts.resize_filter
+            };
+            image = match opts.scaling_mode {
+                ScalingMode::Stretch => image.resize_exact(width, height, filter),
+                ScalingMode::Fit => {
+                    DynamicImage::ImageRgb8(fit_into(&image, width, height, opts.pad_color, filter))
+                }
+                ScalingMode::Fill => image.resize_to_fill(width, height, filter),
+            };
+        }
+    }
+    image = opts.orientation.apply(image);
+
+    // the transparency is keyed as last step, color correction and dithering could turn visible pixels black otherwise:
+    let transparency = if !image.color().has_alpha() {
+        None
+    } else if opts.transparent_overlay {
+        Some(image.to_rgba8())
+    } else {
+        image = DynamicImage::ImageRgb8(flatten_alpha(&image.to_rgba8(), opts.pad_color));
+        None
+    };
+
+    if let Some(correction) = &opts.color_correction {
+        let mut rgb_image = image.into_rgb8();
+        color_correct(&mut rgb_image, correction);
+        image = DynamicImage::ImageRgb8(rgb_image);
+    }
+
+    if opts.dither {
+        let mut rgb_image = image.into_rgb8();
+        dither_floyd_steinberg(&mut rgb_image, opts.dither_levels);
+        image = DynamicImage::ImageRgb8(rgb_image);
+    }
+
+    if let Some(transparency) = transparency {
+        image = DynamicImage::ImageRgb8(key_transparency(image.into_rgb8(), &transparency));
+    }
+
+    if let Some((module_width, module_height)) = opts.serpentine {
+        image = DynamicImage::ImageRgb8(remap_serpentine(
+            &image.into_rgb8(),
+            module_width,
+            module_height,
+        ));
+    }
+
+    // the channels are reordered last, all other steps expect RGB pixels:
+    if opts.channel_order != ChannelOrder::Rgb {
+        let mut rgb_image = image.into_rgb8();
+        opts.channel_order.apply(&mut rgb_image);
+        image = DynamicImage::ImageRgb8(rgb_image);
+    }
+
+    Ok(image)
+}
+
+/// Returns true if the given source dimensions are the given target dimensions scaled by the same integer factor
+/// on both axes, e.g. 128x64 for 64x32. Downscaling such frames with `FilterType::Nearest` keeps exactly one pixel
+/// of each block, so crisp pixel art stays crisp.
+pub fn is_integer_multiple(source: (u32, u32), target: (u32, u32)) -> bool {
+    let ((source_width, source_height), (target_width, target_height)) = (source, target);
+    if target_width == 0 || target_height == 0 {
+        return false;
+    }
+    source_width % target_width == 0
+        && source_height % target_height == 0
+        && source_width / target_width == source_height / target_height
+        && source_width >= target_width
+}
+
+/// Writes the transparent pixels of the given RGBA image black into the given image of the same dimensions,
+/// and brightens its opaque black pixels, see `ConversionOptions::transparent_overlay`.
+fn key_transparency(mut image: RgbImage, transparency: &RgbaImage) -> RgbImage {
+    for (pixel, source) in image.pixels_mut().zip(transparency.pixels()) {
+        if source[3] < 128 {
+            *pixel = Rgb([0, 0, 0]);
+        } else if *pixel == Rgb([0, 0, 0]) {
+            *pixel = Rgb([1, 1, 1]);
+        }
+    }
+    image
+}
+
+/// Remaps the given image for LED modules of the given size wired in a serpentine (boustrophedon) layout:
+/// the odd pixel rows of each module run from right to left, so they are mirrored within the module.
+/// Modules exceeding the image at its right or bottom edge are clipped to it.
+/// A module size of 0 stands for the size of the image, i.e. a single module.
+///
+/// The remap is its own inverse: applying it twice returns the original image.
+pub fn remap_serpentine(img: &RgbImage, module_width: u32, module_height: u32) -> RgbImage {
+    let (width, height) = img.dimensions();
+    let module_width = if module_width == 0 {
+        width
+    } else {
+        module_width
+    };
+    let module_height = if module_height == 0 {
+        height
+    } else {
+        module_height
+    };
+    RgbImage::from_fn(width, height, |x, y| {
+        if (y % module_height) % 2 == 0 {
+            return *img.get_pixel(x, y);
+        }
+        let module_x = x - x % module_width;
+        let module_right = (module_x + module_width).min(width) - 1;
+        *img.get_pixel(module_right - (x - module_x), y)
+    })
+}
+
+/// Resizes the given image to fit into the given dimensions while preserving its aspect ratio.
+/// The resized image is centered and the remaining space is padded with the given background color.
+pub fn fit_into(
+    img: &DynamicImage,
+    width: u32,
+    height: u32,
+    bg: Rgb<u8>,
+    filter: FilterType,
+) -> RgbImage {
+    let resized = img.resize(width, height, filter).into_rgb8();
+    let mut output = RgbImage::from_pixel(width, height, bg);
+    imageops::overlay(
+        &mut output,
+        &resized,
+        (width - resized.width()) / 2,
+        (height - resized.height()) / 2,
+    );
+
+    output
+}
+
+/// Replaces the transparency of the given image by blending it onto the given background color.
+/// PPM has no alpha channel, transparent pixels would otherwise show their undefined color values.
+pub fn flatten_alpha(img: &RgbaImage, bg: Rgb<u8>) -> RgbImage {
+    RgbImage::from_fn(img.width(), img.height(), |x, y| {
+        let pixel = img.get_pixel(x, y);
+        let alpha = pixel[3] as u16;
+        let blend = |channel: usize| {
+            ((pixel[channel] as u16 * alpha + bg[channel] as u16 * (255 - alpha)) / 255) as u8
+        };
+        Rgb([blend(0), blend(1), blend(2)])
+    })
+}
+
+/// Clamps the given crop rectangle (x, y, width, height) to the given image dimensions.
+/// Logs a warning if the rectangle exceeds the bounds of the image, fails if nothing of it is left.
+fn clamp_crop(
+    crop: (u32, u32, u32, u32),
+    dimensions: (u32, u32),
+) -> FtResult<(u32, u32, u32, u32)> {
+    let (image_width, image_height) = dimensions;
+    let clamped = intersect_frame(crop, dimensions);
+    // an empty frame cannot be resized to the screen, e.g. for a crop starting at 100% of the frame width:
+    if clamped.2 == 0 || clamped.3 == 0 {
+        return Err(FtError::Convert(
+            format!(
+                "crop region {:?} does not cover any pixel of the frame of {}x{} pixels",
+                crop, image_width, image_height
+            )
+            .into(),
+        ));
+    }
+    if clamped != crop {
+        warn!(
+            "crop region {:?} exceeds the frame dimensions {}x{}, clamping it to {:?}",
+            crop, image_width, image_height, clamped
+        );
+    }
+
+    Ok(clamped)
+}
+
+/// Returns the part of the given rectangle (x, y, width, height) within a frame of the given dimensions.
+fn intersect_frame(
+    rect: (u32, u32, u32, u32),
+    (width, height): (u32, u32),
+) -> (u32, u32, u32, u32) {
+    let (x, y, rect_width, rect_height) = rect;
+    let x = x.min(width);
+    let y = y.min(height);
+    (x, y, rect_width.min(width - x), rect_height.min(height - y))
+}
+
+/// Starts screencasting like `start_screencasting`, but converts the frames to PPM before passing them to `on_ppm`.
+/// Each frame is converted using the given conversion options, followed by the given custom `transform`,
+/// e.g. to draw an overlay or apply a custom filter.
+///
+/// Both `transform` and `on_ppm` run on the worker thread handling the frames queued by `start_screencasting`,
+/// never on the caller's thread. Frames are handled one at a time, a slow `transform` causes older frames
+/// to be dropped once `ScreencastOptions::frame_queue_capacity` is exceeded.
+pub fn start_screencasting_with_transform<T, F, C>(
+    opts: ScreencastOptions,
+    conversion_opts: ConversionOptions,
+    transform: T,
+    on_ppm: F,
+    on_ppm_context: Arc<C>,
+) -> Result<Browser>
+where
+    C: 'static + Send + Sync,
+    T: 'static + Fn(DynamicImage) -> Result<DynamicImage> + Send + Sync,
+    F: 'static + Fn(&[u8], &C) -> Result<()> + Send + Sync,
+{
+    let converter = Mutex::new(PpmConverter::with_transform(
+        opts.format,
+        conversion_opts,
+        Box::new(transform),
+    ));
+    start_screencasting(
+        opts,
+        move |frame: &Page::events::ScreencastFrameEvent, context: &C| {
+            let mut converter = converter.lock().unwrap();
+            let ppm = converter.convert(&frame.params.data)?;
+            on_ppm(ppm, context)
+        },
+        on_ppm_context,
+    )
+}
+
+/// Starts screencasting like `start_screencasting_with_transform` without a transformation, but passes
+/// the metadata of each frame alongside its PPM to `on_ppm`, e.g. to collect own stats or to record frames.
+pub fn start_screencasting_with_info<F, C>(
+    opts: ScreencastOptions,
+    conversion_opts: ConversionOptions,
+    on_ppm: F,
+    on_ppm_context: Arc<C>,
+) -> Result<Browser>
+where
+    C: 'static + Send + Sync,
+    F: 'static + Fn(&[u8], &FrameInfo, &C) -> Result<()> + Send + Sync,
+{
+    let converter = Mutex::new(PpmConverter::new(opts.format, conversion_opts));
+    start_screencasting(
+        opts,
+        move |frame: &Page::events::ScreencastFrameEvent, context: &C| {
+            let mut converter = converter.lock().unwrap();
+            let (ppm, info) = converter.convert_frame(frame)?;
+            on_ppm(ppm, &info, context)
+        },
+        on_ppm_context,
+    )
+}
+
+/// Starts screencasting like `start_screencasting`, but encodes the frames using the given encoder
+/// before passing them to `on_frame`. Each frame is converted using the given conversion options before encoding it.
+///
+/// Both `encoder` and `on_frame` run on the worker thread handling the frames queued by `start_screencasting`,
+/// see `start_screencasting_with_transform`.
+pub fn start_screencasting_with_encoder<F, C>(
+    opts: ScreencastOptions,
+    conversion_opts: ConversionOptions,
+    encoder: Box<dyn FrameEncoder>,
+    on_frame: F,
+    on_frame_context: Arc<C>,
+) -> Result<Browser>
+where
+    C: 'static + Send + Sync,
+    F: 'static + Fn(&[u8], &C) -> Result<()> + Send + Sync,
+{
+    let format = opts.format;
+    start_screencasting(
+        opts,
+        move |frame: &Page::events::ScreencastFrameEvent, context: &C| {
+            let buffer =
+                base64::decode(&frame.params.data).map_err(|err| FtError::Decode(err.into()))?;
+            let image = load_from_memory_with_format(buffer.as_slice(), format.as_image_format())
+                .map_err(|err| FtError::Decode(err.into()))?;
+            let image =
+                transform_image(image, &conversion_opts, conversion_opts.strict_dimensions)?;
+            let encoded = encoder.encode(&image)?;
+            on_frame(encoded.as_slice(), context)
+        },
+        on_frame_context,
+    )
+}
+
+/// Returns a copy of the given P6 PPM with the flaschentaschen offset comment `# FT: <x> <y> <layer>` inserted
+/// right after the magic line, before the dimensions line.
+pub fn insert_ft_offset(ppm: &[u8], x: u32, y: u32, layer: u8) -> Result<Vec<u8>> {
+    if !ppm.starts_with(b"P6") {
+        return Err(eyre!("expected a P6 PPM, got an unknown magic number"));
+    }
+    // skip the whitespace separating the magic from the dimensions, we write our own line breaks:
+    let header_rest = &ppm[2..];
+    let dimensions_start = header_rest
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .ok_or_else(|| eyre!("PPM header is missing its dimensions"))?;
+
+    let offset_line = format!("P6\n# FT: {} {} {}\n", x, y, layer);
+    let mut output = Vec::with_capacity(offset_line.len() + ppm.len());
+    output.extend_from_slice(offset_line.as_bytes());
+    output.extend_from_slice(&header_rest[dimensions_start..]);
+
+    Ok(output)
+}
+
+/// A token bucket limiting the number of bytes sent per second.
+/// The bucket holds up to `burst` bytes and is refilled with `bytes_per_sec` bytes per second,
+/// so short bursts above the rate are allowed as long as the average stays below it.
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    burst: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+impl RateLimiter {
+    /// Returns a new rate limiter allowing the given number of bytes per second, with a burst of one second.
+    pub fn new(bytes_per_sec: u64) -> RateLimiter {
+        RateLimiter::with_burst(bytes_per_sec, bytes_per_sec)
+    }
+
+    /// Returns a new rate limiter allowing the given number of bytes per second and bursts of up to `burst` bytes.
+    /// The bucket starts full.
+    pub fn with_burst(bytes_per_sec: u64, burst: u64) -> RateLimiter {
+        RateLimiter {
+            bytes_per_sec,
+            burst,
+            tokens: burst as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Takes the given number of bytes from the bucket if they are available and returns true,
+    /// or returns false without taking anything if sending them now would exceed the rate.
+    pub fn try_acquire(&mut self, bytes: u64) -> bool {
+        self.try_acquire_at(bytes, Instant::now())
+    }
+
+    /// Like `try_acquire`, but refills the bucket up to the given point in time instead of now.
+    /// Sends larger than `burst` are allowed whenever the bucket is full, otherwise they could never be sent.
+    pub fn try_acquire_at(&mut self, bytes: u64, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * self.bytes_per_sec as f64)
+            .min(self.burst as f64);
+        self.last_refill = now;
+
+        let required = bytes.min(self.burst) as f64;
+        if self.tokens < required {
+            return false;
+        }
+        self.tokens -= required;
+        true
+    }
 }
@@ -0,0 +1,88 @@
+use crate::control::ScreencastControl;
+use crate::{FlaschenTaschen, FlaschenTaschenGroup, ShutdownReason};
+use log::{debug, error, info, warn};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Starts a thread regularly sending the size query to all servers of the given group until the given control is stopped.
+/// Once a server did not answer `max_failures` consecutive queries, it is considered dead.
+/// Like sending frames to the group, which only fails if all servers failed, a `ShutdownReason::ServerUnresponsive`
+/// is sent to the given channel once all probed servers are dead.
+///
+/// This detects servers which are down even though sending to them succeeds: UDP is connectionless,
+/// `send` on a connected socket only fails if the platform happened to receive an ICMP error before.
+/// Servers not answering the first query are assumed to not support it and are not probed,
+/// like the stock flaschentaschen server. If no server supports it, the thread exits right away.
+///
+/// The queries are sent from a socket of their own, see `FlaschenTaschen::with_own_socket`,
+/// so they neither race with the frames sent to the server nor with `FlaschenTaschen::send_ppm_checked`.
+pub fn spawn_liveness_probe(
+    flaschentaschen: Arc<FlaschenTaschenGroup>,
+    interval: Duration,
+    max_failures: u32,
+    shutdown_sender: Sender<ShutdownReason>,
+    control: Arc<ScreencastControl>,
+) {
+    let thread_control = control.clone();
+    control.spawn(move || {
+        let control = thread_control;
+        let mut probed: Vec<(FlaschenTaschen, u32)> = flaschentaschen
+            .members
+            .iter()
+            .filter_map(|member| {
+                let probe = match member.with_own_socket() {
+                    Ok(probe) => probe,
+                    Err(err) => {
+                        warn!("{}, the liveness of {} is not probed", err, member);
+                        return None;
+                    }
+                };
+                match probe.query_size() {
+                    Ok(_) => Some((probe, 0)),
+                    Err(err) => {
+                        warn!("{}, its liveness is not probed", err);
+                        None
+                    }
+                }
+            })
+            .collect();
+        if probed.is_empty() {
+            return;
+        }
+        info!(
+            "probing the liveness of {} server(s) every {}ms",
+            probed.len(),
+            interval.as_millis()
+        );
+
+        while control.sleep(interval) {
+            for (member, failures) in probed.iter_mut() {
+                match member.query_size() {
+                    Ok(_) => {
+                        debug!("{} answered the liveness probe", member);
+                        *failures = 0;
+                    }
+                    Err(err) => {
+                        *failures += 1;
+                        warn!(
+                            "liveness probe failed ({}/{}): {}",
+                            failures, max_failures, err
+                        );
+                    }
+                }
+                if *failures == max_failures {
+                    error!(
+                        "{} did not answer {} consecutive liveness probes, it is considered dead",
+                        member, max_failures
+                    );
+                }
+            }
+            if probed.iter().all(|(_, failures)| *failures >= max_failures) {
+                error!("all probed servers are dead");
+                let _ = shutdown_sender.send(ShutdownReason::ServerUnresponsive);
+                return;
+            }
+        }
+    });
+}
@@ -0,0 +1,89 @@
+use clap::ArgEnum;
+use eyre::Result;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::Deserialize;
+use serde_json::json;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The format of the log output
+#[derive(ArgEnum, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// Human readable log lines
+    Human,
+    /// One JSON object per line, for log collectors
+    Json,
+}
+
+/// Returns the level filter for the given verbosity, mapped the same way as `loggerv` does.
+fn level_filter(verbosity: u64) -> LevelFilter {
+    match verbosity {
+        0 => LevelFilter::Error,
+        1 => LevelFilter::Warn,
+        2 => LevelFilter::Info,
+        3 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// Initializes the global logger with the given format and verbosity (0 = errors only, 4 = trace).
+/// All log output is written to stderr.
+pub fn init_logging(format: LogFormat, verbosity: u64) -> Result<()> {
+    match format {
+        // loggerv writes info and lower levels to stdout by default, which is reserved for frames, see `--stdout-ppm`:
+        LogFormat::Human => loggerv::Logger::new()
+            .verbosity(verbosity)
+            .output(&Level::Info, loggerv::Output::Stderr)
+            .output(&Level::Debug, loggerv::Output::Stderr)
+            .output(&Level::Trace, loggerv::Output::Stderr)
+            .init()?,
+        LogFormat::Json => {
+            let level = level_filter(verbosity);
+            log::set_boxed_logger(Box::new(JsonLogger { level }))?;
+            log::set_max_level(level);
+        }
+    }
+
+    Ok(())
+}
+
+/// A logger writing each record as JSON object to stderr, e.g.
+/// `{"timestamp":1641234567.123,"level":"INFO","module":"flaschentaschen_web","message":"..."}`
+struct JsonLogger {
+    level: LevelFilter,
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs_f64())
+            .unwrap_or_default();
+        let mut line = json!({
+            "timestamp": timestamp,
+            "level": record.level().to_string(),
+            "module": record.module_path().unwrap_or_else(|| record.target()),
+            "message": record.args().to_string(),
+        });
+        if record.level() >= Level::Debug {
+            if let (Some(file), Some(line_number)) = (record.file(), record.line()) {
+                line["location"] = json!(format!("{}:{}", file, line_number));
+            }
+        }
+
+        let _ = writeln!(std::io::stderr(), "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
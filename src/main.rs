@@ -1,61 +1,1193 @@
 use clap::Parser;
-use color_eyre::eyre::Result;
-use flaschentaschen_web::{cli::CliArgs, ScreencastOptions};
-use flaschentaschen_web::{get_ppm_from_jpeg, start_screencasting, FlaschenTaschen};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use flaschentaschen_web::animation::{decode_gif, play_animation};
+use flaschentaschen_web::cli::{CliArgs, FrameSource};
+use flaschentaschen_web::control::ScreencastControl;
+use flaschentaschen_web::cookies::load_cookies_file;
+use flaschentaschen_web::diff::diff_regions;
+use flaschentaschen_web::health::spawn_health_server;
+use flaschentaschen_web::liveness::spawn_liveness_probe;
+use flaschentaschen_web::logging::init_logging;
+use flaschentaschen_web::pattern::test_patterns;
+use flaschentaschen_web::ppm::{binary_ppm_size, solid_ppm, validate_ppm};
+use flaschentaschen_web::record::{FileSink, FtSink, Recording, RecordingSink};
+use flaschentaschen_web::region::RegionSpec;
+use flaschentaschen_web::stats::{spawn_stats_logger, Stats};
+use flaschentaschen_web::TrackedElement;
+use flaschentaschen_web::{capture_single_frame, start_screencasting};
+use flaschentaschen_web::{color::ColorCorrection, get_ppm_from_dynamic_image};
+use flaschentaschen_web::{get_ppm_from_buffer, guess_image_format};
+use flaschentaschen_web::{launch_browser, start_screencasting_tab, Orientation};
+use flaschentaschen_web::{reload_pages, stop_screencasting, wait_for_shutdown};
+use flaschentaschen_web::{BrowserSource, ConversionOptions, ResizeFilter};
+use flaschentaschen_web::{FlaschenTaschen, FlaschenTaschenGroup, MAX_DATAGRAM_SIZE};
+use flaschentaschen_web::{FrameEncoder, OutputProtocol, PpmEncoder};
+use flaschentaschen_web::{PpmConverter, RateLimiter, ScreencastOptions, ShutdownReason};
 use headless_chrome::protocol::cdp::Page;
-use log::info;
-use signal_hook::{consts::SIGINT, iterator::Signals};
+use headless_chrome::Browser;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use log::{error, info, trace, warn};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The exit code used if screencasting stopped because of too many errors (`EX_TEMPFAIL` of sysexits.h)
+const EXIT_CODE_TEMPFAIL: i32 = 75;
+
+/// The context passed to each invocation of `on_screencast_frame`.
+struct FrameContext {
+    /// The servers, and the recording if --record is enabled
+    sink: Arc<dyn FtSink>,
+    converter: Mutex<PpmConverter>,
+    stats: Arc<Stats>,
+    frame_dump: Option<FrameDump>,
+    partial_update: Option<PartialUpdate>,
+    offset: FrameOffset,
+    /// Shared by all regions, the bandwidth budget applies to this process as a whole
+    rate_limiter: Option<Arc<Mutex<RateLimiter>>>,
+    unchanged_filter: Option<UnchangedFilter>,
+    pacer: Option<FramePacer>,
+}
+impl FrameContext {
+    /// Returns a context for the frames of a region at the given offset, see `--region`.
+    /// The region has its own converter and filters, but shares the servers, stats and rate limiter of this context.
+    fn for_region(&self, converter: PpmConverter, offset: FrameOffset) -> FrameContext {
+        FrameContext {
+            sink: self.sink.clone(),
+            converter: Mutex::new(converter),
+            stats: self.stats.clone(),
+            frame_dump: None,
+            partial_update: self
+                .partial_update
+                .as_ref()
+                .map(|partial_update| PartialUpdate {
+                    block_size: partial_update.block_size,
+                    encoder: partial_update.encoder,
+                    validate_output: partial_update.validate_output,
+                    state: Mutex::new(PartialUpdateState {
+                        previous: None,
+                        frames_since_full: 0,
+                    }),
+                }),
+            offset,
+            rate_limiter: self.rate_limiter.clone(),
+            unchanged_filter: self.unchanged_filter.as_ref().map(|unchanged_filter| {
+                UnchangedFilter {
+                    keyframe_interval: unchanged_filter.keyframe_interval,
+                    state: Mutex::new(UnchangedFilterState {
+                        previous_hash: None,
+                        last_sent: Instant::now(),
+                    }),
+                }
+            }),
+            pacer: None,
+        }
+    }
+}
+
+/// The position of all frames of this process on the LED screen, see `FlaschenTaschen::send_ppm_at`
+#[derive(Clone, Copy, PartialEq)]
+struct FrameOffset {
+    x: u32,
+    y: u32,
+    layer: u8,
+}
+
+/// The number of frames sent as partial updates before sending a full frame again.
+/// Lost datagrams would otherwise leave outdated regions on the LED screen forever.
+const FULL_FRAME_INTERVAL: u32 = 100;
+
+/// Sends only the regions of each frame which changed since the previous frame.
+struct PartialUpdate {
+    block_size: u32,
+    /// Encodes the changed regions as binary PPM, the offset of each region can only be inserted into a P6 header
+    encoder: PpmEncoder,
+    /// Validates the encoded regions like `ConversionOptions::validate_output` validates full frames
+    validate_output: bool,
+    state: Mutex<PartialUpdateState>,
+}
+struct PartialUpdateState {
+    previous: Option<RgbImage>,
+    frames_since_full: u32,
+}
+/// The changes of a frame prepared by `PartialUpdate::prepare`, sent by `PartialUpdate::send`.
+struct PreparedUpdate {
+    /// The PPM of each changed region with its position (x, y) in the frame, `None` if the full frame is sent
+    regions: Option<Vec<(u32, u32, Vec<u8>)>>,
+    next: RgbImage,
+}
+impl PreparedUpdate {
+    /// Returns the number of bytes sent to each server, the given number of bytes of the full PPM if it is sent.
+    fn bytes(&self, full_bytes: usize) -> usize {
+        self.regions.as_ref().map_or(full_bytes, |regions| {
+            regions
+                .iter()
+                .map(|(_, _, region_ppm)| region_ppm.len())
+                .sum()
+        })
+    }
+}
+impl PartialUpdate {
+    /// Encodes the regions of the given converted frame which changed since the previously sent frame,
+    /// or prepares sending the whole frame if there is no comparable previous frame.
+    /// The frame already went through all conversion steps, the regions only need to be encoded.
+    fn prepare(&self, image: &DynamicImage) -> Result<PreparedUpdate> {
+        let next = image.to_rgb8();
+        let state = self.state.lock().unwrap();
+        let regions = match &state.previous {
+            Some(previous) if state.frames_since_full < FULL_FRAME_INTERVAL => Some(
+                diff_regions(previous, &next, self.block_size)
+                    .into_iter()
+                    .map(|(rect, contents)| {
+                        let region_ppm = self.encoder.encode(&DynamicImage::ImageRgb8(contents))?;
+                        if self.validate_output {
+                            validate_ppm(&region_ppm, rect.width, rect.height)?;
+                        }
+                        Ok((rect.x, rect.y, region_ppm))
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            _ => None,
+        };
+
+        Ok(PreparedUpdate { regions, next })
+    }
+
+    /// Sends the given prepared changes to the given sink, or the given full PPM if there are none.
+    /// The frame is only compared to the next one once it was sent. Returns the number of sent bytes.
+    fn send(
+        &self,
+        sink: &dyn FtSink,
+        prepared: PreparedUpdate,
+        ppm: &[u8],
+        offset: FrameOffset,
+    ) -> Result<usize> {
+        let sent_bytes = prepared.bytes(ppm.len());
+        let mut state = self.state.lock().unwrap();
+        match &prepared.regions {
+            Some(regions) => {
+                for (x, y, region_ppm) in regions {
+                    sink.send_frame_at(region_ppm, offset.x + x, offset.y + y, offset.layer)?;
+                }
+                state.frames_since_full += 1;
+            }
+            None => {
+                send_frame(sink, ppm, offset)?;
+                state.frames_since_full = 0;
+            }
+        }
+
+        state.previous = Some(prepared.next);
+        Ok(sent_bytes)
+    }
+}
+
+/// Detects frames identical to the previously sent one, so they do not need to be sent again.
+struct UnchangedFilter {
+    /// Identical frames are sent anyway after this duration, e.g. to redraw a restarted server.
+    /// Identical frames are never sent if `None`.
+    keyframe_interval: Option<Duration>,
+    state: Mutex<UnchangedFilterState>,
+}
+struct UnchangedFilterState {
+    previous_hash: Option<u64>,
+    last_sent: Instant,
+}
+impl UnchangedFilter {
+    /// Returns the hash identifying the given PPM, see `is_unchanged`.
+    fn hash(ppm: &[u8]) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        ppm.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns true if the PPM of the given hash equals the previously sent one
+    /// and the keyframe interval has not passed yet.
+    fn is_unchanged(&self, hash: u64) -> bool {
+        let state = self.state.lock().unwrap();
+        let keyframe_due = self
+            .keyframe_interval
+            .map_or(false, |interval| state.last_sent.elapsed() >= interval);
+        state.previous_hash == Some(hash) && !keyframe_due
+    }
+
+    /// Remembers the PPM of the given hash as the previously sent one.
+    /// Only called once the frame was sent, a dropped or failed frame must not hide the identical frames following it.
+    fn record_sent(&self, hash: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.previous_hash = Some(hash);
+        state.last_sent = Instant::now();
+    }
+}
+
+/// Sends the most recent frame at a fixed interval instead of each frame as it arrives, see `--fixed-fps`.
+/// The last frame is sent again if chrome sent no new one in the meantime.
+struct FramePacer {
+    interval: Duration,
+    latest: Mutex<Option<Vec<u8>>>,
+    /// Stops and joins the sending thread, see `spawn_frame_pacer`
+    control: ScreencastControl,
+}
+impl FramePacer {
+    /// Replaces the frame sent on the next tick with the given PPM.
+    fn replace(&self, ppm: &[u8]) {
+        let mut latest = self.latest.lock().unwrap();
+        match latest.as_mut() {
+            Some(latest) => {
+                latest.clear();
+                latest.extend_from_slice(ppm);
+            }
+            None => *latest = Some(ppm.to_vec()),
+        }
+    }
+}
+
+/// Spawns the thread sending the frames of the pacer of the given context until its control is stopped.
+/// Ticks are scheduled from the start time, so a slow send does not delay all following frames.
+/// Send errors are only logged, they do not count towards --error-threshold.
+fn spawn_frame_pacer(context: Arc<FrameContext>) {
+    let pacer = match &context.pacer {
+        Some(pacer) => pacer,
+        None => return,
+    };
+    let thread_context = context.clone();
+    pacer.control.spawn(move || {
+        let context = thread_context;
+        let pacer = context.pacer.as_ref().unwrap();
+        // the frame is copied out of the lock, converting the next frame must not wait for a slow send:
+        let mut ppm = Vec::new();
+        let mut next_tick = Instant::now();
+        loop {
+            next_tick += pacer.interval;
+            let now = Instant::now();
+            if next_tick > now {
+                if !pacer.control.sleep(next_tick - now) {
+                    break;
+                }
+            } else if pacer.control.is_stopped() {
+                break;
+            } else {
+                // skip the ticks missed while sending, they would otherwise be sent in a burst:
+                next_tick = now;
+            }
+
+            match pacer.latest.lock().unwrap().as_deref() {
+                Some(latest) => {
+                    ppm.clear();
+                    ppm.extend_from_slice(latest);
+                }
+                None => continue,
+            }
+            let ppm = ppm.as_slice();
+            if let Some(rate_limiter) = &context.rate_limiter {
+                let bytes = (ppm.len() * context.sink.destinations()) as u64;
+                if !rate_limiter.lock().unwrap().try_acquire(bytes) {
+                    trace!("dropping frame to respect the maximum bytes per second");
+                    context.stats.record_rate_limited_frame();
+                    continue;
+                }
+            }
+            match send_frame(context.sink.as_ref(), ppm, context.offset) {
+                Ok(()) => context.stats.record_sent_frame(ppm.len()),
+                Err(err) => error!("{}", err),
+            }
+        }
+    });
+}
+
+/// Saves every nth converted frame as PNG file to a directory for debugging.
+struct FrameDump {
+    directory: PathBuf,
+    every: u64,
+    frame_count: AtomicU64,
+}
+impl FrameDump {
+    /// Saves the given frame if it is the nth frame since the last saved one.
+    /// Failures are only logged, they should not interrupt the screencast.
+    fn dump(&self, frame: &Page::events::ScreencastFrameEvent, image: &DynamicImage) {
+        if self.frame_count.fetch_add(1, Ordering::Relaxed) % self.every != 0 {
+            return;
+        }
+
+        let timestamp_ms = match frame.params.metadata.timestamp {
+            Some(timestamp) => (timestamp * 1000.0) as u128,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_millis()),
+        };
+        let path = self.directory.join(format!("frame_{}.png", timestamp_ms));
+        if let Err(err) = image.save(&path) {
+            warn!("failed to dump frame to {}: {}", path.display(), err);
+        }
+    }
+}
 
 /// handles an incoming screencast frame from the browser by converting it to PPM
-/// and sending it to the flaschentaschen server.
+/// and sending it to all flaschentaschen servers.
 fn on_screencast_frame(
     frame: &Page::events::ScreencastFrameEvent,
-    flaschentaschen: &FlaschenTaschen,
+    context: &FrameContext,
+) -> Result<()> {
+    let mut converter = context.converter.lock().unwrap();
+    let ppm = converter.convert(&frame.params.data)?;
+
+    let unchanged_hash = match &context.unchanged_filter {
+        Some(unchanged_filter) => {
+            let hash = UnchangedFilter::hash(ppm);
+            if unchanged_filter.is_unchanged(hash) {
+                trace!("skipping unchanged frame");
+                Stats::add(&context.stats.frames_skipped, 1);
+                return Ok(());
+            }
+            Some((unchanged_filter, hash))
+        }
+        None => None,
+    };
+
+    // the pacer thread sends the frame on its next tick, see `spawn_frame_pacer`:
+    if let Some(pacer) = &context.pacer {
+        pacer.replace(ppm);
+        // the pacer keeps sending the frame, it counts as sent once it was handed over:
+        if let Some((unchanged_filter, hash)) = unchanged_hash {
+            unchanged_filter.record_sent(hash);
+        }
+        if let (Some(frame_dump), Some(image)) = (&context.frame_dump, converter.last_image()) {
+            frame_dump.dump(frame, image);
+        }
+        return Ok(());
+    }
+
+    let partial_update = match (&context.partial_update, converter.last_image()) {
+        (Some(partial_update), Some(image)) => {
+            Some((partial_update, partial_update.prepare(image)?))
+        }
+        _ => None,
+    };
+
+    // drop frames exceeding the bandwidth budget instead of delaying them, the next frame is more recent anyway.
+    // Partial updates are only charged the bytes of the changed regions:
+    if let Some(rate_limiter) = &context.rate_limiter {
+        let frame_bytes = partial_update
+            .as_ref()
+            .map_or(ppm.len(), |(_, prepared)| prepared.bytes(ppm.len()));
+        let bytes = (frame_bytes * context.sink.destinations()) as u64;
+        if !rate_limiter.lock().unwrap().try_acquire(bytes) {
+            trace!("dropping frame to respect the maximum bytes per second");
+            context.stats.record_rate_limited_frame();
+            return Ok(());
+        }
+    }
+
+    match partial_update {
+        Some((partial_update, prepared)) => {
+            let sent_bytes =
+                partial_update.send(context.sink.as_ref(), prepared, ppm, context.offset)?;
+            context.stats.record_sent_frame(sent_bytes);
+        }
+        None => {
+            send_frame(context.sink.as_ref(), ppm, context.offset)?;
+            context.stats.record_sent_frame(ppm.len());
+        }
+    }
+    if let Some((unchanged_filter, hash)) = unchanged_hash {
+        unchanged_filter.record_sent(hash);
+    }
+
+    if let (Some(frame_dump), Some(image)) = (&context.frame_dump, converter.last_image()) {
+        frame_dump.dump(frame, image);
+    }
+
+    Ok(())
+}
+
+/// Sends the frames of the recording at the given path to the given sink at their recorded pace,
+/// see `--replay`. Frames failing to send are only logged, like the frames of an animation.
+fn replay_recording(sink: &dyn FtSink, path: &Path, offset: FrameOffset) -> Result<()> {
+    info!("replaying {}", path.display());
+    let started = Instant::now();
+    for frame in Recording::open(path)? {
+        let frame = frame?;
+        if let Some(delay) = frame.elapsed.checked_sub(started.elapsed()) {
+            thread::sleep(delay);
+        }
+        if let Err(err) = send_frame(sink, &frame.ppm, offset) {
+            error!("{}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Sends the given PPM to the given sink at the given offset, see `FtSink::send_frame_at`.
+fn send_frame(sink: &dyn FtSink, ppm: &[u8], offset: FrameOffset) -> Result<()> {
+    sink.send_frame_at(ppm, offset.x, offset.y, offset.layer)
+}
+
+/// The interval between the frames sent by `clear_screen` while fading out.
+const FADE_FRAME_INTERVAL: Duration = Duration::from_millis(40);
+
+/// Clears the LED screen by sending an all-black frame of the given dimensions.
+/// If a fade duration is given, the last frame is faded out to black within this duration first.
+fn clear_screen(
+    sink: &dyn FtSink,
+    last_frame: Option<RgbImage>,
+    (width, height): (u32, u32),
+    fade: Option<Duration>,
+    offset: FrameOffset,
 ) -> Result<()> {
-    let ppm = get_ppm_from_jpeg(&frame.params.data)?;
-    flaschentaschen.send_ppm(ppm.as_slice())?;
+    if let (Some(last_frame), Some(fade)) = (last_frame, fade) {
+        let steps = (fade.as_millis() / FADE_FRAME_INTERVAL.as_millis()).max(1) as u32;
+        for step in 1..steps {
+            let factor = 1.0 - step as f32 / steps as f32;
+            let mut frame = last_frame.clone();
+            for pixel in frame.pixels_mut() {
+                for channel in pixel.0.iter_mut() {
+                    *channel = (*channel as f32 * factor).round() as u8;
+                }
+            }
+            let ppm = PpmEncoder::default().encode(&DynamicImage::ImageRgb8(frame))?;
+            send_frame(sink, ppm.as_slice(), offset)?;
+            thread::sleep(FADE_FRAME_INTERVAL);
+        }
+    }
+
+    send_frame(
+        sink,
+        solid_ppm(width, height, Rgb([0, 0, 0])).as_slice(),
+        offset,
+    )
+}
 
+/// Sends each test pattern for the given hold time to the given sink, see `pattern::test_patterns`.
+/// The patterns are converted like frames of the website, so they also show the effect of the orientation.
+fn send_test_patterns(
+    sink: &dyn FtSink,
+    conversion_opts: &ConversionOptions,
+    offset: FrameOffset,
+    hold: Duration,
+) -> Result<()> {
+    let (width, height) = match conversion_opts.target_size {
+        Some(target_size) => conversion_opts.orientation.source_dimensions(target_size),
+        None => {
+            return Err(eyre!(
+                "test patterns require the dimensions of the LED screen"
+            ))
+        }
+    };
+    for (name, pattern) in test_patterns(width, height) {
+        info!("showing test pattern: {}", name);
+        let ppm = get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(pattern), conversion_opts)?;
+        send_frame(sink, ppm.as_slice(), offset)?;
+        thread::sleep(hold);
+    }
     Ok(())
 }
 
+/// Spawns a thread reading URLs from stdin, one per line, and returns the channel receiving them.
+/// Surrounding whitespace is trimmed and blank lines are ignored.
+fn spawn_stdin_reader() -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => {
+                    if sender.send(line.trim().to_string()).is_err() {
+                        return;
+                    }
+                }
+                Err(err) => {
+                    error!("failed to read URL from stdin: {}", err);
+                    return;
+                }
+            }
+        }
+        info!("stdin closed, no further URLs are read");
+    });
+    receiver
+}
+
+/// Returns the dimensions of the LED screen, querying them from the first server of the given group
+/// if they are not both given explicitly.
+fn resolve_screen_size(
+    flaschentaschen: &FlaschenTaschenGroup,
+    width: Option<u32>,
+    height: Option<u32>,
+) -> Result<(u32, u32)> {
+    if let (Some(width), Some(height)) = (width, height) {
+        return Ok((width, height));
+    }
+
+    let (queried_width, queried_height) = flaschentaschen.members[0].query_size().wrap_err(
+        "could not detect the screen size, set it using --screen-width and --screen-height",
+    )?;
+    info!("detected screen size {}x{}", queried_width, queried_height);
+    Ok((
+        width.unwrap_or(queried_width),
+        height.unwrap_or(queried_height),
+    ))
+}
+
+/// Sends the image file at the given path, or read from stdin if the path is `-`, to the given sink.
+/// The image format is detected from the file contents, or from the file extension if they are not recognized,
+/// e.g. PNG, JPEG, WebP or GIF. Animated GIFs are played once, or forever if `repeat` is true,
+/// until the given control is stopped. Returns the last shown frame if the playback was stopped, e.g. to fade it out.
+fn send_image_file(
+    sink: &dyn FtSink,
+    path: &Path,
+    conversion_opts: &ConversionOptions,
+    offset: FrameOffset,
+    repeat: bool,
+    max_fps: Option<u32>,
+    control: &ScreencastControl,
+) -> Result<Option<RgbImage>> {
+    let buffer = if path == Path::new("-") {
+        let mut buffer = Vec::new();
+        io::stdin()
+            .read_to_end(&mut buffer)
+            .wrap_err("failed to read image from stdin")?;
+        buffer
+    } else {
+        fs::read(path).wrap_err_with(|| format!("failed to read image {}", path.display()))?
+    };
+
+    let format = guess_image_format(&buffer, path)
+        .ok_or_else(|| eyre!("unknown image format of {}", path.display()))?;
+    if format == ImageFormat::Gif {
+        let frames = decode_gif(&buffer, conversion_opts)
+            .wrap_err_with(|| format!("failed to decode GIF {}", path.display()))?;
+        info!("playing GIF with {} frames", frames.len());
+        let mut last_shown = None;
+        // a single failed frame should not stop the playback:
+        let completed = play_animation(&frames, repeat, max_fps, control, |ppm| {
+            last_shown = Some(ppm);
+            if let Err(err) = send_frame(sink, ppm, offset) {
+                error!("{}", err);
+            }
+        });
+        return match last_shown.filter(|_| !completed) {
+            Some(ppm) => Ok(Some(
+                image::load_from_memory_with_format(ppm, ImageFormat::Pnm)?.into_rgb8(),
+            )),
+            None => Ok(None),
+        };
+    }
+
+    // the conversion handles the transparency of the image, see `ConversionOptions::transparent_overlay`:
+    let ppm = get_ppm_from_buffer(&buffer, format, conversion_opts)
+        .wrap_err_with(|| format!("failed to decode image {}", path.display()))?;
+    send_frame(sink, ppm.as_slice(), offset)?;
+    Ok(None)
+}
+
+/// Screencasts each region in its own tab of one browser, see `--region`.
+/// A region which fails to load is only logged and its tab closed so the other regions keep running,
+/// starting fails only if none of the regions could be started.
+/// Returns the context and dimensions of each started region, e.g. to clear them on exit.
+fn start_regions(
+    opts: &ScreencastOptions,
+    regions: &[RegionSpec],
+    conversion_opts: &ConversionOptions,
+    frame_context: &FrameContext,
+) -> Result<(Browser, Vec<(Arc<FrameContext>, (u32, u32))>)> {
+    let browser = launch_browser(opts)?;
+    let mut started_regions = Vec::new();
+    for (index, region) in regions.iter().enumerate() {
+        let region_opts = opts.for_region(region);
+        let mut converter = PpmConverter::new(
+            region_opts.format,
+            ConversionOptions {
+                target_size: Some((region.width, region.height)),
+                ..conversion_opts.clone()
+            },
+        );
+        converter.set_keep_last_image(frame_context.converter.lock().unwrap().keeps_last_image());
+        let offset = FrameOffset {
+            x: region_opts.offset_x,
+            y: region_opts.offset_y,
+            layer: region_opts.layer,
+        };
+        let region_context = Arc::new(frame_context.for_region(converter, offset));
+
+        let tab = if index == 0 {
+            browser.wait_for_initial_tab()
+        } else {
+            browser.new_tab()
+        };
+        let tab = match tab {
+            Ok(tab) => tab,
+            Err(err) => {
+                error!(
+                    "failed to open a tab for the region at {},{}: {}",
+                    region.x, region.y, err
+                );
+                continue;
+            }
+        };
+        let started = start_screencasting_tab(
+            tab.clone(),
+            region_opts,
+            on_screencast_frame,
+            region_context.clone(),
+        );
+        match started {
+            Ok(()) => {
+                info!(
+                    "screencasting {} into the region at {},{}",
+                    region.url, region.x, region.y
+                );
+                started_regions.push((region_context, (region.width, region.height)));
+            }
+            Err(err) => {
+                error!(
+                    "failed to screencast {} into the region at {},{}: {:?}",
+                    region.url, region.x, region.y, err
+                );
+                // the page of a failed region would keep running in the background otherwise:
+                if let Err(err) = tab.close(false) {
+                    warn!("failed to close the tab of {}: {}", region.url, err);
+                }
+            }
+        }
+    }
+
+    if started_regions.is_empty() {
+        return Err(eyre!(
+            "none of the {} regions could be screencasted",
+            regions.len()
+        ));
+    }
+    Ok((browser, started_regions))
+}
+
 fn main() -> Result<()> {
     color_eyre::install()?;
-    let args = CliArgs::parse();
-    loggerv::init_with_verbosity(args.verbosity)?;
+    let args = CliArgs::parse().resolve()?;
+    init_logging(args.log_format, args.verbosity)?;
+
+    let (shutdown_sender, shutdown_receiver) = mpsc::channel();
+    let stats = Stats::new();
+    if let Some(interval) = args.stats_interval {
+        spawn_stats_logger(stats.clone(), Duration::from_secs(interval));
+    }
+    if let Some(port) = args.health_port {
+        spawn_health_server(
+            stats.clone(),
+            port,
+            Duration::from_secs(args.health_max_frame_age),
+        )?;
+    }
+
+    // the members are shared with the sending threads once grouped, they are configured before:
+    let mut members = Vec::new();
+    for host_port in args.ft_endpoint {
+        // a dry run does not need a server, it must not fail connecting to one:
+        let mut member = if args.dry_run {
+            FlaschenTaschen::new_dry_run(host_port)?
+        } else {
+            FlaschenTaschen::with_bind(host_port, args.bind_addr.clone())?
+        };
+        member.max_reconnect_backoff = args.reconnect_max_backoff_ms.map(Duration::from_millis);
+        member.output_protocol = args.output_protocol;
+        member.pad_datagram = args.pad_datagram;
+        members.push(member);
+    }
+    let mut flaschentaschen = FlaschenTaschenGroup::from_members(members);
+    flaschentaschen.parallel_sends = args.parallel_sends;
+    if let FrameSource::QuerySize = args.source {
+        // the size is printed to stdout to be usable by scripts, logs are written to stderr:
+        for member in flaschentaschen.members.iter() {
+            let (width, height) = member.query_size()?;
+            println!("{}x{}", width, height);
+        }
+        return Ok(());
+    }
+    // raw frames have no header to carry an offset, and partial updates are sent at offsets:
+    if args.output_protocol == OutputProtocol::RawRgb
+        && (args.partial_update
+            || !args.offset_x.is_zero()
+            || !args.offset_y.is_zero()
+            || args.layer != 0)
+    {
+        return Err(eyre!(
+            "--output-protocol raw-rgb only sends whole frames, it cannot be combined with --partial-update, --offset-x, --offset-y or --layer"
+        ));
+    }
+    // offsets are inserted into binary headers and raw frames are the binary pixel data, both fail for every P3 frame:
+    if args.ppm_ascii
+        && (args.output_protocol == OutputProtocol::RawRgb
+            || args.partial_update
+            || !args.offset_x.is_zero()
+            || !args.offset_y.is_zero()
+            || args.layer != 0
+            || matches!(args.source, FrameSource::Regions(_)))
+    {
+        return Err(eyre!(
+            "--ppm-ascii frames can only be sent whole and at the origin, it cannot be combined with --output-protocol raw-rgb, --partial-update, --offset-x, --offset-y, --layer or --region"
+        ));
+    }
+    if args.dry_run {
+        info!("dry run, frames are converted but not sent");
+        if args.screen_width.is_none() || args.screen_height.is_none() {
+            return Err(eyre!(
+                "--dry-run requires --screen-width and --screen-height, the screen size cannot be queried without sending"
+            ));
+        }
+    }
+    // launching chrome is expensive, fail early if a server is obviously unreachable:
+    if args.verify_server && !args.dry_run {
+        for member in flaschentaschen.members.iter() {
+            member
+                .probe()
+                .wrap_err_with(|| format!("{} is not reachable, check --ft-endpoint", member))?;
+        }
+    }
 
-    let screencast_opts = ScreencastOptions {
-        url: args.url,
-        width: args.screen_width,
-        height: args.screen_height,
+    let (screen_width, screen_height) =
+        resolve_screen_size(&flaschentaschen, args.screen_width, args.screen_height)?;
+    let flaschentaschen = Arc::new(flaschentaschen);
+    // every frame is sent through this sink, which also records it if --record is enabled:
+    let sink: Arc<dyn FtSink> = match args.record.as_deref() {
+        Some(path) => {
+            info!("recording the sent frames to {}", path.display());
+            Arc::new(RecordingSink {
+                sink: flaschentaschen.clone(),
+                recorder: FileSink::create(path)?,
+            })
+        }
+        None => flaschentaschen.clone(),
+    };
+    let (offset_x, offset_y) = (
+        args.offset_x.resolve(screen_width),
+        args.offset_y.resolve(screen_height),
+    );
+    // every frame must fit into a single datagram, the size of the sent frames only depends on the screen size:
+    if !matches!(args.source, FrameSource::Regions(_))
+        && flaschentaschen
+            .members
+            .iter()
+            .any(|member| member.transport().is_datagram())
+    {
+        let frame_size = match args.output_protocol {
+            OutputProtocol::RawRgb => screen_width as usize * screen_height as usize * 3,
+            OutputProtocol::FlaschenTaschenPpm
+                if offset_x == 0 && offset_y == 0 && args.layer == 0 =>
+            {
+                binary_ppm_size(screen_width, screen_height)
+            }
+            OutputProtocol::FlaschenTaschenPpm => {
+                let offset_line = format!("# FT: {} {} {}\n", offset_x, offset_y, args.layer);
+                binary_ppm_size(screen_width, screen_height) + offset_line.len()
+            }
+        };
+        if frame_size > MAX_DATAGRAM_SIZE {
+            return Err(eyre!(
+                "frames of {}x{} pixels take {} bytes, more than the {} bytes of a single datagram, reduce the screen size or use --transport tcp",
+                screen_width,
+                screen_height,
+                frame_size,
+                MAX_DATAGRAM_SIZE
+            ));
+        }
+    }
+
+    if args.ppm_ascii {
+        warn!("--ppm-ascii is enabled, frames are sent as ASCII PPM which is only meant for debugging");
+    }
+    // shared by the screencast tracking the element and the conversion cropping frames to it:
+    let capture_element = args.capture_selector.map(TrackedElement::new);
+    let conversion_opts = ConversionOptions {
+        target_size: Some((screen_width, screen_height)),
+        resize_filter: args.resize_filter.unwrap_or(ResizeFilter::Triangle).into(),
+        nearest_for_integer_multiples: args.resize_filter.is_none(),
+        scaling_mode: args.scaling_mode,
+        pad_color: args.pad_color,
+        crop: args.crop,
+        capture_element: capture_element.clone(),
+        color_correction: match (args.gamma, args.brightness, args.contrast, args.saturation) {
+            (None, None, None, None) => None,
+            (gamma, brightness, contrast, saturation) => Some(ColorCorrection {
+                gamma: gamma.unwrap_or(1.0),
+                brightness: brightness.unwrap_or(1.0),
+                contrast: contrast.unwrap_or(1.0),
+                saturation: saturation.unwrap_or(1.0),
+            }),
+        },
+        dither: args.dither,
+        dither_levels: args.dither_levels,
+        orientation: args.orientation,
+        validate_output: args.validate_frames,
+        ascii_output: args.ppm_ascii,
+        transparent_overlay: args.transparent,
+        channel_order: args.channel_order,
+        serpentine: if args.serpentine {
+            Some((
+                args.module_width.unwrap_or(screen_width),
+                args.module_height.unwrap_or(screen_height),
+            ))
+        } else {
+            None
+        },
+        strict_dimensions: args.strict_dimensions,
     };
 
-    // leak is fine here: this context instance is created once and passed as reference to `on_screencast_frame`.
-    // As soon as main exits, this memory reference is not needed anymore because the thread handling the browser tab event is haltet too.
-    let flaschentaschen: &'static mut FlaschenTaschen =
-        Box::leak(Box::new(FlaschenTaschen::new(args.ft_endpoint)?));
+    if args.test_pattern {
+        send_test_patterns(
+            sink.as_ref(),
+            &conversion_opts,
+            FrameOffset {
+                x: offset_x,
+                y: offset_y,
+                layer: args.layer,
+            },
+            Duration::from_millis(args.test_pattern_hold),
+        )?;
+    }
+
+    let (url, playlist, regions) = match args.source {
+        FrameSource::TestPattern | FrameSource::QuerySize => return Ok(()),
+        FrameSource::Replay(path) => {
+            return replay_recording(
+                sink.as_ref(),
+                &path,
+                FrameOffset {
+                    x: offset_x,
+                    y: offset_y,
+                    layer: args.layer,
+                },
+            );
+        }
+        FrameSource::Url(url) => (url, Vec::new(), Vec::new()),
+        FrameSource::Playlist(playlist) => (playlist[0].url.clone(), playlist, Vec::new()),
+        FrameSource::Regions(regions) => (regions[0].url.clone(), Vec::new(), regions),
+        FrameSource::Image(path) => {
+            // a looping GIF only ends by a signal, which must clear the screen like it does for a screencast:
+            let playback = Arc::new(ScreencastControl::new());
+            let signal_playback = playback.clone();
+            thread::spawn(move || {
+                match wait_for_shutdown(shutdown_sender, shutdown_receiver, || ()) {
+                    Ok(_) => signal_playback.stop(),
+                    Err(err) => error!("failed to wait for signals: {}", err),
+                }
+            });
+            let offset = FrameOffset {
+                x: offset_x,
+                y: offset_y,
+                layer: args.layer,
+            };
+            let stopped_frame = send_image_file(
+                sink.as_ref(),
+                &path,
+                &conversion_opts,
+                offset,
+                args.loop_animation,
+                args.max_fps,
+                &playback,
+            )?;
+            if let Some(last_frame) = stopped_frame {
+                if args.clear_on_exit || args.fade_on_exit.is_some() {
+                    let fade = args.fade_on_exit.map(Duration::from_millis);
+                    let cleared = clear_screen(
+                        sink.as_ref(),
+                        Some(last_frame),
+                        (screen_width, screen_height),
+                        fade,
+                        offset,
+                    );
+                    if let Err(err) = cleared {
+                        error!("failed to clear the screen: {}", err);
+                    }
+                }
+            }
+            return Ok(());
+        }
+    };
+    if !regions.is_empty() {
+        // each region is rendered at its own dimensions, which neither a viewport, a rotation nor a serpentine remap can apply to:
+        if args.once
+            || args.control_stdin
+            || args.dump_frames.is_some()
+            || args.orientation != Orientation::None
+            || args.serpentine
+            || args.fixed_fps.is_some()
+            || capture_element.is_some()
+            || args.viewport_width.is_some()
+            || args.viewport_height.is_some()
+        {
+            return Err(eyre!(
+                "--region cannot be combined with --once, --control-stdin, --dump-frames, --rotate, --flip, --serpentine, --fixed-fps, --capture-selector, --viewport-width or --viewport-height"
+            ));
+        }
+        for region in regions.iter() {
+            if !region.fits_into((screen_width, screen_height)) {
+                return Err(eyre!(
+                    "region {},{},{},{} of {} exceeds the screen size of {}x{}",
+                    region.x,
+                    region.y,
+                    region.width,
+                    region.height,
+                    region.url,
+                    screen_width,
+                    screen_height
+                ));
+            }
+        }
+    }
 
-    let browser = start_screencasting(screencast_opts, on_screencast_frame, flaschentaschen)?;
-    info!(
-        "started chrome instance with process id {}",
-        browser.get_process_id().unwrap()
+    // chrome renders frames at their dimensions before applying the orientation,
+    // the conversion downscales frames of a larger viewport to the screen size:
+    let (default_width, default_height) = args
+        .orientation
+        .source_dimensions((screen_width, screen_height));
+    let (render_width, render_height) = (
+        args.viewport_width.unwrap_or(default_width),
+        args.viewport_height.unwrap_or(default_height),
     );
+    if (render_width, render_height) != (default_width, default_height) {
+        info!(
+            "rendering the page at a viewport of {}x{}",
+            render_width, render_height
+        );
+    }
+    let screencast_opts = ScreencastOptions::builder(url, render_width, render_height)
+        .format(args.image_format)
+        .max_fps(args.max_fps)
+        .user_agent(args.user_agent)
+        .extra_chrome_args(args.chrome_args)
+        .browser_source(match args.chrome_ws_url {
+            Some(ws_url) => BrowserSource::Connect(ws_url),
+            None => BrowserSource::Launch,
+        })
+        .jpeg_quality(args.jpeg_quality)
+        .wait_for_selector(args.wait_for_selector)
+        .wait_for_navigation_idle(args.wait_for_navigation)
+        .inject_css(
+            args.inject_css_file
+                .map(fs::read_to_string)
+                .transpose()
+                .wrap_err("failed to read CSS file")?,
+        )
+        .inject_js(
+            args.inject_js_file
+                .map(fs::read_to_string)
+                .transpose()
+                .wrap_err("failed to read JS file")?,
+        )
+        .stats(stats.clone())
+        .error_threshold(args.error_threshold)
+        .error_window(Duration::from_secs(args.error_window_secs))
+        .shutdown_sender(shutdown_sender.clone())
+        .frame_queue_capacity(args.frame_queue_capacity)
+        .page_load_timeout_secs(args.load_timeout_secs)
+        .extra_headers(args.headers)
+        .basic_auth(args.basic_auth)
+        .playlist(playlist)
+        .navigation_receiver(if args.control_stdin {
+            Some(spawn_stdin_reader())
+        } else {
+            None
+        })
+        .cookies(match args.cookies_file {
+            Some(path) => {
+                let mut cookies = load_cookies_file(&path)?;
+                cookies.extend(args.cookies);
+                cookies
+            }
+            None => args.cookies,
+        })
+        .device_scale_factor(args.device_scale_factor)
+        .reload_interval(args.reload_interval.map(Duration::from_secs))
+        .offset(offset_x, offset_y, args.layer)
+        .no_sandbox(args.no_sandbox)
+        .proxy(args.proxy)
+        .proxy_bypass_list(args.proxy_bypass_list)
+        .transparent_background(args.transparent)
+        .log_page_console(args.log_page_console)
+        .frame_timeout(args.frame_timeout.map(Duration::from_secs))
+        .adaptive_quality(args.adaptive_quality)
+        .pause_on_selector(args.pause_on_selector)
+        .capture_every_nth_frame(args.capture_every_nth_frame)
+        .ready_expression(args.ready_expression)
+        .capture_element(capture_element)
+        .build()?;
 
-    // wait for a SIGINT signal
-    let mut signals = Signals::new(&[SIGINT])?;
-    let signal_thread = thread::spawn(move || {
-        for sig in signals.forever() {
-            info!("Received signal {}, exiting...", sig);
-            return;
+    let offset = FrameOffset {
+        x: screencast_opts.offset_x,
+        y: screencast_opts.offset_y,
+        layer: screencast_opts.layer,
+    };
+    if args.once {
+        let ppm = capture_single_frame(&screencast_opts, &conversion_opts)?;
+        if args.stdout_ppm {
+            let mut stdout = io::stdout();
+            stdout
+                .write_all(&ppm)
+                .and_then(|_| stdout.flush())
+                .wrap_err("failed to write PPM to stdout")?;
+            return Ok(());
+        }
+        return send_frame(sink.as_ref(), ppm.as_slice(), offset);
+    }
+
+    let frame_dump = match args.dump_frames {
+        Some(directory) => {
+            fs::create_dir_all(&directory).wrap_err_with(|| {
+                format!(
+                    "failed to create frame dump directory {}",
+                    directory.display()
+                )
+            })?;
+            Some(FrameDump {
+                directory,
+                every: args.dump_every,
+                frame_count: AtomicU64::new(0),
+            })
         }
+        None => None,
+    };
+
+    // closures capture `args` as a whole in edition 2018, which is partially moved by now:
+    let keyframe_interval = args.keyframe_interval;
+    let partial_update_block_size = args.partial_update_block_size;
+    let partial_update_validate = conversion_opts.validate_output;
+    // shared with the worker thread of each screencast, which drops its reference once screencasting stopped:
+    let frame_context = Arc::new(FrameContext {
+        sink: sink.clone(),
+        converter: Mutex::new({
+            let mut converter = PpmConverter::new(args.image_format, conversion_opts.clone());
+            // frame dumps, partial updates and fading out on exit need the last frame as image:
+            converter.set_keep_last_image(
+                frame_dump.is_some() || args.partial_update || args.fade_on_exit.is_some(),
+            );
+            converter
+        }),
+        stats,
+        frame_dump,
+        offset,
+        rate_limiter: args
+            .max_bytes_per_sec
+            .map(|bytes_per_sec| Arc::new(Mutex::new(RateLimiter::new(bytes_per_sec)))),
+        unchanged_filter: args.skip_unchanged.then(|| UnchangedFilter {
+            keyframe_interval: Some(keyframe_interval)
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            state: Mutex::new(UnchangedFilterState {
+                previous_hash: None,
+                last_sent: Instant::now(),
+            }),
+        }),
+        partial_update: args.partial_update.then(|| PartialUpdate {
+            block_size: partial_update_block_size,
+            encoder: PpmEncoder { ascii: false },
+            validate_output: partial_update_validate,
+            state: Mutex::new(PartialUpdateState {
+                previous: None,
+                frames_since_full: 0,
+            }),
+        }),
+        pacer: args.fixed_fps.map(|fps| FramePacer {
+            interval: Duration::from_secs(1) / fps,
+            latest: Mutex::new(None),
+            control: ScreencastControl::new(),
+        }),
     });
+    spawn_frame_pacer(frame_context.clone());
+
+    let liveness_control = Arc::new(ScreencastControl::new());
+    if let (Some(interval), false) = (args.liveness_interval, args.dry_run) {
+        spawn_liveness_probe(
+            flaschentaschen.clone(),
+            Duration::from_secs(interval),
+            args.liveness_failures,
+            shutdown_sender.clone(),
+            liveness_control.clone(),
+        );
+    }
+
+    // each screencast is cleared on exit through its own context, which converted its last frame:
+    let (browser, screencasts) = if regions.is_empty() {
+        let browser =
+            start_screencasting(screencast_opts, on_screencast_frame, frame_context.clone())?;
+        (
+            browser,
+            vec![(frame_context.clone(), (screen_width, screen_height))],
+        )
+    } else {
+        start_regions(&screencast_opts, &regions, &conversion_opts, &frame_context)?
+    };
+    // whichever comes first, the maximum runtime or a signal, shuts down the same way:
+    if let Some(max_runtime) = args.max_runtime {
+        let max_runtime_sender = shutdown_sender.clone();
+        thread::spawn(move || {
+            thread::sleep(max_runtime);
+            let _ = max_runtime_sender.send(ShutdownReason::MaxRuntimeElapsed);
+        });
+    }
+    if let Some(process_id) = browser.get_process_id() {
+        info!("started chrome instance with process id {}", process_id);
+    }
 
     // Important: We need to make sure to keep this process busy.
     // If `browser` leaves its scope, the browser instance will be stopped and screencasting halts.
-    // We do this by waiting for a SIGINT signal in a separate thread and join it:
-    signal_thread
-        .join()
-        .expect("failed to wait for signal thread");
+    // We do this by blocking until we are asked to shut down:
+    let shutdown_reason =
+        wait_for_shutdown(shutdown_sender, shutdown_receiver, reload_pages(&browser))?;
+    if shutdown_reason == ShutdownReason::BrowserCrashed {
+        // there is no screencast left to stop, fail so a supervisor can restart us:
+        return Err(eyre!("{}", shutdown_reason));
+    }
+
+    // stop the screencast before dropping the browser to not leave an orphaned chrome process behind:
+    stop_screencasting(&browser)?;
+    drop(browser);
+    // the pacer would otherwise keep sending the last frame over the cleared screen,
+    // joining it makes sure its last send completed before clearing:
+    if let Some(pacer) = &frame_context.pacer {
+        pacer.control.stop();
+    }
+    liveness_control.stop();
+
+    // a wall frozen on the last frame looks broken, a failed clear is therefore not worth a failed exit:
+    if args.clear_on_exit || args.fade_on_exit.is_some() {
+        let fade = args.fade_on_exit.map(Duration::from_millis);
+        // regions fade out at the same time, not one after another:
+        thread::scope(|scope| {
+            for (context, dimensions) in screencasts.iter() {
+                scope.spawn(move || {
+                    let last_frame = context
+                        .converter
+                        .lock()
+                        .unwrap()
+                        .last_image()
+                        .map(DynamicImage::to_rgb8);
+                    let cleared = clear_screen(
+                        context.sink.as_ref(),
+                        last_frame,
+                        *dimensions,
+                        fade,
+                        context.offset,
+                    );
+                    if let Err(err) = cleared {
+                        error!("failed to clear the screen: {}", err);
+                    }
+                });
+            }
+        });
+    }
+
+    // exit with a distinguishable code so a supervisor knows it should restart us:
+    if shutdown_reason == ShutdownReason::CircuitBreakerTripped
+        || shutdown_reason == ShutdownReason::FramesStalled
+        || shutdown_reason == ShutdownReason::ServerUnresponsive
+    {
+        process::exit(EXIT_CODE_TEMPFAIL);
+    }
 
     Ok(())
 }
@@ -0,0 +1,49 @@
+use image::{Rgb, RgbImage};
+
+/// Returns an image of the given dimensions filled with the given color.
+pub fn solid(width: u32, height: u32, color: Rgb<u8>) -> RgbImage {
+    RgbImage::from_pixel(width, height, color)
+}
+
+/// Returns a gray gradient from black in the top left corner to white in the bottom right corner.
+pub fn diagonal_gradient(width: u32, height: u32) -> RgbImage {
+    let max_distance = (width + height).saturating_sub(2).max(1);
+    RgbImage::from_fn(width, height, |x, y| {
+        let value = ((x + y) * 255 / max_distance) as u8;
+        Rgb([value, value, value])
+    })
+}
+
+/// Returns a black image with a square marker of a distinct color in each corner:
+/// red in the top left, green in the top right, blue in the bottom left and white in the bottom right.
+/// This shows whether the screen is rotated or flipped.
+pub fn corner_markers(width: u32, height: u32) -> RgbImage {
+    let size = (width.min(height) / 4).max(1);
+    RgbImage::from_fn(width, height, |x, y| {
+        match (
+            x < size,
+            x >= width.saturating_sub(size),
+            y < size,
+            y >= height.saturating_sub(size),
+        ) {
+            (true, _, true, _) => Rgb([255, 0, 0]),
+            (_, true, true, _) => Rgb([0, 255, 0]),
+            (true, _, _, true) => Rgb([0, 0, 255]),
+            (_, true, _, true) => Rgb([255, 255, 255]),
+            _ => Rgb([0, 0, 0]),
+        }
+    })
+}
+
+/// Returns the test patterns verifying the wiring of an LED screen of the given dimensions, in the order to show them:
+/// solid red, green, blue and white, a diagonal gradient and the corner markers.
+pub fn test_patterns(width: u32, height: u32) -> Vec<(&'static str, RgbImage)> {
+    vec![
+        ("red", solid(width, height, Rgb([255, 0, 0]))),
+        ("green", solid(width, height, Rgb([0, 255, 0]))),
+        ("blue", solid(width, height, Rgb([0, 0, 255]))),
+        ("white", solid(width, height, Rgb([255, 255, 255]))),
+        ("diagonal gradient", diagonal_gradient(width, height)),
+        ("corner markers", corner_markers(width, height)),
+    ]
+}
@@ -0,0 +1,89 @@
+use eyre::{eyre, Result};
+use std::time::Duration;
+
+/// A URL shown for the given duration before switching to the next entry of a playlist
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaylistEntry {
+    pub url: String,
+    pub duration: Duration,
+}
+
+/// Parses a comma separated playlist of the form `url1=30s,url2=2m`, see `parse_duration`.
+/// URLs may contain `=` themselves, the duration follows the last one.
+pub fn parse_playlist(playlist: &str) -> Result<Vec<PlaylistEntry>> {
+    let entries = playlist
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.rsplit_once('=') {
+            Some((url, duration)) if !url.is_empty() => Ok(PlaylistEntry {
+                url: url.to_string(),
+                duration: parse_duration(duration)?,
+            }),
+            _ => Err(eyre!(
+                "invalid playlist entry `{}`, expected the form `url=duration`",
+                entry
+            )),
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    if entries.is_empty() {
+        return Err(eyre!("playlist is empty"));
+    }
+    Ok(entries)
+}
+
+/// Parses a non-zero duration with a unit suffix, e.g. `500ms`, `30s`, `2m` or `1h`,
+/// or the sum of several of them like `1h30m` or `1h 30m`. A number without suffix on its own is seconds.
+pub fn parse_duration(duration: &str) -> Result<Duration> {
+    let duration = duration.trim();
+    let invalid = || {
+        eyre!(
+            "invalid duration `{}`, expected e.g. 30s or 1h 30m",
+            duration
+        )
+    };
+    if duration.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut parsed = Duration::from_secs(0);
+    let mut rest = duration;
+    while !rest.is_empty() {
+        let amount_end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit_end = rest[amount_end..]
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .map_or(rest.len(), |unit_len| amount_end + unit_len);
+        let amount: u64 = rest[..amount_end].parse().map_err(|_| invalid())?;
+
+        let part = match &rest[amount_end..unit_end] {
+            "ms" => Some(Duration::from_millis(amount)),
+            "s" => Some(Duration::from_secs(amount)),
+            "" if rest.len() == duration.len() && unit_end == rest.len() => {
+                Some(Duration::from_secs(amount))
+            }
+            "" => return Err(invalid()),
+            "m" => amount.checked_mul(60).map(Duration::from_secs),
+            "h" => amount.checked_mul(60 * 60).map(Duration::from_secs),
+            unit => {
+                return Err(eyre!(
+                    "invalid duration unit `{}`, expected ms, s, m or h",
+                    unit
+                ))
+            }
+        };
+        parsed = part
+            .and_then(|part| parsed.checked_add(part))
+            .ok_or_else(|| eyre!("duration `{}` is too large", duration))?;
+        rest = rest[unit_end..].trim_start();
+    }
+    if parsed.is_zero() {
+        return Err(eyre!(
+            "invalid duration `{}`, expected more than 0",
+            duration
+        ));
+    }
+    Ok(parsed)
+}
@@ -0,0 +1,153 @@
+use eyre::{eyre, Result};
+use image::Rgb;
+
+/// Parses the header of a binary PPM (magic P6) and returns its width, height and maximum color value.
+/// Comments (`#` up to the end of the line) are allowed between the header fields.
+pub fn parse_ppm_header(bytes: &[u8]) -> Result<(u32, u32, u16)> {
+    parse_header(bytes).map(|(header, _)| header)
+}
+
+/// Parses the header of a binary PPM like `parse_ppm_header` and additionally returns its length in bytes,
+/// including the whitespace terminating it.
+fn parse_header(bytes: &[u8]) -> Result<((u32, u32, u16), usize)> {
+    if !bytes.starts_with(b"P6") {
+        return Err(eyre!("expected a P6 PPM, got an unknown magic number"));
+    }
+
+    let mut fields = HeaderFields { bytes, position: 2 };
+    let width = fields.next_number("width")?;
+    let height = fields.next_number("height")?;
+    let max_value = fields.next_number("maximum color value")?;
+
+    // the header ends with a single whitespace character after the maximum color value:
+    if !bytes
+        .get(fields.position)
+        .map_or(false, u8::is_ascii_whitespace)
+    {
+        return Err(eyre!("PPM header is not terminated by a whitespace"));
+    }
+    if width == 0 || height == 0 {
+        return Err(eyre!("PPM has invalid dimensions {}x{}", width, height));
+    }
+    if max_value == 0 || max_value > u16::MAX as u32 {
+        return Err(eyre!(
+            "PPM has an invalid maximum color value {}, expected 1-65535",
+            max_value
+        ));
+    }
+
+    Ok(((width, height, max_value as u16), fields.position + 1))
+}
+
+/// Returns the width, height and pixel data following the header of the given binary PPM,
+/// which must use one byte per sample (a maximum color value of up to 255).
+pub fn ppm_pixel_data(bytes: &[u8]) -> Result<(u32, u32, &[u8])> {
+    let ((width, height, max_value), header_len) = parse_header(bytes)?;
+    if max_value > 255 {
+        return Err(eyre!(
+            "PPM has a maximum color value of {}, expected one byte per sample",
+            max_value
+        ));
+    }
+    Ok((width, height, &bytes[header_len..]))
+}
+
+/// Returns an error if the given bytes are not a complete binary PPM of the expected dimensions.
+/// Use this to detect frames chrome rendered at unexpected dimensions.
+pub fn validate_ppm(bytes: &[u8], expected_width: u32, expected_height: u32) -> Result<()> {
+    let ((width, height, max_value), header_len) = parse_header(bytes)?;
+    if (width, height) != (expected_width, expected_height) {
+        return Err(eyre!(
+            "PPM has dimensions {}x{}, expected {}x{}",
+            width,
+            height,
+            expected_width,
+            expected_height
+        ));
+    }
+
+    // samples take two bytes if the maximum color value exceeds 255:
+    let bytes_per_sample = if max_value > 255 { 2 } else { 1 };
+    let expected_len = width as usize * height as usize * 3 * bytes_per_sample;
+    let data_len = bytes.len() - header_len;
+    if data_len != expected_len {
+        return Err(eyre!(
+            "PPM has {} bytes of pixel data, expected {}",
+            data_len,
+            expected_len
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns the header of a binary PPM of the given dimensions with one byte per sample.
+fn binary_ppm_header(width: u32, height: u32) -> String {
+    format!("P6\n{} {}\n255\n", width, height)
+}
+
+/// Returns the number of bytes of a binary PPM of the given dimensions as written by `solid_ppm`,
+/// e.g. to check at startup whether frames of this size fit into a single datagram.
+pub fn binary_ppm_size(width: u32, height: u32) -> usize {
+    binary_ppm_header(width, height).len() + width as usize * height as usize * 3
+}
+
+/// Returns a binary PPM of the given dimensions filled with the given color, e.g. to clear the LED screen.
+pub fn solid_ppm(width: u32, height: u32, color: Rgb<u8>) -> Vec<u8> {
+    let header = binary_ppm_header(width, height);
+    let pixel_count = width as usize * height as usize;
+    let mut ppm = Vec::with_capacity(header.len() + pixel_count * 3);
+    ppm.extend_from_slice(header.as_bytes());
+    for _ in 0..pixel_count {
+        ppm.extend_from_slice(&color.0);
+    }
+    ppm
+}
+
+/// Reads the whitespace separated numeric fields of a PPM header, skipping comments.
+struct HeaderFields<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+impl HeaderFields<'_> {
+    /// Returns the next field of the header, failing with a message containing the given field name
+    /// if it is missing or not a number.
+    fn next_number(&mut self, name: &str) -> Result<u32> {
+        self.skip_whitespace_and_comments();
+        let start = self.position;
+        while self
+            .bytes
+            .get(self.position)
+            .map_or(false, u8::is_ascii_digit)
+        {
+            self.position += 1;
+        }
+
+        if start == self.position {
+            return Err(eyre!("PPM header is missing its {}", name));
+        }
+        // the field consists of ASCII digits only:
+        std::str::from_utf8(&self.bytes[start..self.position])
+            .unwrap()
+            .parse()
+            .map_err(|_| eyre!("PPM header has an invalid {}", name))
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(byte) = self.bytes.get(self.position) {
+            match byte {
+                b'#' => {
+                    while self
+                        .bytes
+                        .get(self.position)
+                        .map_or(false, |byte| *byte != b'\n')
+                    {
+                        self.position += 1;
+                    }
+                }
+                byte if byte.is_ascii_whitespace() => self.position += 1,
+                _ => break,
+            }
+        }
+    }
+}
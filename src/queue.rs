@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+
+/// A bounded, blocking FIFO queue handing frames from the browser event thread to a worker thread.
+/// If the queue is full, pushing a new item drops the oldest one (latest wins),
+/// so a slow consumer never stalls the producer.
+pub struct FrameQueue<T> {
+    capacity: usize,
+    state: Mutex<QueueState<T>>,
+    available: Condvar,
+}
+
+struct QueueState<T> {
+    items: VecDeque<T>,
+    closed: bool,
+}
+
+impl<T> FrameQueue<T> {
+    /// Returns a new, empty queue holding at most `capacity` items (at least one).
+    pub fn new(capacity: usize) -> FrameQueue<T> {
+        let capacity = capacity.max(1);
+        FrameQueue {
+            capacity,
+            state: Mutex::new(QueueState {
+                items: VecDeque::with_capacity(capacity),
+                closed: false,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Appends the given item to the queue and returns the oldest item if it had to be dropped to make room.
+    pub fn push(&self, item: T) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        let dropped = if state.items.len() >= self.capacity {
+            state.items.pop_front()
+        } else {
+            None
+        };
+        state.items.push_back(item);
+        self.available.notify_one();
+
+        dropped
+    }
+
+    /// Removes and returns the oldest item of the queue, blocking until one is available.
+    /// Returns `None` as soon as the queue is closed.
+    pub fn pop(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if state.closed {
+                return None;
+            }
+            if let Some(item) = state.items.pop_front() {
+                return Some(item);
+            }
+            state = self.available.wait(state).unwrap();
+        }
+    }
+
+    /// Returns the number of items currently waiting in the queue.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().items.len()
+    }
+
+    /// Returns true if no items are waiting in the queue.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Discards all waiting items and returns their number, e.g. to not show frames of a previous page.
+    pub fn clear(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let discarded = state.items.len();
+        state.items.clear();
+        discarded
+    }
+
+    /// Closes the queue, discarding all waiting items and waking up all blocked consumers.
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.items.clear();
+        self.available.notify_all();
+    }
+}
@@ -0,0 +1,316 @@
+use crate::{
+    insert_ft_offset, FlaschenTaschen, FlaschenTaschenGroup, FtResult, FtTransport, TcpSink,
+    MAX_DATAGRAM_SIZE,
+};
+use eyre::{eyre, Report, Result, WrapErr};
+use log::{error, warn};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::vec;
+
+/// The first word of the header line preceding each frame of a recording file.
+const FRAME_HEADER_MAGIC: &str = "FTFRAME";
+
+/// Receives the converted frames of a screencast, e.g. the flaschentaschen servers or a recording.
+pub trait FtSink: Send + Sync {
+    /// Handles the given frame, a complete PPM, positioned at the given x/y offset on the given layer,
+    /// see `FlaschenTaschen::send_ppm_at`.
+    fn send_frame_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> Result<()>;
+
+    /// Like `send_frame_at`, but the frame is sent unchanged, shown at the top left corner of the LED screen.
+    fn send_frame(&self, ppm: &[u8]) -> Result<()> {
+        self.send_frame_at(ppm, 0, 0, 0)
+    }
+
+    /// The number of servers receiving each frame, e.g. to charge a bandwidth budget for all of them.
+    fn destinations(&self) -> usize {
+        1
+    }
+}
+
+impl<S: FtSink + ?Sized> FtSink for Arc<S> {
+    fn send_frame_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> Result<()> {
+        (**self).send_frame_at(ppm, x, y, layer)
+    }
+
+    fn destinations(&self) -> usize {
+        (**self).destinations()
+    }
+}
+
+/// Sends a frame using the given send function, or the given offset send function for any offset but the origin.
+/// Frames without offset are sent unchanged, not every server understands the offset comment.
+fn send_with_offset<T>(
+    (x, y, layer): (u32, u32, u8),
+    send: impl FnOnce() -> T,
+    send_at: impl FnOnce(u32, u32, u8) -> T,
+) -> T {
+    if (x, y, layer) == (0, 0, 0) {
+        send()
+    } else {
+        send_at(x, y, layer)
+    }
+}
+
+/// Sends each frame as datagram to a single flaschentaschen server, what each member of a group does
+/// for a `udp` endpoint. Like the group, it detects a server which is not listening, see
+/// `FlaschenTaschen::send_ppm_checked`.
+pub struct UdpSink {
+    flaschentaschen: FlaschenTaschen,
+}
+impl UdpSink {
+    /// Returns a sink sending to the given host/port, failing for endpoints of other transports,
+    /// see `FtTransport::of`.
+    pub fn new(host_port: &str) -> Result<UdpSink> {
+        let flaschentaschen = FlaschenTaschen::new(host_port.to_string())?;
+        if flaschentaschen.transport() != FtTransport::Udp {
+            return Err(eyre!("{} is not a UDP endpoint", host_port));
+        }
+        Ok(UdpSink { flaschentaschen })
+    }
+}
+impl FtSink for UdpSink {
+    fn send_frame_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> Result<()> {
+        let flaschentaschen = &self.flaschentaschen;
+        send_with_offset(
+            (x, y, layer),
+            || flaschentaschen.send_ppm_checked(ppm),
+            |x, y, layer| flaschentaschen.send_ppm_at_checked(ppm, x, y, layer),
+        )
+        .map(|_| ())
+        .wrap_err_with(|| format!("failed to send frame to {}", flaschentaschen))
+    }
+}
+
+/// Sends each frame to all servers of the group, the default sink. Failed sends are logged, the frame
+/// only fails if it could not be sent to any of the servers, a single failing server does not stop the screencast.
+impl FtSink for FlaschenTaschenGroup {
+    fn send_frame_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> Result<()> {
+        let send_results: Vec<FtResult<usize>> = send_with_offset(
+            (x, y, layer),
+            || self.send_ppm_checked(ppm),
+            |x, y, layer| self.send_ppm_at_checked(ppm, x, y, layer),
+        );
+        let mut failed_sends = 0;
+        let mut last_error = None;
+        for err in send_results.into_iter().filter_map(|result| result.err()) {
+            error!("{}", err);
+            failed_sends += 1;
+            last_error = Some(err);
+        }
+
+        // the cause is kept, so the frame handler can tell send errors from decode errors:
+        match last_error.filter(|_| failed_sends == self.members.len()) {
+            Some(err) => {
+                Err(Report::new(err).wrap_err(format!("failed to send frame to {}", self)))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn destinations(&self) -> usize {
+        self.members.len()
+    }
+}
+
+/// Writes each frame to the TCP connection, failing if it could not be written completely.
+/// Offsets are inserted as comment into the frame, see `insert_ft_offset`.
+impl FtSink for TcpSink {
+    fn send_frame_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> Result<()> {
+        let ppm = send_with_offset(
+            (x, y, layer),
+            || Ok(Cow::Borrowed(ppm)),
+            |x, y, layer| insert_ft_offset(ppm, x, y, layer).map(Cow::Owned),
+        )?;
+        self.send(&ppm)
+            .map(|_| ())
+            .wrap_err("failed to send frame over TCP")
+    }
+}
+
+/// Sends each frame to the given sink and records it with the given file sink, see `--record`.
+/// Frames are recorded even if sending them failed, failing to record a frame is only logged,
+/// it should not interrupt the screencast.
+pub struct RecordingSink<S> {
+    pub sink: S,
+    pub recorder: FileSink,
+}
+impl<S: FtSink> FtSink for RecordingSink<S> {
+    fn send_frame_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> Result<()> {
+        let result = self.sink.send_frame_at(ppm, x, y, layer);
+        if let Err(err) = self.recorder.send_frame_at(ppm, x, y, layer) {
+            warn!("{:#}", err);
+        }
+        result
+    }
+
+    fn destinations(&self) -> usize {
+        self.sink.destinations()
+    }
+}
+
+/// Records frames with the time they were received at, so `Recording` can replay them at their original pace.
+/// Frames are recorded as they would be sent, with the offset comment inserted for offsets other than the origin,
+/// and must fit into a single datagram, see `MAX_DATAGRAM_SIZE`.
+pub struct FileSink {
+    target: FileSinkTarget,
+    started: Instant,
+    /// The number of frames recorded so far, orders frames recorded within the same millisecond
+    recorded_frames: AtomicU64,
+}
+enum FileSinkTarget {
+    /// All frames in a single file, each preceded by the header line `FTFRAME <millis> <length>`
+    File(Mutex<File>),
+    /// One file per frame, named `frame_<millis>_<sequence>.ppm`
+    Directory(PathBuf),
+}
+impl FileSink {
+    /// Returns a sink recording into the given path. If it is an existing directory, each frame is written
+    /// to its own file `frame_<millis>_<sequence>.ppm`, which common image viewers open. Otherwise, all frames
+    /// are written to a single file created at the path, replacing an existing one.
+    /// The milliseconds are counted from the moment this sink was created, the sequence number from 0.
+    pub fn create(path: &Path) -> Result<FileSink> {
+        let target = if path.is_dir() {
+            FileSinkTarget::Directory(path.to_path_buf())
+        } else {
+            let file = File::create(path)
+                .wrap_err_with(|| format!("failed to create recording {}", path.display()))?;
+            FileSinkTarget::File(Mutex::new(file))
+        };
+        Ok(FileSink {
+            target,
+            started: Instant::now(),
+            recorded_frames: AtomicU64::new(0),
+        })
+    }
+}
+impl FtSink for FileSink {
+    fn send_frame_at(&self, ppm: &[u8], x: u32, y: u32, layer: u8) -> Result<()> {
+        let ppm = send_with_offset(
+            (x, y, layer),
+            || Ok(Cow::Borrowed(ppm)),
+            |x, y, layer| insert_ft_offset(ppm, x, y, layer).map(Cow::Owned),
+        )?;
+        if ppm.len() > MAX_DATAGRAM_SIZE {
+            return Err(eyre!(
+                "cannot record frame of {} bytes, recorded frames are limited to {} bytes",
+                ppm.len(),
+                MAX_DATAGRAM_SIZE
+            ));
+        }
+
+        let millis = self.started.elapsed().as_millis();
+        let sequence = self.recorded_frames.fetch_add(1, Ordering::Relaxed);
+        match &self.target {
+            FileSinkTarget::File(file) => {
+                let mut file = file.lock().unwrap();
+                writeln!(file, "{} {} {}", FRAME_HEADER_MAGIC, millis, ppm.len())
+                    .and_then(|_| file.write_all(&ppm))
+                    .wrap_err("failed to record frame")
+            }
+            FileSinkTarget::Directory(directory) => {
+                let path = directory.join(format!("frame_{}_{}.ppm", millis, sequence));
+                fs::write(&path, &ppm)
+                    .wrap_err_with(|| format!("failed to record frame to {}", path.display()))
+            }
+        }
+    }
+}
+
+/// A frame of a recording
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordedFrame {
+    /// The time since the recording started when the frame was recorded
+    pub elapsed: Duration,
+    pub ppm: Vec<u8>,
+}
+
+/// Reads the frames recorded by a `FileSink` in the order they were recorded.
+/// Frames are read lazily, recordings do not need to fit into memory.
+pub enum Recording {
+    File(BufReader<File>),
+    Directory(vec::IntoIter<((Duration, u64), PathBuf)>),
+}
+impl Recording {
+    /// Opens the recording at the given path, a recording file or a directory of `frame_<millis>_<sequence>.ppm`
+    /// files. Other files of the directory are ignored.
+    pub fn open(path: &Path) -> Result<Recording> {
+        if !path.is_dir() {
+            let file = File::open(path)
+                .wrap_err_with(|| format!("failed to open recording {}", path.display()))?;
+            return Ok(Recording::File(BufReader::new(file)));
+        }
+
+        let mut frames = Vec::new();
+        let entries = fs::read_dir(path)
+            .wrap_err_with(|| format!("failed to read recording {}", path.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            let position = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("frame_"))
+                .and_then(|name| name.strip_suffix(".ppm"))
+                .and_then(|name| name.split_once('_'))
+                .and_then(|(millis, sequence)| {
+                    Some((millis.parse::<u64>().ok()?, sequence.parse::<u64>().ok()?))
+                });
+            if let Some((millis, sequence)) = position {
+                frames.push(((Duration::from_millis(millis), sequence), path));
+            }
+        }
+        frames.sort();
+        Ok(Recording::Directory(frames.into_iter()))
+    }
+
+    /// Reads the next frame of a recording file, `None` at its end.
+    fn read_frame(reader: &mut BufReader<File>) -> Result<Option<RecordedFrame>> {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let invalid = || eyre!("invalid frame header `{}` in recording", header.trim_end());
+        let (millis, length) = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [FRAME_HEADER_MAGIC, millis, length] => (
+                millis.parse::<u64>().map_err(|_| invalid())?,
+                length.parse::<usize>().map_err(|_| invalid())?,
+            ),
+            _ => return Err(invalid()),
+        };
+        // the length is read from the file, a corrupt one must not allocate an arbitrary amount of memory:
+        if length > MAX_DATAGRAM_SIZE {
+            return Err(eyre!(
+                "frame of {} bytes in recording exceeds the maximum of {} bytes",
+                length,
+                MAX_DATAGRAM_SIZE
+            ));
+        }
+        let mut ppm = vec![0; length];
+        reader
+            .read_exact(&mut ppm)
+            .wrap_err("recording ends within a frame")?;
+        Ok(Some(RecordedFrame {
+            elapsed: Duration::from_millis(millis),
+            ppm,
+        }))
+    }
+}
+impl Iterator for Recording {
+    type Item = Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Recording::File(reader) => Recording::read_frame(reader).transpose(),
+            Recording::Directory(frames) => frames.next().map(|((elapsed, _), path)| {
+                let ppm = fs::read(&path)
+                    .wrap_err_with(|| format!("failed to read frame {}", path.display()))?;
+                Ok(RecordedFrame { elapsed, ppm })
+            }),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use eyre::{eyre, Result};
+
+/// A page screencasted into a fixed region of the LED screen, e.g. one pane of a dashboard
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionSpec {
+    pub url: String,
+    /// The horizontal offset of the region on the LED screen, in pixels
+    pub x: u32,
+    /// The vertical offset of the region on the LED screen, in pixels
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl RegionSpec {
+    /// Parses a region of the form `x,y,width,height=url`, e.g. `0,0,32,32=https://example.com`.
+    /// The URL may contain `=` itself, the region follows the first one.
+    pub fn parse(region: &str) -> Result<RegionSpec> {
+        let invalid = || {
+            eyre!(
+                "invalid region `{}`, expected the form `x,y,width,height=url`",
+                region
+            )
+        };
+        let (bounds, url) = region.trim().split_once('=').ok_or_else(invalid)?;
+        let bounds = bounds
+            .split(',')
+            .map(|bound| bound.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| invalid())?;
+        match bounds[..] {
+            [x, y, width, height] if width > 0 && height > 0 && !url.is_empty() => Ok(RegionSpec {
+                url: url.to_string(),
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Returns true if this region lies entirely within a screen of the given dimensions.
+    pub fn fits_into(&self, (width, height): (u32, u32)) -> bool {
+        self.x
+            .checked_add(self.width)
+            .map_or(false, |right| right <= width)
+            && self
+                .y
+                .checked_add(self.height)
+                .map_or(false, |bottom| bottom <= height)
+    }
+}
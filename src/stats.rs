@@ -0,0 +1,177 @@
+use log::info;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Runtime statistics of a screencast, updated concurrently by the browser event thread and frame handlers.
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// The number of frames received from the browser
+    pub frames_received: AtomicU64,
+    /// The number of received frames dropped before reaching the frame handler, e.g. because of the max fps
+    pub frames_dropped: AtomicU64,
+    /// The number of converted frames dropped to respect the maximum bytes per second, also counted in `frames_dropped`
+    pub frames_rate_limited: AtomicU64,
+    /// The number of converted frames not sent because they were identical to the previously sent one
+    pub frames_skipped: AtomicU64,
+    /// The number of frames sent to the flaschentaschen server
+    pub frames_sent: AtomicU64,
+    /// The number of PPM bytes sent to the flaschentaschen server
+    pub bytes_sent: AtomicU64,
+    /// The number of frames which could not be handled, the sum of `decode_errors` and `send_errors`
+    pub errors: AtomicU64,
+    /// The number of frames which could not be decoded or converted, see `FtError::is_decode_error`
+    pub decode_errors: AtomicU64,
+    /// The number of frames which could not be sent, or failed for any other reason
+    pub send_errors: AtomicU64,
+    /// The number of frames waiting in the frame queue of `start_screencasting`, see `record_queue_depth`
+    pub queue_depth: AtomicU64,
+    /// The highest `queue_depth` recorded so far
+    pub peak_queue_depth: AtomicU64,
+    /// The number of handled frames whose latency was recorded, see `record_frame_latency`
+    pub latency_samples: AtomicU64,
+    /// The sum of the latencies of all `latency_samples`, in microseconds
+    pub total_latency_micros: AtomicU64,
+    /// The highest frame latency recorded so far, in microseconds
+    pub max_latency_micros: AtomicU64,
+    /// When the last frame was sent to the flaschentaschen server, `None` before the first frame
+    pub last_frame_sent: Mutex<Option<Instant>>,
+}
+
+/// A point-in-time copy of the counters of `Stats`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StatsSnapshot {
+    pub frames_received: u64,
+    pub frames_dropped: u64,
+    pub frames_rate_limited: u64,
+    pub frames_skipped: u64,
+    pub frames_sent: u64,
+    pub bytes_sent: u64,
+    pub errors: u64,
+    pub decode_errors: u64,
+    pub send_errors: u64,
+    pub queue_depth: u64,
+    pub peak_queue_depth: u64,
+    pub latency_samples: u64,
+    pub total_latency_micros: u64,
+    pub max_latency_micros: u64,
+}
+impl StatsSnapshot {
+    /// Returns the average latency of the frames handled since the given earlier snapshot,
+    /// or `None` if no latency was recorded in between.
+    pub fn average_latency_since(&self, previous: &StatsSnapshot) -> Option<Duration> {
+        let samples = self.latency_samples - previous.latency_samples;
+        (samples > 0).then(|| {
+            Duration::from_micros(
+                (self.total_latency_micros - previous.total_latency_micros) / samples,
+            )
+        })
+    }
+}
+
+impl Stats {
+    /// Returns a new, shareable stats instance with all counters set to zero.
+    pub fn new() -> Arc<Stats> {
+        Arc::new(Stats::default())
+    }
+
+    /// Increments the given counter by the given amount.
+    pub fn add(counter: &AtomicU64, amount: u64) {
+        counter.fetch_add(amount, Ordering::Relaxed);
+    }
+
+    /// Records a frame sent to the flaschentaschen server with the given size in bytes.
+    pub fn record_sent_frame(&self, bytes: usize) {
+        Stats::add(&self.frames_sent, 1);
+        Stats::add(&self.bytes_sent, bytes as u64);
+        *self.last_frame_sent.lock().unwrap() = Some(Instant::now());
+    }
+
+    /// Records a converted frame dropped to respect the maximum bytes per second.
+    pub fn record_rate_limited_frame(&self) {
+        Stats::add(&self.frames_dropped, 1);
+        Stats::add(&self.frames_rate_limited, 1);
+    }
+
+    /// Records the current number of frames waiting in the frame queue, updating the peak depth.
+    pub fn record_queue_depth(&self, depth: usize) {
+        self.queue_depth.store(depth as u64, Ordering::Relaxed);
+        self.peak_queue_depth
+            .fetch_max(depth as u64, Ordering::Relaxed);
+    }
+
+    /// Records the time from capturing a frame in the browser until it was handled, see `frame_latency`.
+    pub fn record_frame_latency(&self, latency: Duration) {
+        let micros = latency.as_micros() as u64;
+        Stats::add(&self.latency_samples, 1);
+        Stats::add(&self.total_latency_micros, micros);
+        self.max_latency_micros.fetch_max(micros, Ordering::Relaxed);
+    }
+
+    /// Returns the time since the last frame was sent, or `None` if no frame was sent yet.
+    pub fn last_frame_age(&self) -> Option<Duration> {
+        self.last_frame_sent
+            .lock()
+            .unwrap()
+            .map(|sent| sent.elapsed())
+    }
+
+    /// Returns the current value of all counters.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            frames_received: self.frames_received.load(Ordering::Relaxed),
+            frames_dropped: self.frames_dropped.load(Ordering::Relaxed),
+            frames_rate_limited: self.frames_rate_limited.load(Ordering::Relaxed),
+            frames_skipped: self.frames_skipped.load(Ordering::Relaxed),
+            frames_sent: self.frames_sent.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+            send_errors: self.send_errors.load(Ordering::Relaxed),
+            queue_depth: self.queue_depth.load(Ordering::Relaxed),
+            peak_queue_depth: self.peak_queue_depth.load(Ordering::Relaxed),
+            latency_samples: self.latency_samples.load(Ordering::Relaxed),
+            total_latency_micros: self.total_latency_micros.load(Ordering::Relaxed),
+            max_latency_micros: self.max_latency_micros.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Spawns a thread logging a summary of the given stats every `interval`.
+/// The effective rates are computed from the counter deltas between two summaries.
+pub fn spawn_stats_logger(stats: Arc<Stats>, interval: Duration) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut previous = stats.snapshot();
+        let mut previous_time = Instant::now();
+        loop {
+            thread::sleep(interval);
+            let current = stats.snapshot();
+            let elapsed = previous_time.elapsed().as_secs_f64();
+            previous_time = Instant::now();
+
+            let average_latency_millis = current
+                .average_latency_since(&previous)
+                .map_or(f64::NAN, |latency| latency.as_secs_f64() * 1000.0);
+
+            info!(
+                "stats: {:.1} fps received, {:.1} fps sent, {:.0} bytes/s, {} dropped, {} skipped, {} errors ({} decode, {} send), {} queued (peak {}), {:.1}ms latency (max {:.1}ms) (total: {} frames sent, {} bytes sent)",
+                (current.frames_received - previous.frames_received) as f64 / elapsed,
+                (current.frames_sent - previous.frames_sent) as f64 / elapsed,
+                (current.bytes_sent - previous.bytes_sent) as f64 / elapsed,
+                current.frames_dropped - previous.frames_dropped,
+                current.frames_skipped - previous.frames_skipped,
+                current.errors - previous.errors,
+                current.decode_errors - previous.decode_errors,
+                current.send_errors - previous.send_errors,
+                current.queue_depth,
+                current.peak_queue_depth,
+                average_latency_millis,
+                current.max_latency_micros as f64 / 1000.0,
+                current.frames_sent,
+                current.bytes_sent,
+            );
+            previous = current;
+        }
+    })
+}
@@ -0,0 +1,143 @@
+use crate::{FlaschenTaschen, MAX_DATAGRAM_SIZE, SIZE_QUERY};
+use eyre::Result;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// How often the receiving thread checks if the server was dropped.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A fake flaschentaschen server listening on an ephemeral local UDP port, available with the `testing` feature.
+/// All received datagrams are recorded in order and can be inspected using `received` or `wait_for_datagrams`.
+/// Like the stock server, it does not answer the size query unless started with `start_with_size_response`.
+/// The server stops listening once it is dropped, see `stop`.
+pub struct MockFtServer {
+    address: SocketAddr,
+    received: Arc<(Mutex<Vec<Vec<u8>>>, Condvar)>,
+    stopped: Arc<AtomicBool>,
+    receiver: Option<JoinHandle<()>>,
+}
+
+impl MockFtServer {
+    /// Binds a new mock server on the IPv4 loopback address and starts receiving datagrams on a background thread.
+    pub fn start() -> Result<MockFtServer> {
+        MockFtServer::start_on("127.0.0.1:0")
+    }
+
+    /// Binds a new mock server on the given local address, e.g. `[::1]:0` to test IPv6,
+    /// and starts receiving datagrams on a background thread.
+    pub fn start_on(bind_addr: &str) -> Result<MockFtServer> {
+        MockFtServer::bind(bind_addr, Duration::from_secs(0), None)
+    }
+
+    /// Like `start`, but each datagram is only recorded the given latency after it was received,
+    /// like a server behind a slow link. Datagrams are handled one after another, like the stock server does.
+    pub fn start_with_latency(latency: Duration) -> Result<MockFtServer> {
+        MockFtServer::bind("127.0.0.1:0", latency, None)
+    }
+
+    /// Like `start`, but answers each `SIZE_QUERY` with the given response, e.g. `64x32`.
+    /// The queries are not recorded, they are no frames.
+    pub fn start_with_size_response(response: &[u8]) -> Result<MockFtServer> {
+        MockFtServer::bind(
+            "127.0.0.1:0",
+            Duration::from_secs(0),
+            Some(response.to_vec()),
+        )
+    }
+
+    fn bind(
+        bind_addr: &str,
+        latency: Duration,
+        size_response: Option<Vec<u8>>,
+    ) -> Result<MockFtServer> {
+        let socket = UdpSocket::bind(bind_addr)?;
+        socket.set_read_timeout(Some(STOP_POLL_INTERVAL))?;
+        let address = socket.local_addr()?;
+
+        let received = Arc::new((Mutex::new(Vec::new()), Condvar::new()));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread_received = received.clone();
+        let thread_stopped = stopped.clone();
+        let receiver = thread::spawn(move || {
+            let mut buffer = vec![0u8; MAX_DATAGRAM_SIZE];
+            while !thread_stopped.load(Ordering::Relaxed) {
+                // errors are read timeouts, which only give us the chance to check `stopped`:
+                if let Ok((len, sender)) = socket.recv_from(&mut buffer) {
+                    let query = &buffer[..len] == SIZE_QUERY;
+                    if let Some(response) = size_response.as_ref().filter(|_| query) {
+                        let _ = socket.send_to(response, sender);
+                        continue;
+                    }
+                    if latency > Duration::from_secs(0) {
+                        thread::sleep(latency);
+                    }
+                    let (datagrams, signal) = &*thread_received;
+                    datagrams.lock().unwrap().push(buffer[..len].to_vec());
+                    signal.notify_all();
+                }
+            }
+        });
+
+        Ok(MockFtServer {
+            address,
+            received,
+            stopped,
+            receiver: Some(receiver),
+        })
+    }
+
+    /// Stops listening like dropping this server, e.g. to simulate a server restart.
+    /// Returns once the port is released, a new server can be started on the same address using `start_on`.
+    pub fn stop(self) {
+        drop(self);
+    }
+
+    /// Returns the local address this server is listening on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns a new flaschentaschen instance sending to this server.
+    pub fn flaschentaschen(&self) -> Result<FlaschenTaschen> {
+        Ok(FlaschenTaschen::new(self.address.to_string())?)
+    }
+
+    /// Returns all datagrams received so far.
+    pub fn received(&self) -> Vec<Vec<u8>> {
+        self.received.0.lock().unwrap().clone()
+    }
+
+    /// Forgets all datagrams received so far, e.g. between the iterations of a benchmark.
+    pub fn clear_received(&self) {
+        self.received.0.lock().unwrap().clear();
+    }
+
+    /// Blocks until at least `count` datagrams were received or the given timeout elapsed,
+    /// then returns all datagrams received so far.
+    pub fn wait_for_datagrams(&self, count: usize, timeout: Duration) -> Vec<Vec<u8>> {
+        let (datagrams, signal) = &*self.received;
+        let deadline = Instant::now() + timeout;
+        let mut datagrams = datagrams.lock().unwrap();
+        while datagrams.len() < count {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            datagrams = signal.wait_timeout(datagrams, deadline - now).unwrap().0;
+        }
+        datagrams.clone()
+    }
+}
+
+impl Drop for MockFtServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // the socket is closed once the receiving thread noticed it should stop:
+        if let Some(receiver) = self.receiver.take() {
+            let _ = receiver.join();
+        }
+    }
+}
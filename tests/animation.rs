@@ -0,0 +1,45 @@
+use flaschentaschen_web::animation::{play_animation, AnimationFrame};
+use flaschentaschen_web::control::ScreencastControl;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn frames() -> Vec<AnimationFrame> {
+    vec![
+        AnimationFrame {
+            ppm: vec![1],
+            delay: Duration::from_millis(20),
+        },
+        AnimationFrame {
+            ppm: vec![2],
+            delay: Duration::from_millis(20),
+        },
+    ]
+}
+
+#[test]
+fn plays_all_frames_once() {
+    let mut shown = Vec::new();
+    let completed = play_animation(&frames(), false, None, &ScreencastControl::new(), |ppm| {
+        shown.push(ppm[0])
+    });
+    assert!(completed);
+    assert_eq!(shown, vec![1, 2]);
+}
+
+#[test]
+fn stops_a_repeating_animation_once_stopped() {
+    let control = Arc::new(ScreencastControl::new());
+    let stopping_control = control.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        stopping_control.stop();
+    });
+
+    let start = Instant::now();
+    let mut shown = 0;
+    let completed = play_animation(&frames(), true, None, &control, |_| shown += 1);
+    assert!(!completed);
+    assert!(shown >= 2);
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
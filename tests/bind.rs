@@ -0,0 +1,40 @@
+use flaschentaschen_web::error::FtError;
+use flaschentaschen_web::FlaschenTaschen;
+use std::net::UdpSocket;
+
+#[test]
+fn binds_ipv4_socket_for_ipv4_target() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = server.local_addr().unwrap();
+
+    let flaschentaschen = FlaschenTaschen::new(target.to_string()).unwrap();
+
+    assert!(flaschentaschen.local_addr().unwrap().is_ipv4());
+}
+
+#[test]
+fn binds_given_local_address() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = server.local_addr().unwrap();
+
+    let flaschentaschen =
+        FlaschenTaschen::with_bind(target.to_string(), Some(String::from("127.0.0.1:0"))).unwrap();
+
+    assert_eq!(
+        flaschentaschen.local_addr().unwrap().ip().to_string(),
+        "127.0.0.1"
+    );
+}
+
+#[test]
+fn reports_the_server_address_if_the_local_address_cannot_be_bound() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let target = server.local_addr().unwrap().to_string();
+
+    let result = FlaschenTaschen::with_bind(target.clone(), Some(String::from("256.0.0.1:0")));
+
+    match result {
+        Err(FtError::SocketBind { address, .. }) => assert_eq!(address, target),
+        _ => panic!("expected a SocketBind error"),
+    }
+}
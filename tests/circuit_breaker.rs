@@ -0,0 +1,53 @@
+use flaschentaschen_web::circuit_breaker::CircuitBreaker;
+use std::thread;
+use std::time::Duration;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+#[test]
+fn trips_after_more_than_the_threshold_of_consecutive_errors() {
+    let mut breaker = CircuitBreaker::new(3, WINDOW);
+    for _ in 0..3 {
+        assert!(!breaker.record_error());
+    }
+    assert!(!breaker.is_tripped());
+
+    assert!(breaker.record_error());
+    assert!(breaker.is_tripped());
+    assert_eq!(breaker.consecutive_errors(), 4);
+}
+
+#[test]
+fn resets_the_consecutive_errors_on_success() {
+    let mut breaker = CircuitBreaker::new(2, WINDOW);
+    breaker.record_error();
+    breaker.record_error();
+    breaker.record_success();
+    assert_eq!(breaker.consecutive_errors(), 0);
+
+    assert!(!breaker.record_error());
+    assert!(!breaker.record_error());
+    assert!(!breaker.is_tripped());
+}
+
+#[test]
+fn resets_the_consecutive_errors_once_the_window_elapsed() {
+    let mut breaker = CircuitBreaker::new(1, Duration::from_millis(50));
+    breaker.record_error();
+    thread::sleep(Duration::from_millis(100));
+
+    assert!(!breaker.record_error());
+    assert_eq!(breaker.consecutive_errors(), 1);
+    assert!(!breaker.is_tripped());
+}
+
+#[test]
+fn stays_tripped_and_reports_tripping_only_once() {
+    let mut breaker = CircuitBreaker::new(0, WINDOW);
+    assert!(breaker.record_error());
+    assert!(!breaker.record_error());
+
+    breaker.record_success();
+    assert!(breaker.is_tripped());
+    assert!(!breaker.record_error());
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use clap::Parser;
+use common::temp_path;
+use flaschentaschen_web::cli::{CliArgs, FrameSource, ResolvedArgs};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+fn resolve(args: &[&str]) -> eyre::Result<ResolvedArgs> {
+    let mut argv = vec![
+        "flaschentaschen-web",
+        "--ft-endpoint",
+        "localhost:1337",
+        "--url",
+        "https://example.com",
+    ];
+    argv.extend_from_slice(args);
+    CliArgs::try_parse_from(argv)?.resolve()
+}
+
+/// Like `resolve`, but without a source, e.g. to give a subcommand.
+fn resolve_command(args: &[&str]) -> eyre::Result<ResolvedArgs> {
+    let mut argv = vec!["flaschentaschen-web"];
+    argv.extend_from_slice(args);
+    CliArgs::try_parse_from(argv)?.resolve()
+}
+
+#[test]
+fn parses_shared_arguments_after_the_screencast_subcommand() {
+    let args = resolve_command(&[
+        "screencast",
+        "--url",
+        "https://example.com",
+        "--ft-endpoint",
+        "localhost:1337",
+        "--gamma",
+        "2",
+    ])
+    .unwrap();
+    assert!(matches!(args.source, FrameSource::Url(url) if url == "https://example.com"));
+    assert_eq!(args.ft_endpoint, vec!["localhost:1337"]);
+    assert_eq!(args.gamma, Some(2.0));
+}
+
+#[test]
+fn parses_shared_arguments_after_the_image_subcommand() {
+    let args = resolve_command(&[
+        "image",
+        "foo.png",
+        "--ft-endpoint",
+        "localhost:1337",
+        "--gamma",
+        "2",
+    ])
+    .unwrap();
+    assert!(matches!(args.source, FrameSource::Image(path) if path == Path::new("foo.png")));
+    assert_eq!(args.gamma, Some(2.0));
+}
+
+#[test]
+fn parses_the_test_pattern_subcommand() {
+    let args = resolve_command(&["test-pattern", "--ft-endpoint", "localhost:1337"]).unwrap();
+    assert!(matches!(args.source, FrameSource::TestPattern));
+    assert!(args.test_pattern);
+}
+
+#[test]
+fn parses_the_query_size_subcommand() {
+    let args = resolve_command(&["--ft-endpoint", "localhost:1337", "query-size"]).unwrap();
+    assert!(matches!(args.source, FrameSource::QuerySize));
+}
+
+#[test]
+fn rejects_sources_next_to_subcommands_giving_their_own() {
+    assert!(resolve(&["image", "foo.png"]).is_err());
+    assert!(resolve_command(&[
+        "image",
+        "foo.png",
+        "--ft-endpoint",
+        "localhost:1337",
+        "--url",
+        "https://example.com",
+    ])
+    .is_err());
+    assert!(resolve(&["test-pattern"]).is_err());
+    assert!(resolve(&["query-size"]).is_err());
+    // the screencast subcommand only screencasts websites:
+    assert!(resolve_command(&[
+        "screencast",
+        "--ft-endpoint",
+        "localhost:1337",
+        "--image",
+        "foo.png",
+    ])
+    .is_err());
+}
+
+/// Writes the given TOML to a new config file and returns its path.
+fn config_file(toml: &str) -> String {
+    let path = temp_path("config.toml");
+    fs::write(&path, toml).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn reads_settings_from_the_config_file() {
+    let config = config_file(
+        r#"
+ft-endpoint = ["wall:1337"]
+screen-width = 64
+screen-height = 32
+"#,
+    );
+
+    // the source is given on the command line, another test sets FT_URL which overrides the config file:
+    let args = resolve_command(&["--config", &config, "--url", "https://example.com"]).unwrap();
+    assert_eq!(args.ft_endpoint, vec!["wall:1337"]);
+    assert_eq!(
+        (args.screen_width, args.screen_height),
+        (Some(64), Some(32))
+    );
+    fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn command_line_arguments_override_the_config_file() {
+    let config = config_file(
+        r#"
+ft-endpoint = ["wall:1337"]
+screen-width = 64
+screen-height = 32
+"#,
+    );
+
+    let args = resolve(&["--config", &config, "--screen-width", "45"]).unwrap();
+    assert_eq!(args.ft_endpoint, vec!["localhost:1337"]);
+    assert_eq!(
+        (args.screen_width, args.screen_height),
+        (Some(45), Some(32))
+    );
+    fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn rejects_unknown_config_file_settings() {
+    let config = config_file("screen-widht = 64\n");
+
+    assert!(resolve(&["--config", &config]).is_err());
+    fs::remove_file(config).unwrap();
+}
+
+#[test]
+fn parses_compound_max_runtimes() {
+    let args = resolve(&["--max-runtime", "1h 30m"]).unwrap();
+    assert_eq!(args.max_runtime, Some(Duration::from_secs(90 * 60)));
+    let args = resolve(&["--max-runtime", "1h30m"]).unwrap();
+    assert_eq!(args.max_runtime, Some(Duration::from_secs(90 * 60)));
+}
+
+#[test]
+fn rejects_invalid_max_runtimes() {
+    assert!(resolve(&["--max-runtime", "0s"]).is_err());
+    assert!(resolve(&["--max-runtime", "forever"]).is_err());
+    assert!(resolve(&["--max-runtime", "9999999999999999999h"]).is_err());
+}
+
+#[test]
+fn sources_on_the_command_line_override_source_environment_variables() {
+    // the only test setting FT_URL, other tests give their source on the command line:
+    std::env::set_var("FT_URL", "https://example.org");
+
+    let args = CliArgs::try_parse_from([
+        "flaschentaschen-web",
+        "--ft-endpoint",
+        "localhost:1337",
+        "--image",
+        "frame.png",
+    ])
+    .unwrap()
+    .resolve()
+    .unwrap();
+    assert!(matches!(args.source, FrameSource::Image(path) if path == Path::new("frame.png")));
+
+    let args = CliArgs::try_parse_from(["flaschentaschen-web", "--ft-endpoint", "localhost:1337"])
+        .unwrap()
+        .resolve()
+        .unwrap();
+    assert!(matches!(args.source, FrameSource::Url(url) if url == "https://example.org"));
+
+    std::env::remove_var("FT_URL");
+}
@@ -0,0 +1,100 @@
+use flaschentaschen_web::color::{
+    apply_gamma, color_correct, dither_floyd_steinberg, ColorCorrection,
+};
+use image::{Rgb, RgbImage};
+use std::collections::BTreeSet;
+
+/// Returns the given pixel after applying the given color correction to it.
+fn corrected(pixel: [u8; 3], params: ColorCorrection) -> [u8; 3] {
+    let mut image = RgbImage::from_pixel(1, 1, Rgb(pixel));
+    color_correct(&mut image, &params);
+    image.get_pixel(0, 0).0
+}
+
+#[test]
+fn keeps_pixels_unchanged_by_default() {
+    assert_eq!(
+        corrected([12, 128, 250], ColorCorrection::default()),
+        [12, 128, 250]
+    );
+}
+
+#[test]
+fn applies_gamma_and_brightness() {
+    let params = ColorCorrection {
+        gamma: 2.0,
+        brightness: 0.5,
+        ..Default::default()
+    };
+    // 255 * 0.5 * (x / 255) ^ 2:
+    assert_eq!(corrected([0, 51, 255], params), [0, 5, 128]);
+}
+
+#[test]
+fn applies_the_gamma_of_led_screens() {
+    let mut image = RgbImage::from_fn(5, 1, |x, _| {
+        let value = [0, 64, 128, 192, 255][x as usize];
+        Rgb([value, value, value])
+    });
+    apply_gamma(&mut image, 2.2, 1.0);
+    // 255 * (x / 255) ^ 2.2:
+    let values: Vec<u8> = image.pixels().map(|pixel| pixel.0[0]).collect();
+    assert_eq!(values, [0, 12, 56, 137, 255]);
+}
+
+#[test]
+fn reduces_contrast_towards_the_midtone() {
+    let params = ColorCorrection {
+        contrast: 0.5,
+        ..Default::default()
+    };
+    assert_eq!(corrected([0, 255, 127], params), [64, 191, 127]);
+}
+
+#[test]
+fn increases_contrast_and_clamps() {
+    let params = ColorCorrection {
+        contrast: 2.0,
+        ..Default::default()
+    };
+    assert_eq!(corrected([0, 255, 60], params), [0, 255, 0]);
+}
+
+#[test]
+fn desaturates_to_the_lightness() {
+    let params = ColorCorrection {
+        saturation: 0.0,
+        ..Default::default()
+    };
+    assert_eq!(corrected([255, 0, 0], params), [128, 128, 128]);
+}
+
+#[test]
+fn halves_saturation_keeping_hue_and_lightness() {
+    let params = ColorCorrection {
+        saturation: 0.5,
+        ..Default::default()
+    };
+    assert_eq!(corrected([255, 0, 0], params), [191, 64, 64]);
+    assert_eq!(corrected([0, 0, 255], params), [64, 64, 191]);
+}
+
+#[test]
+fn clamps_saturation_of_saturated_pixels() {
+    let params = ColorCorrection {
+        saturation: 2.0,
+        ..Default::default()
+    };
+    assert_eq!(corrected([0, 255, 0], params), [0, 255, 0]);
+}
+
+#[test]
+fn dithers_gradients_to_the_quantized_levels() {
+    let mut gradient = RgbImage::from_fn(256, 4, |x, y| {
+        Rgb([x as u8, 255 - x as u8, (x / 2 + y) as u8])
+    });
+    dither_floyd_steinberg(&mut gradient, 4);
+
+    let values: BTreeSet<u8> = gradient.pixels().flat_map(|pixel| pixel.0).collect();
+    assert_eq!(values, BTreeSet::from([0, 85, 170, 255]));
+}
@@ -0,0 +1,16 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of paths returned by `temp_path` in this test binary
+static TEMP_PATHS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a path in the temp directory ending with the given name, unique to this process and call.
+/// Tests of one binary run in parallel, a path only made unique by the process would be shared between them.
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "flaschentaschen-web-{}-{}-{}",
+        std::process::id(),
+        TEMP_PATHS.fetch_add(1, Ordering::Relaxed),
+        name
+    ))
+}
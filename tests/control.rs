@@ -0,0 +1,70 @@
+use flaschentaschen_web::control::ScreencastControl;
+use flaschentaschen_web::queue::FrameQueue;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A frame handler context recording when it is dropped
+struct Context {
+    dropped: Arc<AtomicBool>,
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        self.dropped.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn drops_the_context_of_the_worker_once_stopped() {
+    let control = ScreencastControl::new();
+    let queue: Arc<FrameQueue<u32>> = Arc::new(FrameQueue::new(4));
+    let dropped = Arc::new(AtomicBool::new(false));
+    let context = Arc::new(Context {
+        dropped: dropped.clone(),
+    });
+
+    let stop_queue = queue.clone();
+    control.on_stop(move || stop_queue.close());
+    let worker_queue = queue.clone();
+    control.spawn(move || {
+        while let Some(_frame) = worker_queue.pop() {
+            let _ = &context;
+        }
+    });
+    queue.push(1);
+
+    control.stop();
+    assert!(control.is_stopped());
+    assert!(dropped.load(Ordering::SeqCst));
+}
+
+#[test]
+fn wakes_up_sleeping_threads_when_stopped() {
+    let control = Arc::new(ScreencastControl::new());
+    let slept = Arc::new(AtomicBool::new(true));
+
+    let thread_control = control.clone();
+    let thread_slept = slept.clone();
+    control.spawn(move || {
+        let slept = thread_control.sleep(Duration::from_secs(60));
+        thread_slept.store(slept, Ordering::SeqCst);
+    });
+
+    let start = Instant::now();
+    control.stop();
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(!slept.load(Ordering::SeqCst));
+    assert!(!control.sleep(Duration::from_secs(60)));
+}
+
+#[test]
+fn runs_callbacks_registered_after_stopping_right_away() {
+    let control = ScreencastControl::new();
+    control.stop();
+
+    let called = Arc::new(AtomicBool::new(false));
+    let callback_called = called.clone();
+    control.on_stop(move || callback_called.store(true, Ordering::SeqCst));
+    assert!(called.load(Ordering::SeqCst));
+}
@@ -0,0 +1,496 @@
+use flaschentaschen_web::error::FtError;
+use flaschentaschen_web::ppm::validate_ppm;
+use flaschentaschen_web::{
+    fit_into, get_ppm_from_buffer, get_ppm_from_dynamic_image, guess_image_format, image_to_ppm,
+    is_integer_multiple, remap_serpentine, ChannelOrder, ConversionOptions, Dimension, Orientation,
+    PpmConverter, ScalingMode, ScreencastImageFormat, TrackedElement,
+};
+use image::imageops::FilterType;
+use image::{
+    DynamicImage, GenericImageView, ImageFormat, ImageOutputFormat, Rgb, RgbImage, Rgba, RgbaImage,
+};
+use std::path::Path;
+
+/// A lossy WebP image of 1x1 pixels
+const WEBP_1X1: &[u8] = &[
+    0x52, 0x49, 0x46, 0x46, 0x22, 0x00, 0x00, 0x00, 0x57, 0x45, 0x42, 0x50, 0x56, 0x50, 0x38, 0x20,
+    0x16, 0x00, 0x00, 0x00, 0x30, 0x01, 0x00, 0x9d, 0x01, 0x2a, 0x01, 0x00, 0x01, 0x00, 0x0e, 0xc0,
+    0xfe, 0x25, 0xa4, 0x00, 0x03, 0x70, 0x00, 0x00, 0x00, 0x00,
+];
+
+#[test]
+fn resizes_stray_sized_frames_to_the_target_size() {
+    for scaling_mode in [ScalingMode::Stretch, ScalingMode::Fit, ScalingMode::Fill] {
+        let opts = ConversionOptions {
+            target_size: Some((64, 32)),
+            scaling_mode,
+            ..Default::default()
+        };
+        // a frame smaller than the screen, e.g. because the page content is smaller than the viewport:
+        let frame = DynamicImage::ImageRgb8(RgbImage::new(50, 30));
+
+        let ppm = get_ppm_from_dynamic_image(frame, &opts).unwrap();
+        assert!(
+            validate_ppm(&ppm, 64, 32).is_ok(),
+            "{:?} produced a frame of the wrong size",
+            scaling_mode
+        );
+    }
+}
+
+#[test]
+fn keeps_frames_of_the_target_size() {
+    let opts = ConversionOptions {
+        target_size: Some((64, 32)),
+        ..Default::default()
+    };
+    let frame = DynamicImage::ImageRgb8(RgbImage::new(64, 32));
+
+    let ppm = get_ppm_from_dynamic_image(frame, &opts).unwrap();
+    assert!(validate_ppm(&ppm, 64, 32).is_ok());
+}
+
+#[test]
+fn resizes_frames_with_the_given_filter() {
+    let red = Rgb([255, 0, 0]);
+    let blue = Rgb([0, 0, 255]);
+    let frame = || {
+        let mut frame = RgbImage::from_pixel(2, 1, red);
+        frame.put_pixel(1, 0, blue);
+        DynamicImage::ImageRgb8(frame)
+    };
+    let opts = |resize_filter| ConversionOptions {
+        target_size: Some((4, 1)),
+        resize_filter,
+        ..Default::default()
+    };
+
+    let ppm = get_ppm_from_dynamic_image(frame(), &opts(FilterType::Nearest)).unwrap();
+    assert_eq!(
+        pixel_data(&ppm, 4, 1),
+        [255, 0, 0, 255, 0, 0, 0, 0, 255, 0, 0, 255]
+    );
+
+    // interpolating filters blend the pixels next to the edge between both colors:
+    let ppm = get_ppm_from_dynamic_image(frame(), &opts(FilterType::Triangle)).unwrap();
+    validate_ppm(&ppm, 4, 1).unwrap();
+    let blended = &pixel_data(&ppm, 4, 1)[3..6];
+    assert!(blended[0] < 255 && blended[2] > 0, "{:?}", blended);
+}
+
+#[test]
+fn pads_fitted_frames_with_the_background_color() {
+    let white = Rgb([255, 255, 255]);
+    let blue = Rgb([0, 0, 255]);
+    let frame = DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 2, white));
+
+    let fitted = fit_into(&frame, 4, 4, blue, FilterType::Nearest);
+    assert_eq!(fitted.dimensions(), (4, 4));
+    for x in 0..4 {
+        assert_eq!(*fitted.get_pixel(x, 0), blue);
+        assert_eq!(*fitted.get_pixel(x, 1), white);
+        assert_eq!(*fitted.get_pixel(x, 2), white);
+        assert_eq!(*fitted.get_pixel(x, 3), blue);
+    }
+}
+
+#[test]
+fn crops_the_overflowing_sides_of_filled_frames() {
+    let opts = ConversionOptions {
+        target_size: Some((2, 2)),
+        resize_filter: FilterType::Nearest,
+        scaling_mode: ScalingMode::Fill,
+        ..Default::default()
+    };
+    // the outer columns are cut off when covering a square screen:
+    let frame = RgbImage::from_fn(4, 2, |x, _| match x {
+        0 | 3 => Rgb([0, 0, 255]),
+        _ => Rgb([255, 0, 0]),
+    });
+
+    let ppm = get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(frame), &opts).unwrap();
+    assert_eq!(pixel_data(&ppm, 2, 2), [255, 0, 0].repeat(4).as_slice());
+}
+
+/// Returns the pixel data of the given binary PPM of the given dimensions.
+fn pixel_data(ppm: &[u8], width: u32, height: u32) -> &[u8] {
+    &ppm[ppm.len() - (width * height * 3) as usize..]
+}
+
+#[test]
+fn blends_transparent_pixels_onto_the_pad_color() {
+    let opts = ConversionOptions {
+        pad_color: Rgb([0, 0, 255]),
+        ..Default::default()
+    };
+    let frame = RgbaImage::from_raw(2, 1, vec![255, 0, 0, 0, 255, 0, 0, 255]).unwrap();
+
+    let ppm = get_ppm_from_dynamic_image(DynamicImage::ImageRgba8(frame), &opts).unwrap();
+    assert_eq!(pixel_data(&ppm, 2, 1), &[0, 0, 255, 255, 0, 0]);
+}
+
+#[test]
+fn keys_transparent_pixels_black_for_overlays() {
+    let opts = ConversionOptions {
+        transparent_overlay: true,
+        ..Default::default()
+    };
+    let mut frame = RgbaImage::new(3, 1);
+    frame.put_pixel(0, 0, Rgba([255, 0, 0, 0]));
+    frame.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+    frame.put_pixel(2, 0, Rgba([10, 20, 30, 255]));
+
+    let ppm = get_ppm_from_dynamic_image(DynamicImage::ImageRgba8(frame), &opts).unwrap();
+    assert_eq!(pixel_data(&ppm, 3, 1), &[0, 0, 0, 1, 1, 1, 10, 20, 30]);
+}
+
+#[test]
+fn detects_integer_multiples() {
+    assert!(is_integer_multiple((64, 32), (64, 32)));
+    assert!(is_integer_multiple((128, 64), (64, 32)));
+    assert!(is_integer_multiple((192, 96), (64, 32)));
+}
+
+#[test]
+fn rejects_non_integer_multiples() {
+    // different factors per axis:
+    assert!(!is_integer_multiple((128, 96), (64, 32)));
+    // not a multiple:
+    assert!(!is_integer_multiple((100, 50), (64, 32)));
+    // upscaling:
+    assert!(!is_integer_multiple((32, 16), (64, 32)));
+    assert!(!is_integer_multiple((64, 32), (0, 0)));
+}
+
+#[test]
+fn downscales_integer_multiples_with_nearest_neighbor() {
+    let opts = ConversionOptions {
+        target_size: Some((1, 1)),
+        nearest_for_integer_multiples: true,
+        ..Default::default()
+    };
+    // a smooth filter would blend these pixels into gray:
+    let frame =
+        RgbImage::from_raw(2, 2, vec![255, 255, 255, 0, 0, 0, 0, 0, 0, 255, 255, 255]).unwrap();
+
+    let ppm = get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(frame), &opts).unwrap();
+    let pixel = pixel_data(&ppm, 1, 1);
+    assert!(pixel == [255, 255, 255] || pixel == [0, 0, 0]);
+}
+
+#[test]
+fn decodes_webp_into_ppm() {
+    let opts = ConversionOptions {
+        target_size: Some((4, 2)),
+        ..Default::default()
+    };
+
+    let ppm = get_ppm_from_buffer(WEBP_1X1, ImageFormat::WebP, &opts).unwrap();
+    assert!(validate_ppm(&ppm, 4, 2).is_ok());
+}
+
+#[test]
+fn guesses_image_format_from_magic_bytes_before_the_extension() {
+    assert_eq!(
+        guess_image_format(WEBP_1X1, Path::new("image.png")),
+        Some(ImageFormat::WebP)
+    );
+}
+
+#[test]
+fn guesses_image_format_from_the_extension_of_unknown_contents() {
+    assert_eq!(
+        guess_image_format(b"unknown", Path::new("image.webp")),
+        Some(ImageFormat::WebP)
+    );
+    assert_eq!(guess_image_format(b"unknown", Path::new("-")), None);
+}
+
+#[test]
+fn converts_images_to_ppm_without_resizing() {
+    let mut image = RgbaImage::from_pixel(3, 2, Rgba([0, 255, 0, 255]));
+    image.put_pixel(0, 0, Rgba([255, 255, 255, 0]));
+
+    let ppm = image_to_ppm(&DynamicImage::ImageRgba8(image)).unwrap();
+
+    validate_ppm(&ppm, 3, 2).unwrap();
+    let pixels = &ppm[ppm.len() - 3 * 2 * 3..];
+    assert_eq!(&pixels[..6], &[0, 0, 0, 0, 255, 0]);
+}
+
+#[test]
+fn reorders_the_channels_of_each_pixel() {
+    let frame = RgbImage::from_pixel(1, 1, Rgb([10, 20, 30]));
+
+    for (channel_order, expected) in [
+        (ChannelOrder::Rgb, [10u8, 20, 30]),
+        (ChannelOrder::Bgr, [30, 20, 10]),
+        (ChannelOrder::Grb, [20, 10, 30]),
+    ] {
+        let opts = ConversionOptions {
+            channel_order,
+            ..Default::default()
+        };
+        let ppm =
+            get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(frame.clone()), &opts).unwrap();
+        assert_eq!(pixel_data(&ppm, 1, 1), &expected);
+    }
+}
+
+/// Returns an image whose pixels encode their own position: red is x, green is y
+fn position_grid(width: u32, height: u32) -> RgbImage {
+    RgbImage::from_fn(width, height, |x, y| Rgb([x as u8, y as u8, 0]))
+}
+
+#[test]
+fn rotates_and_flips_frames() {
+    // the top left pixel of the converted frame for each orientation of a 3x2 frame:
+    for (orientation, dimensions, top_left) in [
+        (Orientation::None, (3, 2), [0, 0, 0]),
+        (Orientation::Rotate90, (2, 3), [0, 1, 0]),
+        (Orientation::Rotate180, (3, 2), [2, 1, 0]),
+        (Orientation::Rotate270, (2, 3), [2, 0, 0]),
+        (Orientation::FlipH, (3, 2), [2, 0, 0]),
+        (Orientation::FlipV, (3, 2), [0, 1, 0]),
+    ] {
+        let opts = ConversionOptions {
+            orientation,
+            ..Default::default()
+        };
+
+        let ppm = get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(position_grid(3, 2)), &opts)
+            .unwrap();
+        validate_ppm(&ppm, dimensions.0, dimensions.1).unwrap();
+        assert_eq!(
+            pixel_data(&ppm, dimensions.0, dimensions.1)[..3],
+            top_left,
+            "{:?}",
+            orientation
+        );
+    }
+}
+
+#[test]
+fn resizes_rotated_frames_to_the_swapped_target_size() {
+    let opts = ConversionOptions {
+        target_size: Some((2, 4)),
+        orientation: Orientation::Rotate90,
+        ..Default::default()
+    };
+    assert_eq!(opts.orientation.source_dimensions((2, 4)), (4, 2));
+
+    let ppm =
+        get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(RgbImage::new(8, 4)), &opts).unwrap();
+    validate_ppm(&ppm, 2, 4).unwrap();
+}
+
+#[test]
+fn mirrors_the_odd_rows_of_each_serpentine_module() {
+    // two modules of 2x2 pixels side by side:
+    let remapped = remap_serpentine(&position_grid(4, 2), 2, 2);
+
+    let row = |y| {
+        (0..4)
+            .map(|x| remapped.get_pixel(x, y)[0])
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(row(0), vec![0, 1, 2, 3]);
+    assert_eq!(row(1), vec![1, 0, 3, 2]);
+}
+
+#[test]
+fn serpentine_remap_is_its_own_inverse() {
+    // modules clipped at the right and bottom edge:
+    let grid = position_grid(7, 5);
+    for (module_width, module_height) in [(7, 5), (2, 2), (3, 4), (1, 1)] {
+        let remapped = remap_serpentine(&grid, module_width, module_height);
+        assert_eq!(
+            remap_serpentine(&remapped, module_width, module_height),
+            grid,
+            "remapping {}x{} modules twice changed the image",
+            module_width,
+            module_height
+        );
+    }
+}
+
+#[test]
+fn crops_frames_to_the_current_bounds_of_the_tracked_element() {
+    let element = TrackedElement::new("#widget");
+    let opts = ConversionOptions {
+        capture_element: Some(element.clone()),
+        ..Default::default()
+    };
+    let frame = || DynamicImage::ImageRgb8(position_grid(8, 6));
+
+    // the element is not on the page yet, the full frame is converted:
+    let ppm = get_ppm_from_dynamic_image(frame(), &opts).unwrap();
+    validate_ppm(&ppm, 8, 6).unwrap();
+
+    element.set_bounds(Some((2, 1, 3, 2)));
+    let ppm = get_ppm_from_dynamic_image(frame(), &opts).unwrap();
+    assert_eq!(pixel_data(&ppm, 3, 2)[..3], [2, 1, 0]);
+
+    // partially outside of the frame, e.g. after scrolling:
+    element.set_bounds(Some((6, 4, 5, 5)));
+    let ppm = get_ppm_from_dynamic_image(frame(), &opts).unwrap();
+    validate_ppm(&ppm, 2, 2).unwrap();
+}
+
+#[test]
+fn crops_frames_before_resizing_them() {
+    let opts = ConversionOptions {
+        crop: Some((
+            Dimension::Pixels(2),
+            Dimension::Pixels(1),
+            Dimension::Pixels(2),
+            Dimension::Pixels(2),
+        )),
+        target_size: Some((4, 4)),
+        resize_filter: FilterType::Nearest,
+        ..Default::default()
+    };
+
+    let ppm =
+        get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(position_grid(8, 6)), &opts).unwrap();
+    validate_ppm(&ppm, 4, 4).unwrap();
+    let pixels = pixel_data(&ppm, 4, 4);
+    assert_eq!(pixels[..3], [2, 1, 0]);
+    assert_eq!(pixels[pixels.len() - 3..], [3, 2, 0]);
+}
+
+#[test]
+fn clamps_crops_exceeding_the_frame() {
+    let opts = ConversionOptions {
+        crop: Some((
+            Dimension::Pixels(6),
+            Dimension::Pixels(4),
+            Dimension::Pixels(5),
+            Dimension::Pixels(5),
+        )),
+        ..Default::default()
+    };
+
+    let ppm =
+        get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(position_grid(8, 6)), &opts).unwrap();
+    validate_ppm(&ppm, 2, 2).unwrap();
+    assert_eq!(pixel_data(&ppm, 2, 2)[..3], [6, 4, 0]);
+}
+
+#[test]
+fn resolves_percentage_crops_against_each_frame() {
+    let opts = ConversionOptions {
+        crop: Some((
+            Dimension::Percent(50.0),
+            Dimension::Pixels(1),
+            Dimension::Percent(25.0),
+            Dimension::Percent(50.0),
+        )),
+        ..Default::default()
+    };
+
+    let ppm =
+        get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(position_grid(8, 6)), &opts).unwrap();
+    validate_ppm(&ppm, 2, 3).unwrap();
+    assert_eq!(pixel_data(&ppm, 2, 3)[..3], [4, 1, 0]);
+
+    // the same crop covers the same part of a larger frame:
+    let ppm =
+        get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(position_grid(16, 12)), &opts).unwrap();
+    validate_ppm(&ppm, 4, 6).unwrap();
+}
+
+#[test]
+fn rejects_crops_without_any_pixel_of_the_frame() {
+    for crop in [
+        // starts at the right edge of the frame:
+        (
+            Dimension::Percent(100.0),
+            Dimension::Pixels(0),
+            Dimension::Percent(10.0),
+            Dimension::Percent(100.0),
+        ),
+        (
+            Dimension::Pixels(0),
+            Dimension::Pixels(0),
+            Dimension::Pixels(0),
+            Dimension::Pixels(4),
+        ),
+    ] {
+        let opts = ConversionOptions {
+            crop: Some(crop),
+            target_size: Some((4, 3)),
+            ..Default::default()
+        };
+
+        let result =
+            get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(position_grid(8, 6)), &opts);
+        assert!(
+            matches!(result, Err(FtError::Convert(_))),
+            "{:?} was not rejected",
+            crop
+        );
+    }
+}
+
+#[test]
+fn parses_dimensions_in_pixels_and_percent() {
+    assert_eq!("32".parse::<Dimension>(), Ok(Dimension::Pixels(32)));
+    assert_eq!(" 12.5% ".parse::<Dimension>(), Ok(Dimension::Percent(12.5)));
+    assert!("101%".parse::<Dimension>().is_err());
+    assert!("-1".parse::<Dimension>().is_err());
+    assert_eq!(Dimension::Percent(50.0).resolve(45), 23);
+}
+
+#[test]
+fn rejects_frames_of_unexpected_dimensions_in_strict_mode() {
+    let opts = ConversionOptions {
+        target_size: Some((8, 6)),
+        strict_dimensions: true,
+        ..Default::default()
+    };
+    let mut converter = PpmConverter::new(ScreencastImageFormat::Png, opts.clone());
+
+    let ppm = converter.convert(&png_frame(position_grid(8, 6))).unwrap();
+    validate_ppm(ppm, 8, 6).unwrap();
+
+    let err = converter
+        .convert(&png_frame(position_grid(16, 12)))
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        FtError::UnexpectedDimensions {
+            expected: (8, 6),
+            actual: (16, 12)
+        }
+    ));
+    assert!(err.to_string().contains("16x12"));
+    assert!(!err.is_decode_error());
+
+    // only screencast frames are checked, other images are still resized:
+    let ppm =
+        get_ppm_from_dynamic_image(DynamicImage::ImageRgb8(position_grid(16, 12)), &opts).unwrap();
+    validate_ppm(&ppm, 8, 6).unwrap();
+}
+
+#[test]
+fn only_keeps_the_last_image_if_enabled() {
+    let frame = png_frame(RgbImage::new(4, 2));
+    let mut converter = PpmConverter::new(ScreencastImageFormat::Png, ConversionOptions::default());
+
+    converter.convert(&frame).unwrap();
+    assert!(converter.last_image().is_none());
+
+    converter.set_keep_last_image(true);
+    converter.convert(&frame).unwrap();
+    assert_eq!(converter.last_image().unwrap().dimensions(), (4, 2));
+}
+
+/// Returns the given image as base64 encoded PNG, like the frames of a screencast.
+fn png_frame(image: RgbImage) -> String {
+    let mut png = Vec::new();
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .unwrap();
+    base64::encode(&png)
+}
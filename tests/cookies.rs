@@ -0,0 +1,58 @@
+use flaschentaschen_web::cookies::{parse_cookies_file, Cookie};
+
+#[test]
+fn parses_cookie_with_attributes() {
+    let cookie =
+        Cookie::parse("session=abc=123; Domain=example.com; path=/app; Secure; HttpOnly").unwrap();
+    assert_eq!(
+        cookie,
+        Cookie {
+            name: String::from("session"),
+            value: String::from("abc=123"),
+            domain: Some(String::from("example.com")),
+            path: Some(String::from("/app")),
+            secure: true,
+            http_only: true,
+            expires: None,
+        }
+    );
+}
+
+#[test]
+fn rejects_invalid_cookies_without_value_in_error() {
+    let err = Cookie::parse("session=secret; color=blue").unwrap_err();
+    assert!(!err.to_string().contains("secret"));
+    assert!(Cookie::parse("=secret").is_err());
+    assert!(Cookie::parse("no-value").is_err());
+}
+
+#[test]
+fn parses_netscape_cookies_file() {
+    let contents = "# Netscape HTTP Cookie File\n\
+        \n\
+        .example.com\tTRUE\t/\tTRUE\t1700000000\tsession\tsecret\n\
+        #HttpOnly_example.com\tFALSE\t/app\tFALSE\t0\ttoken\tabc\n";
+    let cookies = parse_cookies_file(contents).unwrap();
+
+    assert_eq!(cookies.len(), 2);
+    assert_eq!(cookies[0].domain.as_deref(), Some(".example.com"));
+    assert_eq!(cookies[0].value, "secret");
+    assert!(cookies[0].secure);
+    assert_eq!(cookies[0].expires, Some(1700000000.0));
+    assert_eq!(cookies[1].name, "token");
+    assert!(cookies[1].http_only);
+    assert_eq!(cookies[1].expires, None);
+}
+
+#[test]
+fn parses_json_cookies_file() {
+    let contents = r#"[
+        {"name": "session", "value": "secret", "domain": "example.com", "httpOnly": true, "expirationDate": 1700000000.5, "hostOnly": true}
+    ]"#;
+    let cookies = parse_cookies_file(contents).unwrap();
+
+    assert_eq!(cookies.len(), 1);
+    assert_eq!(cookies[0].name, "session");
+    assert!(cookies[0].http_only);
+    assert_eq!(cookies[0].expires, Some(1700000000.5));
+}
@@ -0,0 +1,64 @@
+use flaschentaschen_web::diff::{diff_regions, Rect};
+use image::{Rgb, RgbImage};
+
+#[test]
+fn identical_images_have_no_regions() {
+    let image = RgbImage::from_pixel(32, 16, Rgb([10, 20, 30]));
+    assert!(diff_regions(&image, &image.clone(), 8).is_empty());
+}
+
+#[test]
+fn single_pixel_change_produces_one_block() {
+    let prev = RgbImage::new(32, 16);
+    let mut next = prev.clone();
+    next.put_pixel(10, 3, Rgb([255, 0, 0]));
+
+    let regions = diff_regions(&prev, &next, 8);
+
+    assert_eq!(regions.len(), 1);
+    let (rect, contents) = &regions[0];
+    assert_eq!(
+        *rect,
+        Rect {
+            x: 8,
+            y: 0,
+            width: 8,
+            height: 8
+        }
+    );
+    assert_eq!(contents.dimensions(), (8, 8));
+    assert_eq!(*contents.get_pixel(2, 3), Rgb([255, 0, 0]));
+}
+
+#[test]
+fn adjacent_blocks_are_merged_and_clipped() {
+    let prev = RgbImage::new(20, 10);
+    let mut next = prev.clone();
+    next.put_pixel(9, 9, Rgb([0, 255, 0]));
+    next.put_pixel(19, 9, Rgb([0, 0, 255]));
+
+    let regions = diff_regions(&prev, &next, 8);
+
+    // the pixels are in the blocks 8..16 and 16..20 of the last, 2 pixel high block row:
+    let rects: Vec<Rect> = regions.iter().map(|(rect, _)| *rect).collect();
+    assert_eq!(
+        rects,
+        vec![Rect {
+            x: 8,
+            y: 8,
+            width: 12,
+            height: 2
+        }]
+    );
+}
+
+#[test]
+fn different_dimensions_produce_the_whole_image() {
+    let prev = RgbImage::new(8, 8);
+    let next = RgbImage::new(16, 8);
+
+    let regions = diff_regions(&prev, &next, 4);
+
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].1.dimensions(), (16, 8));
+}
@@ -0,0 +1,47 @@
+use flaschentaschen_web::error::FtError;
+use std::error::Error;
+use std::io;
+
+#[test]
+fn classifies_decode_and_convert_errors_as_decode_errors() {
+    assert!(FtError::Decode("corrupt frame".into()).is_decode_error());
+    assert!(FtError::Convert("invalid dimensions".into()).is_decode_error());
+}
+
+#[test]
+fn does_not_classify_send_errors_as_decode_errors() {
+    let send_error = FtError::Send {
+        address: String::from("localhost:1337"),
+        source: io::Error::new(io::ErrorKind::Other, "network is unreachable"),
+    };
+    assert!(!send_error.is_decode_error());
+    assert!(!FtError::ConnectionRefused {
+        address: String::from("localhost:1337")
+    }
+    .is_decode_error());
+}
+
+#[test]
+fn does_not_classify_frames_which_cannot_be_sent_as_decode_errors() {
+    assert!(!FtError::Unsendable("expected a P6 PPM".into()).is_decode_error());
+}
+
+#[test]
+fn keeps_io_errors_as_source() {
+    let io_error = FtError::Io(io::Error::new(io::ErrorKind::WriteZero, "buffer full"));
+    let source = io_error.source().unwrap();
+    assert_eq!(source.to_string(), "buffer full");
+    assert!(!io_error.is_decode_error());
+}
+
+#[test]
+fn describes_socket_errors_independently_of_the_transport() {
+    let err = FtError::SocketBind {
+        address: String::from("tcp://localhost:1337"),
+        source: io::Error::new(io::ErrorKind::TimedOut, "timed out"),
+    };
+    assert_eq!(
+        err.to_string(),
+        "failed to connect to tcp://localhost:1337: timed out"
+    );
+}
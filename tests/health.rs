@@ -0,0 +1,86 @@
+use flaschentaschen_web::frame_latency;
+use flaschentaschen_web::health::{is_healthy, render_metrics};
+use flaschentaschen_web::stats::Stats;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[test]
+fn is_unhealthy_before_the_first_frame() {
+    let stats = Stats::new();
+    assert!(!is_healthy(&stats, Duration::from_secs(60)));
+}
+
+#[test]
+fn is_healthy_after_a_recent_frame() {
+    let stats = Stats::new();
+    stats.record_sent_frame(100);
+    assert!(is_healthy(&stats, Duration::from_secs(60)));
+}
+
+#[test]
+fn renders_counters_in_prometheus_format() {
+    let stats = Stats::new();
+    stats.record_sent_frame(100);
+    stats.record_sent_frame(50);
+
+    let metrics = render_metrics(&stats);
+    assert!(metrics.contains("# TYPE flaschentaschen_web_frames_sent_total counter\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_frames_sent_total 2\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_bytes_sent_total 150\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_errors_total 0\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_last_frame_age_seconds "));
+}
+
+#[test]
+fn renders_current_and_peak_queue_depth() {
+    let stats = Stats::new();
+    stats.record_queue_depth(3);
+    stats.record_queue_depth(1);
+
+    let metrics = render_metrics(&stats);
+    assert!(metrics.contains("# TYPE flaschentaschen_web_queue_depth gauge\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_queue_depth 1\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_peak_queue_depth 3\n"));
+}
+
+#[test]
+fn renders_nan_frame_age_before_the_first_frame() {
+    let metrics = render_metrics(&Stats::new());
+    assert!(metrics.contains("flaschentaschen_web_last_frame_age_seconds NaN\n"));
+}
+
+#[test]
+fn renders_frame_latencies() {
+    let stats = Stats::new();
+    stats.record_frame_latency(Duration::from_millis(20));
+    stats.record_frame_latency(Duration::from_millis(40));
+
+    let metrics = render_metrics(&stats);
+    assert!(metrics.contains("# TYPE flaschentaschen_web_frame_latency_seconds summary\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_frame_latency_seconds_sum 0.06\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_frame_latency_seconds_count 2\n"));
+    assert!(metrics.contains("\nflaschentaschen_web_max_frame_latency_seconds 0.04\n"));
+}
+
+#[test]
+fn averages_frame_latencies_since_a_snapshot() {
+    let stats = Stats::new();
+    stats.record_frame_latency(Duration::from_millis(100));
+    let previous = stats.snapshot();
+    assert_eq!(stats.snapshot().average_latency_since(&previous), None);
+
+    stats.record_frame_latency(Duration::from_millis(10));
+    stats.record_frame_latency(Duration::from_millis(30));
+    assert_eq!(
+        stats.snapshot().average_latency_since(&previous),
+        Some(Duration::from_millis(20))
+    );
+}
+
+#[test]
+fn computes_frame_latency_from_capture_timestamp() {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    let latency = frame_latency(now.as_secs_f64() - 0.5).unwrap();
+    assert!(latency >= Duration::from_millis(500) && latency < Duration::from_secs(5));
+    assert_eq!(frame_latency(now.as_secs_f64() + 60.0), None);
+    assert_eq!(frame_latency(f64::NAN), None);
+}
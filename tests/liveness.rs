@@ -0,0 +1,73 @@
+use flaschentaschen_web::control::ScreencastControl;
+use flaschentaschen_web::liveness::spawn_liveness_probe;
+use flaschentaschen_web::{FlaschenTaschenGroup, ShutdownReason, SIZE_QUERY};
+use std::net::UdpSocket;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const PROBE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Binds a fake server answering the first `answers` size queries and returns its address.
+fn start_answering_server(answers: usize) -> String {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = socket.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let mut buffer = [0u8; 64];
+        let mut answered = 0;
+        while let Ok((len, sender)) = socket.recv_from(&mut buffer) {
+            if &buffer[..len] == SIZE_QUERY && answered < answers {
+                socket.send_to(b"64x32", sender).unwrap();
+                answered += 1;
+            }
+        }
+    });
+    address
+}
+
+fn group(addresses: Vec<String>) -> Arc<FlaschenTaschenGroup> {
+    Arc::new(FlaschenTaschenGroup::new(addresses).unwrap())
+}
+
+#[test]
+fn trips_once_a_server_stops_answering() {
+    let flaschentaschen = group(vec![start_answering_server(3)]);
+    let (sender, receiver) = mpsc::channel();
+    let control = Arc::new(ScreencastControl::new());
+
+    spawn_liveness_probe(flaschentaschen, PROBE_INTERVAL, 2, sender, control.clone());
+
+    assert_eq!(
+        receiver.recv_timeout(Duration::from_secs(10)),
+        Ok(ShutdownReason::ServerUnresponsive)
+    );
+    control.stop();
+}
+
+#[test]
+fn does_not_trip_while_another_server_answers() {
+    let flaschentaschen = group(vec![
+        start_answering_server(3),
+        start_answering_server(usize::MAX),
+    ]);
+    let (sender, receiver) = mpsc::channel();
+    let control = Arc::new(ScreencastControl::new());
+
+    spawn_liveness_probe(flaschentaschen, PROBE_INTERVAL, 2, sender, control.clone());
+
+    assert!(receiver.recv_timeout(PROBE_INTERVAL * 20).is_err());
+    control.stop();
+}
+
+#[test]
+fn does_not_probe_servers_without_size_query_support() {
+    let flaschentaschen = group(vec![start_answering_server(0)]);
+    let (sender, receiver) = mpsc::channel();
+    let control = Arc::new(ScreencastControl::new());
+
+    spawn_liveness_probe(flaschentaschen, PROBE_INTERVAL, 1, sender, control.clone());
+
+    control.stop();
+    assert!(receiver.try_recv().is_err());
+}
@@ -0,0 +1,34 @@
+use flaschentaschen_web::pattern::{corner_markers, diagonal_gradient, solid, test_patterns};
+use image::Rgb;
+
+#[test]
+fn creates_solid_patterns() {
+    let pattern = solid(4, 2, Rgb([1, 2, 3]));
+    assert_eq!(pattern.dimensions(), (4, 2));
+    assert!(pattern.pixels().all(|pixel| *pixel == Rgb([1, 2, 3])));
+}
+
+#[test]
+fn creates_diagonal_gradient_from_black_to_white() {
+    let pattern = diagonal_gradient(64, 32);
+    assert_eq!(*pattern.get_pixel(0, 0), Rgb([0, 0, 0]));
+    assert_eq!(*pattern.get_pixel(63, 31), Rgb([255, 255, 255]));
+    assert!(pattern.get_pixel(10, 10)[0] < pattern.get_pixel(20, 20)[0]);
+}
+
+#[test]
+fn marks_each_corner_with_a_distinct_color() {
+    let pattern = corner_markers(64, 32);
+    assert_eq!(*pattern.get_pixel(0, 0), Rgb([255, 0, 0]));
+    assert_eq!(*pattern.get_pixel(63, 0), Rgb([0, 255, 0]));
+    assert_eq!(*pattern.get_pixel(0, 31), Rgb([0, 0, 255]));
+    assert_eq!(*pattern.get_pixel(63, 31), Rgb([255, 255, 255]));
+    assert_eq!(*pattern.get_pixel(32, 16), Rgb([0, 0, 0]));
+}
+
+#[test]
+fn handles_single_pixel_screens() {
+    for (_, pattern) in test_patterns(1, 1) {
+        assert_eq!(pattern.dimensions(), (1, 1));
+    }
+}
@@ -0,0 +1,66 @@
+use flaschentaschen_web::playlist::{parse_duration, parse_playlist, PlaylistEntry};
+use std::time::Duration;
+
+#[test]
+fn parses_durations() {
+    assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+    assert_eq!(parse_duration("30s").unwrap(), Duration::from_secs(30));
+    assert_eq!(parse_duration("30").unwrap(), Duration::from_secs(30));
+    assert_eq!(parse_duration("2m").unwrap(), Duration::from_secs(120));
+    assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+}
+
+#[test]
+fn parses_compound_durations() {
+    assert_eq!(
+        parse_duration("1h30m").unwrap(),
+        Duration::from_secs(90 * 60)
+    );
+    assert_eq!(
+        parse_duration(" 1h 30m ").unwrap(),
+        Duration::from_secs(90 * 60)
+    );
+    assert_eq!(
+        parse_duration("1s 500ms").unwrap(),
+        Duration::from_millis(1500)
+    );
+    // a number without unit is only unambiguous on its own:
+    assert!(parse_duration("1h 30").is_err());
+    assert!(parse_duration("1.5h").is_err());
+}
+
+#[test]
+fn rejects_invalid_durations() {
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("0s").is_err());
+    assert!(parse_duration("5d").is_err());
+    assert!(parse_duration("s").is_err());
+    // the seconds overflow a u64:
+    assert!(parse_duration("9999999999999999h").is_err());
+}
+
+#[test]
+fn parses_playlist() {
+    let playlist =
+        parse_playlist("https://example.com=30s, https://example.org/?page=2=1m").unwrap();
+    assert_eq!(
+        playlist,
+        vec![
+            PlaylistEntry {
+                url: String::from("https://example.com"),
+                duration: Duration::from_secs(30),
+            },
+            PlaylistEntry {
+                url: String::from("https://example.org/?page=2"),
+                duration: Duration::from_secs(60),
+            },
+        ]
+    );
+}
+
+#[test]
+fn rejects_invalid_playlists() {
+    assert!(parse_playlist("").is_err());
+    assert!(parse_playlist("https://example.com").is_err());
+    assert!(parse_playlist("=30s").is_err());
+}
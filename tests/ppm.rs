@@ -0,0 +1,77 @@
+use flaschentaschen_web::insert_ft_offset;
+use flaschentaschen_web::ppm::{binary_ppm_size, parse_ppm_header, solid_ppm, validate_ppm};
+use image::Rgb;
+
+#[test]
+fn parses_header() {
+    assert_eq!(
+        parse_ppm_header(b"P6\n64 32\n255\n").unwrap(),
+        (64, 32, 255)
+    );
+}
+
+#[test]
+fn parses_header_with_comments() {
+    let ppm = b"P6\n# FT: 10 5 1\n64 # width\n32\n# maxval follows\n255\n";
+    assert_eq!(parse_ppm_header(ppm).unwrap(), (64, 32, 255));
+}
+
+#[test]
+fn rejects_wrong_magic() {
+    assert!(parse_ppm_header(b"P3\n64 32\n255\n").is_err());
+    assert!(parse_ppm_header(b"").is_err());
+}
+
+#[test]
+fn rejects_missing_max_value() {
+    assert!(parse_ppm_header(b"P6\n64 32\n").is_err());
+    assert!(parse_ppm_header(b"P6\n64 32 # no maxval\n").is_err());
+}
+
+#[test]
+fn rejects_invalid_fields() {
+    assert!(parse_ppm_header(b"P6\n64 x32\n255\n").is_err());
+    assert!(parse_ppm_header(b"P6\n0 32\n255\n").is_err());
+    assert!(parse_ppm_header(b"P6\n64 32\n70000\n").is_err());
+    assert!(parse_ppm_header(b"P6\n64 32\n255").is_err());
+}
+
+#[test]
+fn validates_dimensions_and_data_length() {
+    let mut ppm = b"P6\n2 1\n255\n".to_vec();
+    ppm.extend_from_slice(&[255, 0, 0, 0, 0, 255]);
+
+    assert!(validate_ppm(&ppm, 2, 1).is_ok());
+    assert!(validate_ppm(&ppm, 1, 2).is_err());
+    assert!(validate_ppm(&ppm[..ppm.len() - 1], 2, 1).is_err());
+}
+
+#[test]
+fn creates_solid_ppm() {
+    let ppm = solid_ppm(2, 1, Rgb([1, 2, 3]));
+    assert_eq!(ppm, b"P6\n2 1\n255\n\x01\x02\x03\x01\x02\x03".to_vec());
+    assert!(validate_ppm(&ppm, 2, 1).is_ok());
+}
+
+#[test]
+fn computes_the_size_of_binary_ppms() {
+    assert_eq!(binary_ppm_size(2, 1), 17);
+    assert_eq!(
+        binary_ppm_size(45, 35),
+        solid_ppm(45, 35, Rgb([0, 0, 0])).len()
+    );
+}
+
+#[test]
+fn inserts_the_offset_comment_into_the_header() {
+    let mut ppm = b"P6\n2 1\n255\n".to_vec();
+    ppm.extend_from_slice(&[255, 0, 0, 0, 0, 255]);
+
+    let with_offset = insert_ft_offset(&ppm, 10, 5, 2).unwrap();
+
+    // the comment follows the magic, the header and the pixel data stay unchanged after it:
+    let mut expected = b"P6\n# FT: 10 5 2\n2 1\n255\n".to_vec();
+    expected.extend_from_slice(&[255, 0, 0, 0, 0, 255]);
+    assert_eq!(with_offset, expected);
+    assert!(validate_ppm(&with_offset, 2, 1).is_ok());
+}
@@ -0,0 +1,22 @@
+use flaschentaschen_web::adapted_jpeg_quality;
+
+#[test]
+fn lowers_the_quality_while_many_frames_are_dropped() {
+    assert_eq!(adapted_jpeg_quality(80, (30, 90), 0.5), 70);
+    // never below the min:
+    assert_eq!(adapted_jpeg_quality(35, (30, 90), 0.5), 30);
+    assert_eq!(adapted_jpeg_quality(30, (30, 90), 1.0), 30);
+}
+
+#[test]
+fn raises_the_quality_slowly_while_hardly_any_frames_are_dropped() {
+    assert_eq!(adapted_jpeg_quality(70, (30, 90), 0.0), 75);
+    assert_eq!(adapted_jpeg_quality(70, (30, 90), 0.01), 75);
+    // never above the max:
+    assert_eq!(adapted_jpeg_quality(88, (30, 90), 0.0), 90);
+}
+
+#[test]
+fn keeps_the_quality_at_moderate_drop_rates() {
+    assert_eq!(adapted_jpeg_quality(70, (30, 90), 0.05), 70);
+}
@@ -0,0 +1,43 @@
+use flaschentaschen_web::queue::FrameQueue;
+use std::sync::Arc;
+use std::thread;
+
+#[test]
+fn drops_the_oldest_item_once_full() {
+    let queue = FrameQueue::new(2);
+    assert_eq!(queue.push(1), None);
+    assert_eq!(queue.push(2), None);
+    assert_eq!(queue.push(3), Some(1));
+    assert_eq!(queue.len(), 2);
+
+    assert_eq!(queue.pop(), Some(2));
+    assert_eq!(queue.pop(), Some(3));
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn holds_at_least_one_item() {
+    let queue = FrameQueue::new(0);
+    assert_eq!(queue.push(1), None);
+    assert_eq!(queue.push(2), Some(1));
+    assert_eq!(queue.pop(), Some(2));
+}
+
+#[test]
+fn clears_the_waiting_items() {
+    let queue = FrameQueue::new(4);
+    queue.push(1);
+    queue.push(2);
+    assert_eq!(queue.clear(), 2);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn wakes_up_blocked_consumers_when_closed() {
+    let queue: Arc<FrameQueue<u32>> = Arc::new(FrameQueue::new(1));
+    let consumer_queue = queue.clone();
+    let consumer = thread::spawn(move || consumer_queue.pop());
+
+    queue.close();
+    assert_eq!(consumer.join().unwrap(), None);
+}
@@ -0,0 +1,47 @@
+use flaschentaschen_web::RateLimiter;
+use std::time::{Duration, Instant};
+
+#[test]
+fn allows_bursts_up_to_the_burst_size() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::with_burst(1000, 3000);
+
+    assert!(limiter.try_acquire_at(1000, start));
+    assert!(limiter.try_acquire_at(1000, start));
+    assert!(limiter.try_acquire_at(1000, start));
+    assert!(!limiter.try_acquire_at(1000, start));
+}
+
+#[test]
+fn refills_at_the_steady_state_rate() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::new(1000);
+    assert!(limiter.try_acquire_at(1000, start));
+
+    // 100 bytes are available every 100ms:
+    for i in 1..=10 {
+        let now = start + Duration::from_millis(100 * i);
+        assert!(limiter.try_acquire_at(100, now));
+        assert!(!limiter.try_acquire_at(100, now));
+    }
+}
+
+#[test]
+fn does_not_refill_beyond_the_burst_size() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::with_burst(1000, 500);
+    let later = start + Duration::from_secs(10);
+
+    assert!(limiter.try_acquire_at(500, later));
+    assert!(!limiter.try_acquire_at(1, later));
+}
+
+#[test]
+fn allows_sends_larger_than_the_burst_size_with_a_full_bucket() {
+    let start = Instant::now();
+    let mut limiter = RateLimiter::new(1000);
+
+    assert!(limiter.try_acquire_at(5000, start));
+    assert!(!limiter.try_acquire_at(5000, start + Duration::from_millis(500)));
+    assert!(limiter.try_acquire_at(5000, start + Duration::from_secs(1)));
+}
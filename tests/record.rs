@@ -0,0 +1,121 @@
+mod common;
+
+use common::temp_path;
+use flaschentaschen_web::insert_ft_offset;
+use flaschentaschen_web::record::{FileSink, FtSink, RecordedFrame, Recording};
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// A binary PPM of 1x1 pixels of the given gray value
+fn gray_ppm(value: u8) -> Vec<u8> {
+    let mut ppm = b"P6\n1 1\n255\n".to_vec();
+    ppm.extend_from_slice(&[value, value, value]);
+    ppm
+}
+
+/// Records three frames 20ms apart into the given path and returns the replayed frames.
+fn record_and_replay(path: &PathBuf) -> Vec<RecordedFrame> {
+    let sink = FileSink::create(path).unwrap();
+    for value in [0, 127, 255] {
+        sink.send_frame(&gray_ppm(value)).unwrap();
+        thread::sleep(Duration::from_millis(20));
+    }
+    Recording::open(path)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap()
+}
+
+fn assert_replayed_in_order(frames: &[RecordedFrame]) {
+    let ppms: Vec<Vec<u8>> = frames.iter().map(|frame| frame.ppm.clone()).collect();
+    assert_eq!(ppms, vec![gray_ppm(0), gray_ppm(127), gray_ppm(255)]);
+    assert!(frames
+        .windows(2)
+        .all(|pair| pair[1].elapsed >= pair[0].elapsed + Duration::from_millis(20)));
+}
+
+#[test]
+fn replays_a_recording_file() {
+    let path = temp_path("recording.ftrec");
+
+    let frames = record_and_replay(&path);
+
+    fs::remove_file(&path).unwrap();
+    assert_replayed_in_order(&frames);
+}
+
+#[test]
+fn replays_a_recording_directory() {
+    let path = temp_path("recording");
+    fs::create_dir_all(&path).unwrap();
+    // unrelated files are ignored:
+    fs::write(path.join("notes.txt"), "not a frame").unwrap();
+
+    let frames = record_and_replay(&path);
+
+    fs::remove_dir_all(&path).unwrap();
+    assert_replayed_in_order(&frames);
+}
+
+#[test]
+fn rejects_corrupt_recordings() {
+    let path = temp_path("corrupt.ftrec");
+    fs::write(&path, b"FTFRAME 0 100\nP6\n1 1\n255\n").unwrap();
+
+    let frames: Vec<_> = Recording::open(&path).unwrap().collect();
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert!(frames[0].is_err());
+}
+
+#[test]
+fn keeps_frames_recorded_within_the_same_millisecond() {
+    let path = temp_path("burst");
+    fs::create_dir_all(&path).unwrap();
+    let sink = FileSink::create(&path).unwrap();
+
+    for value in 0..10 {
+        sink.send_frame(&gray_ppm(value)).unwrap();
+    }
+    let frames = Recording::open(&path)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    fs::remove_dir_all(&path).unwrap();
+    let ppms: Vec<Vec<u8>> = frames.into_iter().map(|frame| frame.ppm).collect();
+    assert_eq!(ppms, (0..10).map(gray_ppm).collect::<Vec<_>>());
+}
+
+#[test]
+fn records_the_offset_of_frames() {
+    let path = temp_path("offset.ftrec");
+    let sink = FileSink::create(&path).unwrap();
+
+    sink.send_frame_at(&gray_ppm(127), 2, 3, 4).unwrap();
+    let frames = Recording::open(&path)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        frames[0].ppm,
+        insert_ft_offset(&gray_ppm(127), 2, 3, 4).unwrap()
+    );
+}
+
+#[test]
+fn rejects_frames_larger_than_a_datagram_in_recordings() {
+    let path = temp_path("oversized.ftrec");
+    fs::write(&path, b"FTFRAME 0 18446744073709551615\n").unwrap();
+
+    let frames: Vec<_> = Recording::open(&path).unwrap().collect();
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert!(frames[0].is_err());
+}
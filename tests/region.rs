@@ -0,0 +1,34 @@
+use flaschentaschen_web::region::RegionSpec;
+
+#[test]
+fn parses_regions() {
+    assert_eq!(
+        RegionSpec::parse("32, 0, 32, 16=https://example.com/?page=2").unwrap(),
+        RegionSpec {
+            url: String::from("https://example.com/?page=2"),
+            x: 32,
+            y: 0,
+            width: 32,
+            height: 16,
+        }
+    );
+}
+
+#[test]
+fn rejects_invalid_regions() {
+    assert!(RegionSpec::parse("https://example.com").is_err());
+    assert!(RegionSpec::parse("0,0,32=https://example.com").is_err());
+    assert!(RegionSpec::parse("0,0,0,32=https://example.com").is_err());
+    assert!(RegionSpec::parse("0,0,32,32=").is_err());
+    assert!(RegionSpec::parse("-1,0,32,32=https://example.com").is_err());
+}
+
+#[test]
+fn checks_whether_regions_fit_into_the_screen() {
+    let region = RegionSpec::parse("32,16,32,16=https://example.com").unwrap();
+    assert!(region.fits_into((64, 32)));
+    assert!(!region.fits_into((63, 32)));
+    assert!(!RegionSpec::parse("4294967295,0,1,1=https://example.com")
+        .unwrap()
+        .fits_into((64, 32)));
+}
@@ -0,0 +1,325 @@
+use flaschentaschen_web::error::FtError;
+use flaschentaschen_web::ppm::solid_ppm;
+use flaschentaschen_web::record::{FtSink, UdpSink};
+use flaschentaschen_web::testing::MockFtServer;
+use flaschentaschen_web::{get_ppm_from_dynamic_image, insert_ft_offset, ConversionOptions};
+use flaschentaschen_web::{FlaschenTaschenGroup, OutputProtocol, MAX_DATAGRAM_SIZE};
+use image::{DynamicImage, Rgb, RgbImage};
+use std::time::Duration;
+
+const RECEIVE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A binary PPM of 2x1 pixels: red, blue
+fn test_ppm() -> Vec<u8> {
+    let mut ppm = b"P6\n2 1\n255\n".to_vec();
+    ppm.extend_from_slice(&[255, 0, 0, 0, 0, 255]);
+    ppm
+}
+
+#[test]
+fn sends_ppm_unchanged() {
+    let server = MockFtServer::start().unwrap();
+    let flaschentaschen = server.flaschentaschen().unwrap();
+    let ppm = test_ppm();
+
+    let sent = flaschentaschen.send_ppm(&ppm).unwrap();
+
+    assert_eq!(sent, ppm.len());
+    assert_eq!(server.wait_for_datagrams(1, RECEIVE_TIMEOUT), vec![ppm]);
+}
+
+#[test]
+fn sends_ppm_to_all_group_members() {
+    let servers = [
+        MockFtServer::start().unwrap(),
+        MockFtServer::start().unwrap(),
+    ];
+    let group = FlaschenTaschenGroup::new(
+        servers
+            .iter()
+            .map(|server| server.address().to_string())
+            .collect(),
+    )
+    .unwrap();
+    let ppm = test_ppm();
+
+    for result in group.send_ppm(&ppm) {
+        result.unwrap();
+    }
+
+    for server in servers.iter() {
+        assert_eq!(
+            server.wait_for_datagrams(1, RECEIVE_TIMEOUT),
+            vec![ppm.clone()]
+        );
+    }
+}
+
+#[test]
+fn sinks_send_frames_at_the_given_offset() {
+    let servers = [
+        MockFtServer::start().unwrap(),
+        MockFtServer::start().unwrap(),
+    ];
+    let udp_sink = UdpSink::new(&servers[0].address().to_string()).unwrap();
+    let group = FlaschenTaschenGroup::new(vec![servers[1].address().to_string()]).unwrap();
+    let ppm = test_ppm();
+
+    udp_sink.send_frame_at(&ppm, 2, 3, 4).unwrap();
+    group.send_frame_at(&ppm, 2, 3, 4).unwrap();
+    // frames at the origin are sent unchanged:
+    udp_sink.send_frame(&ppm).unwrap();
+    group.send_frame(&ppm).unwrap();
+
+    let expected = vec![insert_ft_offset(&ppm, 2, 3, 4).unwrap(), ppm];
+    for server in servers.iter() {
+        assert_eq!(server.wait_for_datagrams(2, RECEIVE_TIMEOUT), expected);
+    }
+}
+
+#[test]
+fn sends_to_all_group_members_concurrently_in_member_order() {
+    let servers: Vec<MockFtServer> = (0..5).map(|_| MockFtServer::start().unwrap()).collect();
+    // each member sends a datagram of a distinct size, which identifies its result:
+    let members = servers
+        .iter()
+        .enumerate()
+        .map(|(index, server)| {
+            let mut member = server.flaschentaschen().unwrap();
+            member.pad_datagram = Some(100 + index);
+            member
+        })
+        .collect();
+    let mut group = FlaschenTaschenGroup::from_members(members);
+    group.parallel_sends = 2;
+    let ppm = test_ppm();
+
+    let sent: Vec<usize> = group
+        .send_ppm(&ppm)
+        .into_iter()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(sent, vec![100, 101, 102, 103, 104]);
+    for (index, server) in servers.iter().enumerate() {
+        let received = server.wait_for_datagrams(1, RECEIVE_TIMEOUT);
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].len(), 100 + index);
+    }
+}
+
+#[test]
+fn sends_consecutive_frames_concurrently_in_frame_order() {
+    let servers: Vec<MockFtServer> = (0..4).map(|_| MockFtServer::start().unwrap()).collect();
+    let mut group = FlaschenTaschenGroup::new(
+        servers
+            .iter()
+            .map(|server| server.address().to_string())
+            .collect(),
+    )
+    .unwrap();
+    let frames: Vec<Vec<u8>> = (0..10u8)
+        .map(|value| {
+            let mut ppm = b"P6\n1 1\n255\n".to_vec();
+            ppm.extend_from_slice(&[value, value, value]);
+            ppm
+        })
+        .collect();
+
+    // the sending threads are replaced if the parallelism changes between frames:
+    for (index, frame) in frames.iter().enumerate() {
+        group.parallel_sends = 2 + index % 2;
+        for result in group.send_ppm(frame) {
+            result.unwrap();
+        }
+    }
+
+    for server in servers.iter() {
+        assert_eq!(server.wait_for_datagrams(10, RECEIVE_TIMEOUT), frames);
+    }
+}
+
+/// Returns the PPM of an image of 2x1 pixels: red, blue
+fn converted_test_image() -> Vec<u8> {
+    let mut image = RgbImage::new(2, 1);
+    image.put_pixel(0, 0, Rgb([255, 0, 0]));
+    image.put_pixel(1, 0, Rgb([0, 0, 255]));
+    get_ppm_from_dynamic_image(
+        DynamicImage::ImageRgb8(image),
+        &ConversionOptions::default(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn sends_ppm_protocol_with_header() {
+    let server = MockFtServer::start().unwrap();
+    let mut flaschentaschen = server.flaschentaschen().unwrap();
+    flaschentaschen.output_protocol = OutputProtocol::FlaschenTaschenPpm;
+
+    flaschentaschen.send_ppm(&converted_test_image()).unwrap();
+
+    assert_eq!(
+        server.wait_for_datagrams(1, RECEIVE_TIMEOUT),
+        vec![test_ppm()]
+    );
+}
+
+#[test]
+fn sends_raw_rgb_protocol_without_header() {
+    let server = MockFtServer::start().unwrap();
+    let mut flaschentaschen = server.flaschentaschen().unwrap();
+    flaschentaschen.output_protocol = OutputProtocol::RawRgb;
+
+    let sent = flaschentaschen.send_ppm(&converted_test_image()).unwrap();
+
+    assert_eq!(sent, 6);
+    assert_eq!(
+        server.wait_for_datagrams(1, RECEIVE_TIMEOUT),
+        vec![vec![255, 0, 0, 0, 0, 255]]
+    );
+}
+
+#[test]
+fn rejects_raw_rgb_of_the_wrong_length() {
+    let server = MockFtServer::start().unwrap();
+    let flaschentaschen = server.flaschentaschen().unwrap();
+
+    assert!(matches!(
+        flaschentaschen.send_raw_rgb(&[0; 5], 2, 1),
+        Err(FtError::Unsendable(_))
+    ));
+}
+
+#[test]
+fn rejects_ascii_ppm_at_an_offset_as_unsendable() {
+    let server = MockFtServer::start().unwrap();
+    let flaschentaschen = server.flaschentaschen().unwrap();
+
+    assert!(matches!(
+        flaschentaschen.send_ppm_at(b"P3\n1 1\n255\n0 0 0\n", 1, 1, 0),
+        Err(FtError::Unsendable(_))
+    ));
+}
+
+#[test]
+fn pads_datagrams_to_a_fixed_size() {
+    let server = MockFtServer::start().unwrap();
+    let mut flaschentaschen = server.flaschentaschen().unwrap();
+    flaschentaschen.pad_datagram = Some(32);
+    let ppm = test_ppm();
+
+    let sent = flaschentaschen.send_ppm(&ppm).unwrap();
+
+    let mut padded = ppm.clone();
+    padded.resize(32, 0);
+    assert_eq!(sent, 32);
+    assert_eq!(server.wait_for_datagrams(1, RECEIVE_TIMEOUT), vec![padded]);
+}
+
+#[test]
+fn does_not_pad_datagrams_exceeding_the_fixed_size() {
+    let server = MockFtServer::start().unwrap();
+    let mut flaschentaschen = server.flaschentaschen().unwrap();
+    flaschentaschen.pad_datagram = Some(4);
+    let ppm = test_ppm();
+
+    flaschentaschen.send_ppm(&ppm).unwrap();
+
+    assert_eq!(server.wait_for_datagrams(1, RECEIVE_TIMEOUT), vec![ppm]);
+}
+
+#[test]
+fn rejects_frames_larger_than_a_datagram() {
+    let server = MockFtServer::start().unwrap();
+    let flaschentaschen = server.flaschentaschen().unwrap();
+    // 22,500 bytes per row, the third row exceeds a single datagram:
+    let ppm = solid_ppm(7500, 3, Rgb([255, 0, 0]));
+    assert!(ppm.len() > MAX_DATAGRAM_SIZE);
+
+    let err = flaschentaschen.send_ppm(&ppm).unwrap_err();
+
+    assert!(
+        matches!(err, FtError::FrameTooLarge { size, .. } if size == ppm.len()),
+        "{:?}",
+        err
+    );
+    assert!(err.to_string().contains(&ppm.len().to_string()));
+    assert!(server.wait_for_datagrams(1, RECEIVE_TIMEOUT / 4).is_empty());
+}
+
+// other platforms may not report the ICMP port-unreachable message on the socket, see `FlaschenTaschen::probe`:
+#[cfg(target_os = "linux")]
+#[test]
+fn reconnects_without_waiting_for_the_backoff_while_the_server_restarts() {
+    use std::thread;
+    use std::time::Instant;
+
+    let server = MockFtServer::start().unwrap();
+    let address = server.address();
+    let mut flaschentaschen = server.flaschentaschen().unwrap();
+    flaschentaschen.max_reconnect_backoff = Some(Duration::from_secs(10));
+    let local_addr = flaschentaschen.local_addr().unwrap();
+
+    // the server answers the first send after it stopped with an ICMP message, which fails the following send:
+    server.stop();
+    let _ = flaschentaschen.send_ppm(&test_ppm());
+    thread::sleep(Duration::from_millis(50));
+    let started = Instant::now();
+    let _ = flaschentaschen.send_ppm(&test_ppm());
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert_ne!(
+        flaschentaschen.local_addr().unwrap(),
+        local_addr,
+        "the failed send did not reconnect"
+    );
+
+    let server = MockFtServer::start_on(&address.to_string()).unwrap();
+    flaschentaschen.send_ppm(&test_ppm()).unwrap();
+    assert_eq!(
+        server.wait_for_datagrams(1, RECEIVE_TIMEOUT),
+        vec![test_ppm()]
+    );
+}
+
+#[test]
+fn queries_the_screen_size() {
+    let server = MockFtServer::start_with_size_response(b"64x32").unwrap();
+    let flaschentaschen = server.flaschentaschen().unwrap();
+
+    assert_eq!(flaschentaschen.query_size().unwrap(), (64, 32));
+    assert!(server.received().is_empty());
+}
+
+#[test]
+fn parses_size_responses_separated_by_whitespace() {
+    let server = MockFtServer::start_with_size_response(b"45 35\n").unwrap();
+    let flaschentaschen = server.flaschentaschen().unwrap();
+
+    assert_eq!(flaschentaschen.query_size().unwrap(), (45, 35));
+}
+
+#[test]
+fn rejects_invalid_size_responses() {
+    for response in [&b"64x"[..], b"0x32", b"64x32x2", b"wide"] {
+        let server = MockFtServer::start_with_size_response(response).unwrap();
+        let flaschentaschen = server.flaschentaschen().unwrap();
+
+        assert!(
+            matches!(flaschentaschen.query_size(), Err(FtError::SizeQuery { .. })),
+            "{:?} was accepted",
+            String::from_utf8_lossy(response)
+        );
+    }
+}
+
+#[test]
+fn fails_to_query_the_size_of_servers_not_answering() {
+    let server = MockFtServer::start().unwrap();
+    let flaschentaschen = server.flaschentaschen().unwrap();
+
+    assert!(matches!(
+        flaschentaschen.query_size(),
+        Err(FtError::SizeQuery { .. })
+    ));
+}
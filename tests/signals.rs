@@ -0,0 +1,20 @@
+#![cfg(unix)]
+
+use flaschentaschen_web::{signal_action, SignalAction};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM, SIGUSR1};
+
+#[test]
+fn shuts_down_on_sigint_and_sigterm() {
+    assert_eq!(signal_action(SIGINT), SignalAction::Shutdown);
+    assert_eq!(signal_action(SIGTERM), SignalAction::Shutdown);
+}
+
+#[test]
+fn reloads_on_sighup() {
+    assert_eq!(signal_action(SIGHUP), SignalAction::Reload);
+}
+
+#[test]
+fn ignores_other_signals() {
+    assert_eq!(signal_action(SIGUSR1), SignalAction::Ignore);
+}
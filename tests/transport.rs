@@ -0,0 +1,115 @@
+// only the Unix domain socket test needs a temp path:
+#[cfg(unix)]
+mod common;
+
+use flaschentaschen_web::error::FtError;
+use flaschentaschen_web::{FlaschenTaschen, FtTransport};
+use std::io::Read;
+use std::net::TcpListener;
+
+#[test]
+fn parses_the_transport_of_endpoints() {
+    assert_eq!(
+        FtTransport::of("localhost:1337"),
+        (FtTransport::Udp, "localhost:1337")
+    );
+    assert_eq!(
+        FtTransport::of("unix:/run/ft-bridge.sock"),
+        (FtTransport::UnixDatagram, "/run/ft-bridge.sock")
+    );
+    assert_eq!(
+        FtTransport::of("tcp:localhost:1337"),
+        (FtTransport::Tcp, "localhost:1337")
+    );
+}
+
+#[test]
+fn streams_ppm_over_a_persistent_tcp_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let mut flaschentaschen = FlaschenTaschen::new(format!("tcp:{}", address)).unwrap();
+    // padding would corrupt the stream, it only applies to datagrams:
+    flaschentaschen.pad_datagram = Some(64);
+    let (mut server, _) = listener.accept().unwrap();
+    let first = b"P6\n1 1\n255\n\xff\x00\x00";
+    let second = b"P6\n1 1\n255\n\x00\xff\x00";
+    assert_eq!(flaschentaschen.send_ppm(first).unwrap(), first.len());
+    assert_eq!(flaschentaschen.send_ppm(second).unwrap(), second.len());
+    drop(flaschentaschen);
+
+    let mut received = Vec::new();
+    server.read_to_end(&mut received).unwrap();
+    assert_eq!(received, [&first[..], &second[..]].concat());
+    // both frames were sent over the same connection:
+    listener.set_nonblocking(true).unwrap();
+    assert!(listener.accept().is_err());
+}
+
+#[test]
+fn fails_to_connect_to_a_tcp_server_which_is_not_listening() {
+    // the port of a dropped listener is not listening anymore:
+    let address = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let result = FlaschenTaschen::new(format!("tcp:{}", address));
+
+    assert!(matches!(result, Err(FtError::ConnectionRefused { .. })));
+}
+
+#[test]
+fn does_not_connect_to_a_tcp_server_in_dry_runs() {
+    // the port of a dropped listener is not listening anymore:
+    let address = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let flaschentaschen = FlaschenTaschen::new_dry_run(format!("tcp:{}", address)).unwrap();
+    assert_eq!(flaschentaschen.transport(), FtTransport::Tcp);
+    let ppm = b"P6\n1 1\n255\n\xff\x00\x00";
+    assert!(flaschentaschen.send_ppm(ppm).is_ok());
+}
+
+// other platforms may not report the ICMP port-unreachable message on the socket, see `FlaschenTaschen::probe`:
+#[cfg(target_os = "linux")]
+#[test]
+fn detects_udp_servers_which_are_not_listening() {
+    use std::net::UdpSocket;
+
+    // the port of a dropped socket is not listening anymore:
+    let address = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let result = FlaschenTaschen::new_probed(address.to_string());
+
+    assert!(matches!(result, Err(FtError::ConnectionRefused { .. })));
+}
+
+#[cfg(unix)]
+#[test]
+fn sends_ppm_to_unix_domain_sockets() {
+    use std::os::unix::net::UnixDatagram;
+    use std::time::Duration;
+
+    let path = common::temp_path("ft.sock");
+    let _ = std::fs::remove_file(&path);
+    let server = UnixDatagram::bind(&path).unwrap();
+    server
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+
+    let flaschentaschen = FlaschenTaschen::new(format!("unix:{}", path.display())).unwrap();
+    let ppm = b"P6\n1 1\n255\n\xff\x00\x00";
+    flaschentaschen.send_ppm(ppm).unwrap();
+
+    let mut buffer = [0u8; 64];
+    let len = server.recv(&mut buffer).unwrap();
+    assert_eq!(&buffer[..len], &ppm[..]);
+    assert_eq!(flaschentaschen.transport(), FtTransport::UnixDatagram);
+    std::fs::remove_file(&path).unwrap();
+}